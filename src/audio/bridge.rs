@@ -0,0 +1,359 @@
+//! Audio→inference bridge: drains the capture feeds, mixes them (or keeps them apart per
+//! source), resamples to 16kHz mono, and hands 160ms chunks to the inference thread(s).
+
+use super::mixer::Mixer;
+use super::resampler::AudioResampler;
+use super::vad::Vad;
+use super::SharedFeeds;
+use crate::config::{AudioSource, SourceConfig, VadAggressiveness};
+use crate::stt::{AudioChunk, Caption, EngineFactory, EngineInput, SourceTag, SttEngine};
+use anyhow::Result;
+use ringbuf::traits::Consumer;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How the bridge routes captured audio to inference.
+pub enum BridgeMode {
+    /// Mix every source into one stream for a single inference thread.
+    /// The sender sits behind a mutex so engine switching can swap it at runtime.
+    Mixed(Arc<Mutex<SyncSender<EngineInput>>>),
+    /// Give every source its own resampler and engine; captions are tagged with the source label.
+    PerSource {
+        engine_factory: EngineFactory,
+        caption_tx: SyncSender<Caption>,
+    },
+}
+
+/// Spawn the bridge thread. It runs until `shutdown` is set.
+///
+/// When `captions_enabled` is false the ring buffers are still drained, but resampling and
+/// inference are skipped to save CPU/GPU. The model stays loaded.
+///
+/// Chunks are stamped with their offset from `epoch`, the start of the session timeline,
+/// and pass through voice activity detection at the `vad` level before reaching an engine.
+pub fn spawn_bridge(
+    feeds: SharedFeeds,
+    mode: BridgeMode,
+    captions_enabled: Arc<AtomicBool>,
+    shutdown: Arc<AtomicBool>,
+    epoch: Instant,
+    vad: VadAggressiveness,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || match mode {
+        BridgeMode::Mixed(chunk_tx) => run_mixed(feeds, chunk_tx, captions_enabled, shutdown, epoch, vad),
+        BridgeMode::PerSource { engine_factory, caption_tx } => {
+            run_per_source(feeds, engine_factory, caption_tx, captions_enabled, shutdown, epoch, vad)
+        }
+    })
+}
+
+/// Stamps outgoing chunks with their session offset. While audio flows continuously the
+/// offset counts resampled samples exactly; after a gap (captions paused, streams rebuilt)
+/// it re-anchors to the wall clock, so every source shares one timeline.
+struct Timeline {
+    epoch: Instant,
+    next: Option<u64>,
+}
+
+impl Timeline {
+    fn new(epoch: Instant) -> Self {
+        Timeline { epoch, next: None }
+    }
+
+    fn stamp(&mut self, pcm: Vec<f32>) -> AudioChunk {
+        let start_sample = *self.next.get_or_insert_with(|| crate::stt::session_position(self.epoch));
+        self.next = Some(start_sample + pcm.len() as u64);
+        AudioChunk { start_sample, pcm }
+    }
+
+    /// Audio was skipped: the next chunk is not contiguous with the last one.
+    fn interrupt(&mut self) {
+        self.next = None;
+    }
+}
+
+/// Mixed mode: sum all feeds and send chunks to the shared chunk sender.
+/// Locks `chunk_tx` on each send so engine switching can atomically swap the inner SyncSender.
+fn run_mixed(
+    feeds: SharedFeeds,
+    chunk_tx: Arc<Mutex<SyncSender<EngineInput>>>,
+    captions_enabled: Arc<AtomicBool>,
+    shutdown: Arc<AtomicBool>,
+    epoch: Instant,
+    vad_level: VadAggressiveness,
+) {
+    let mut resampler = AudioResampler::new().expect("creating resampler");
+    let mut timeline = Timeline::new(epoch);
+    let mut vad = Vad::new(vad_level);
+    let mut mixer = Mixer::new(Vec::new());
+    let mut feed_generation = 0u64;
+    let mut raw = vec![0f32; 4096];
+    while !shutdown.load(Ordering::Relaxed) {
+        {
+            let mut feed_set = feeds.lock().unwrap();
+            // The PipeWire thread rebuilt its streams: start a fresh mix.
+            if feed_set.generation != feed_generation {
+                feed_generation = feed_set.generation;
+                mixer = Mixer::new(feed_set.feeds.iter().map(|f| f.config.gain).collect());
+                timeline.interrupt();
+            }
+            for (i, feed) in feed_set.feeds.iter_mut().enumerate() {
+                loop {
+                    let n = feed.consumer.pop_slice(&mut raw);
+                    if n == 0 {
+                        break;
+                    }
+                    mixer.push(i, &raw[..n]);
+                }
+            }
+        }
+        let mixed = mixer.pull();
+        if !captions_enabled.load(Ordering::Relaxed) {
+            timeline.interrupt();
+        } else if !mixed.is_empty() {
+            match resampler.push_interleaved(&mixed) {
+                Ok(chunks) => {
+                    let inputs = chunks.into_iter().flat_map(|chunk| vad.process(timeline.stamp(chunk)));
+                    for input in inputs {
+                        let tx = chunk_tx.lock().unwrap();
+                        if tx.send(input).is_err() {
+                            drop(tx); // release lock before sleep
+                            thread::sleep(Duration::from_millis(10));
+                            break; // engine switching — wait for new tx
+                        }
+                    }
+                }
+                Err(e) => {
+                    eprintln!("warn: resampler error: {e}");
+                }
+            }
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+/// Builds engines on a helper thread, so the bridge keeps draining every feed while a model
+/// loads. Requests are answered in order, each tagged with the ID `request` returned.
+struct EngineLoader {
+    requests: mpsc::Sender<u64>,
+    loaded: mpsc::Receiver<(u64, Result<Box<dyn SttEngine>>)>,
+    next_id: u64,
+}
+
+impl EngineLoader {
+    fn spawn(engine_factory: EngineFactory) -> Self {
+        let (requests, request_rx) = mpsc::channel::<u64>();
+        let (loaded_tx, loaded) = mpsc::channel();
+        thread::Builder::new()
+            .name("engine-loader".to_string())
+            .spawn(move || {
+                for id in request_rx.iter() {
+                    if loaded_tx.send((id, engine_factory())).is_err() {
+                        break; // bridge gone — shutdown
+                    }
+                }
+            })
+            .expect("spawning engine loader thread");
+        EngineLoader { requests, loaded, next_id: 0 }
+    }
+
+    fn request(&mut self) -> u64 {
+        self.next_id += 1;
+        // Fails only if the loader thread died, in which case the source stays silent.
+        let _ = self.requests.send(self.next_id);
+        self.next_id
+    }
+}
+
+/// A pipeline's recognizer: still loading, or running on its inference thread.
+enum Recognizer {
+    Loading(u64),
+    Running {
+        chunk_tx: SyncSender<EngineInput>,
+        _handle: thread::JoinHandle<()>,
+    },
+}
+
+/// Resampler and inference thread serving one source in per-source mode.
+struct SourcePipeline {
+    /// Identifies the source across feed rebuilds, so its engine is not reloaded when only
+    /// the node ID changes. Compared with `AudioSource::same_source`.
+    source: AudioSource,
+    tag: SourceTag,
+    /// Single-input mixer; applies the source's gain.
+    mixer: Mixer,
+    resampler: AudioResampler,
+    timeline: Timeline,
+    vad: Vad,
+    recognizer: Recognizer,
+    /// Chunks dropped since the recognizer fell behind (0 while it keeps up).
+    dropped: u64,
+}
+
+impl SourcePipeline {
+    fn new(config: &SourceConfig, loader: &mut EngineLoader, epoch: Instant, vad: VadAggressiveness) -> Result<Self> {
+        Ok(SourcePipeline {
+            source: config.source.clone(),
+            tag: source_tag(config),
+            mixer: Mixer::new(vec![config.gain]),
+            resampler: AudioResampler::new()?,
+            timeline: Timeline::new(epoch),
+            vad: Vad::new(vad),
+            recognizer: Recognizer::Loading(loader.request()),
+            dropped: 0,
+        })
+    }
+
+    fn serves(&self, config: &SourceConfig) -> bool {
+        self.source.same_source(&config.source) && self.tag == source_tag(config)
+    }
+
+    /// Hand a chunk to the recognizer without blocking: the other sources share this thread.
+    /// Returns false once the inference thread has gone.
+    fn send(&mut self, input: EngineInput) -> bool {
+        let Recognizer::Running { chunk_tx, .. } = &self.recognizer else {
+            return true;
+        };
+        match chunk_tx.try_send(input) {
+            Ok(()) => {
+                if self.dropped > 0 {
+                    eprintln!(
+                        "info: recognizer for '{}' caught up after dropping {} chunks",
+                        self.tag.label, self.dropped
+                    );
+                    self.dropped = 0;
+                }
+                true
+            }
+            Err(TrySendError::Full(_)) => {
+                if self.dropped == 0 {
+                    eprintln!("warn: recognizer for '{}' is falling behind; dropping audio", self.tag.label);
+                }
+                self.dropped += 1;
+                true
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        }
+    }
+}
+
+fn source_tag(config: &SourceConfig) -> SourceTag {
+    SourceTag {
+        label: config.display_label(),
+        color: config.color.clone(),
+    }
+}
+
+/// Per-source mode: one pipeline per feed, index-aligned with the current `FeedSet`.
+fn run_per_source(
+    feeds: SharedFeeds,
+    engine_factory: EngineFactory,
+    caption_tx: SyncSender<Caption>,
+    captions_enabled: Arc<AtomicBool>,
+    shutdown: Arc<AtomicBool>,
+    epoch: Instant,
+    vad: VadAggressiveness,
+) {
+    let mut loader = EngineLoader::spawn(engine_factory);
+    let mut pipelines: Vec<Option<SourcePipeline>> = Vec::new();
+    let mut feed_generation = 0u64;
+    let mut raw = vec![0f32; 4096];
+    while !shutdown.load(Ordering::Relaxed) {
+        {
+            let mut feed_set = feeds.lock().unwrap();
+            // Rebuilding is cheap now that engines load on the loader thread, so it happens
+            // under the feed lock and the pipelines always line up with the feeds.
+            if feed_set.generation != feed_generation {
+                feed_generation = feed_set.generation;
+                let mut old: Vec<SourcePipeline> = pipelines.drain(..).flatten().collect();
+                for feed in &feed_set.feeds {
+                    let config = &feed.config;
+                    let reused = old.iter().position(|p| p.serves(config)).map(|i| old.swap_remove(i));
+                    let pipeline = match reused {
+                        Some(mut p) => {
+                            // Pending samples belong to the old ring buffer; start clean.
+                            p.mixer = Mixer::new(vec![config.gain]);
+                            p.timeline.interrupt();
+                            Some(p)
+                        }
+                        None => match SourcePipeline::new(config, &mut loader, epoch, vad) {
+                            Ok(p) => Some(p),
+                            Err(e) => {
+                                eprintln!("warn: failed to start recognizer for '{}': {e:#}", config.display_label());
+                                None
+                            }
+                        },
+                    };
+                    pipelines.push(pipeline);
+                }
+                // Pipelines left in `old` are dropped here, closing their chunk channels,
+                // which ends their inference threads.
+            }
+            for (feed, pipeline) in feed_set.feeds.iter_mut().zip(pipelines.iter_mut()) {
+                loop {
+                    let n = feed.consumer.pop_slice(&mut raw);
+                    if n == 0 {
+                        break;
+                    }
+                    if let Some(p) = pipeline {
+                        p.mixer.push(0, &raw[..n]);
+                    }
+                }
+            }
+        }
+
+        // Start the recognizers whose engines have loaded. An engine whose source went away
+        // while it loaded is simply dropped.
+        while let Ok((id, engine)) = loader.loaded.try_recv() {
+            let Some(slot) = pipelines
+                .iter_mut()
+                .find(|slot| slot.as_ref().is_some_and(|p| matches!(p.recognizer, Recognizer::Loading(l) if l == id)))
+            else {
+                continue;
+            };
+            match engine {
+                Ok(engine) => {
+                    let p = slot.as_mut().expect("found above");
+                    let (chunk_tx, chunk_rx) = mpsc::sync_channel::<EngineInput>(32);
+                    let handle =
+                        crate::stt::spawn_inference_thread(engine, chunk_rx, caption_tx.clone(), Some(p.tag.clone()));
+                    p.recognizer = Recognizer::Running { chunk_tx, _handle: handle };
+                }
+                Err(e) => {
+                    if let Some(p) = slot.take() {
+                        eprintln!("warn: failed to start recognizer for '{}': {e:#}", p.tag.label);
+                    }
+                }
+            }
+        }
+
+        for p in pipelines.iter_mut().flatten() {
+            let samples = p.mixer.pull();
+            if !captions_enabled.load(Ordering::Relaxed) || matches!(p.recognizer, Recognizer::Loading(_)) {
+                p.timeline.interrupt();
+                continue;
+            }
+            if samples.is_empty() {
+                continue;
+            }
+            match p.resampler.push_interleaved(&samples) {
+                Ok(chunks) => {
+                    let inputs: Vec<EngineInput> =
+                        chunks.into_iter().flat_map(|chunk| p.vad.process(p.timeline.stamp(chunk))).collect();
+                    for input in inputs {
+                        if !p.send(input) {
+                            break;
+                        }
+                    }
+                }
+                Err(e) => {
+                    eprintln!("warn: resampler error: {e}");
+                }
+            }
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+}
@@ -0,0 +1,217 @@
+//! Audio file decoding for offline transcription: WAV, FLAC and Ogg Vorbis via symphonia,
+//! or headerless raw PCM at a caller-supplied rate and channel count.
+
+use anyhow::{bail, Context, Result};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{Decoder, DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+/// Frames read per block from raw PCM files.
+const RAW_BLOCK_FRAMES: usize = 4096;
+
+/// Sample encoding of a raw PCM file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum RawFormat {
+    /// Signed 16-bit little-endian
+    S16le,
+    /// 32-bit float little-endian
+    F32le,
+}
+
+impl RawFormat {
+    fn bytes_per_sample(self) -> usize {
+        match self {
+            RawFormat::S16le => 2,
+            RawFormat::F32le => 4,
+        }
+    }
+}
+
+/// Layout of a raw PCM file, which carries no header to read it from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawSpec {
+    pub sample_rate: u32,
+    pub channels: usize,
+    pub format: RawFormat,
+}
+
+enum Source {
+    Decoded {
+        format: Box<dyn FormatReader>,
+        decoder: Box<dyn Decoder>,
+        track_id: u32,
+    },
+    Raw {
+        reader: BufReader<File>,
+        format: RawFormat,
+    },
+}
+
+/// An open audio file, read as blocks of interleaved f32 samples.
+pub struct AudioFile {
+    pub sample_rate: u32,
+    pub channels: usize,
+    source: Source,
+}
+
+impl AudioFile {
+    /// Open `path`, decoding it as raw PCM when `raw` is given and probing its container
+    /// format otherwise.
+    pub fn open(path: &Path, raw: Option<RawSpec>) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("opening {}", path.display()))?;
+        if let Some(spec) = raw {
+            if spec.sample_rate == 0 || spec.channels == 0 {
+                bail!("raw PCM needs a non-zero sample rate and channel count");
+            }
+            return Ok(AudioFile {
+                sample_rate: spec.sample_rate,
+                channels: spec.channels,
+                source: Source::Raw {
+                    reader: BufReader::new(file),
+                    format: spec.format,
+                },
+            });
+        }
+
+        let stream = MediaSourceStream::new(Box::new(file), Default::default());
+        let mut hint = Hint::new();
+        if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+            hint.with_extension(ext);
+        }
+        let probed = symphonia::default::get_probe()
+            .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+            .with_context(|| format!("unsupported or unreadable audio file {}", path.display()))?;
+        let format = probed.format;
+
+        let track = format
+            .tracks()
+            .iter()
+            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+            .context("file has no audio track")?;
+        let sample_rate = track.codec_params.sample_rate.context("audio track has no sample rate")?;
+        let channels = track
+            .codec_params
+            .channels
+            .map(|c| c.count())
+            .context("audio track has no channel layout")?;
+        let track_id = track.id;
+        let decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &DecoderOptions::default())
+            .context("creating audio decoder")?;
+
+        Ok(AudioFile {
+            sample_rate,
+            channels,
+            source: Source::Decoded { format, decoder, track_id },
+        })
+    }
+
+    /// Next block of interleaved samples, or None at the end of the file.
+    pub fn next_block(&mut self) -> Result<Option<Vec<f32>>> {
+        match &mut self.source {
+            Source::Decoded { format, decoder, track_id } => loop {
+                let packet = match format.next_packet() {
+                    Ok(p) => p,
+                    Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                        return Ok(None);
+                    }
+                    Err(e) => return Err(e).context("reading audio packet"),
+                };
+                if packet.track_id() != *track_id {
+                    continue;
+                }
+                match decoder.decode(&packet) {
+                    Ok(decoded) => {
+                        let mut buf = SampleBuffer::<f32>::new(decoded.capacity() as u64, *decoded.spec());
+                        buf.copy_interleaved_ref(decoded);
+                        return Ok(Some(buf.samples().to_vec()));
+                    }
+                    Err(SymphoniaError::DecodeError(e)) => {
+                        eprintln!("warn: skipping undecodable audio packet: {e}");
+                    }
+                    Err(e) => return Err(e).context("decoding audio"),
+                }
+            },
+            Source::Raw { reader, format } => {
+                let frame_bytes = format.bytes_per_sample() * self.channels;
+                let mut bytes = vec![0u8; RAW_BLOCK_FRAMES * frame_bytes];
+                let n = read_full(reader, &mut bytes).context("reading raw PCM")?;
+                // A trailing partial frame cannot be played back; drop it.
+                let n = n - n % frame_bytes;
+                if n == 0 {
+                    return Ok(None);
+                }
+                Ok(Some(decode_raw(&bytes[..n], *format)))
+            }
+        }
+    }
+}
+
+/// Read until `buf` is full or the reader is exhausted. Returns the number of bytes read.
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+fn decode_raw(bytes: &[u8], format: RawFormat) -> Vec<f32> {
+    match format {
+        RawFormat::S16le => bytes
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32_768.0)
+            .collect(),
+        RawFormat::F32le => bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn raw_s16_file_is_read_in_blocks() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        let samples: Vec<i16> = (0..RAW_BLOCK_FRAMES as i16 * 3).collect();
+        for s in &samples {
+            file.write_all(&s.to_le_bytes()).unwrap();
+        }
+        // Trailing odd byte: half a frame, ignored.
+        file.write_all(&[0x7f]).unwrap();
+        file.flush().unwrap();
+
+        let spec = RawSpec { sample_rate: 16_000, channels: 2, format: RawFormat::S16le };
+        let mut audio = AudioFile::open(file.path(), Some(spec)).unwrap();
+        assert_eq!((audio.sample_rate, audio.channels), (16_000, 2));
+        let mut decoded = Vec::new();
+        while let Some(block) = audio.next_block().unwrap() {
+            assert_eq!(block.len() % 2, 0, "blocks hold whole frames");
+            decoded.extend(block);
+        }
+        assert_eq!(decoded.len(), samples.len());
+        assert_eq!(decoded[1], 1.0 / 32_768.0);
+    }
+
+    #[test]
+    fn raw_f32_samples_decode_unchanged() {
+        let bytes: Vec<u8> = [0.5f32, -0.25].iter().flat_map(|s| s.to_le_bytes()).collect();
+        assert_eq!(decode_raw(&bytes, RawFormat::F32le), vec![0.5, -0.25]);
+    }
+}
@@ -0,0 +1,174 @@
+//! Auto-follow: picks the application stream that is currently producing sound.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// RMS level above which a stream counts as producing signal (about -60 dBFS).
+pub const SIGNAL_THRESHOLD: f32 = 0.001;
+
+/// How long a stream still counts as active after its last signal, so that the
+/// pauses between words do not make a speaker look silent.
+const ACTIVITY_WINDOW: Duration = Duration::from_secs(1);
+
+/// Decides which application stream to follow from periodic level measurements.
+///
+/// The followed stream is kept while it is active. Once it falls silent, the loudest
+/// active stream becomes a candidate and is followed after it has stayed the candidate
+/// for the hold time, so short sounds (notifications, UI clicks) do not steal capture.
+pub struct AutoFollow {
+    hold: Duration,
+    followed: Option<u32>,
+    candidate: Option<(u32, Instant)>,
+    /// Per node: when it last produced signal, and how loud it was.
+    signal: HashMap<u32, (Instant, f32)>,
+}
+
+impl AutoFollow {
+    pub fn new(hold: Duration) -> Self {
+        AutoFollow {
+            hold,
+            followed: None,
+            candidate: None,
+            signal: HashMap::new(),
+        }
+    }
+
+    /// Node ID currently being followed.
+    pub fn followed(&self) -> Option<u32> {
+        self.followed
+    }
+
+    pub fn set_hold(&mut self, hold: Duration) {
+        self.hold = hold;
+    }
+
+    /// Record the level measured for a node since the last call.
+    pub fn observe(&mut self, node_id: u32, level: f32, now: Instant) {
+        if level > SIGNAL_THRESHOLD {
+            self.signal.insert(node_id, (now, level));
+        }
+    }
+
+    /// Drop a node that disappeared. Returns true if it was the followed one.
+    pub fn forget(&mut self, node_id: u32) -> bool {
+        self.signal.remove(&node_id);
+        if self.candidate.is_some_and(|(id, _)| id == node_id) {
+            self.candidate = None;
+        }
+        if self.followed == Some(node_id) {
+            self.followed = None;
+            return true;
+        }
+        false
+    }
+
+    /// Re-evaluate which node to follow. Returns true if the followed node changed.
+    pub fn update(&mut self, now: Instant) -> bool {
+        let is_active = |since: &Instant| now.duration_since(*since) < ACTIVITY_WINDOW;
+        if self.followed.is_some_and(|id| self.signal.get(&id).is_some_and(|(t, _)| is_active(t))) {
+            self.candidate = None;
+            return false;
+        }
+
+        let loudest = self
+            .signal
+            .iter()
+            .filter(|(_, (t, _))| is_active(t))
+            .max_by(|a, b| a.1 .1.total_cmp(&b.1 .1))
+            .map(|(id, _)| *id);
+        let Some(id) = loudest else {
+            // Nothing is playing: keep following the last stream.
+            self.candidate = None;
+            return false;
+        };
+        if self.followed.is_none() {
+            self.followed = Some(id);
+            self.candidate = None;
+            return true;
+        }
+        match self.candidate {
+            Some((candidate, since)) if candidate == id => {
+                if now.duration_since(since) >= self.hold {
+                    self.followed = Some(id);
+                    self.candidate = None;
+                    return true;
+                }
+            }
+            _ => self.candidate = Some((id, now)),
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOLD: Duration = Duration::from_secs(2);
+
+    #[test]
+    fn first_active_stream_is_followed_immediately() {
+        let mut f = AutoFollow::new(HOLD);
+        let t0 = Instant::now();
+        f.observe(7, 0.1, t0);
+        assert!(f.update(t0));
+        assert_eq!(f.followed(), Some(7));
+    }
+
+    #[test]
+    fn switch_waits_for_hold_time() {
+        let mut f = AutoFollow::new(HOLD);
+        let t0 = Instant::now();
+        f.observe(7, 0.1, t0);
+        f.update(t0);
+
+        // 7 falls silent, 9 starts playing.
+        let t1 = t0 + Duration::from_secs(2);
+        f.observe(9, 0.1, t1);
+        assert!(!f.update(t1), "must not switch before the hold time");
+        let t2 = t1 + Duration::from_millis(1500);
+        f.observe(9, 0.1, t2);
+        assert!(!f.update(t2));
+        let t3 = t1 + HOLD;
+        f.observe(9, 0.1, t3);
+        assert!(f.update(t3));
+        assert_eq!(f.followed(), Some(9));
+    }
+
+    #[test]
+    fn active_followed_stream_is_kept() {
+        let mut f = AutoFollow::new(HOLD);
+        let t0 = Instant::now();
+        f.observe(7, 0.01, t0);
+        f.update(t0);
+        for step in 1..10 {
+            let t = t0 + Duration::from_millis(500 * step);
+            f.observe(7, 0.01, t);
+            f.observe(9, 0.5, t);
+            assert!(!f.update(t), "louder stream must not steal an active one");
+        }
+        assert_eq!(f.followed(), Some(7));
+    }
+
+    #[test]
+    fn silence_keeps_last_followed_stream() {
+        let mut f = AutoFollow::new(HOLD);
+        let t0 = Instant::now();
+        f.observe(7, 0.1, t0);
+        f.update(t0);
+        f.observe(9, 0.0, t0 + Duration::from_secs(5));
+        assert!(!f.update(t0 + Duration::from_secs(5)));
+        assert_eq!(f.followed(), Some(7));
+    }
+
+    #[test]
+    fn forgetting_followed_stream_clears_it() {
+        let mut f = AutoFollow::new(HOLD);
+        let t0 = Instant::now();
+        f.observe(7, 0.1, t0);
+        f.update(t0);
+        assert!(f.forget(7));
+        assert_eq!(f.followed(), None);
+        assert!(!f.forget(9));
+    }
+}
@@ -0,0 +1,106 @@
+//! Audio mixer: sums several interleaved 48kHz stereo feeds into one, with per-feed gain.
+
+use std::collections::VecDeque;
+
+/// Maximum backlog (in interleaved samples) the leading feed may build up over a lagging one
+/// before the lagging feed is treated as silent: 100ms of 48kHz stereo.
+///
+/// PipeWire drives all capture streams from the same graph clock, so active feeds stay within
+/// one quantum of each other. A feed that stops delivering buffers (e.g. a paused application)
+/// must not stall the rest of the mix indefinitely.
+pub const MAX_SKEW_SAMPLES: usize = 48_000 * 2 / 10;
+
+/// Mixes several interleaved stereo feeds sample-by-sample.
+///
+/// Each feed is pushed independently as its ring buffer is drained. `pull` returns the
+/// span that every feed has delivered, so samples from different sources stay aligned.
+pub struct Mixer {
+    pending: Vec<VecDeque<f32>>,
+    gains: Vec<f32>,
+}
+
+impl Mixer {
+    /// Create a mixer with one input per gain value.
+    pub fn new(gains: Vec<f32>) -> Self {
+        Mixer {
+            pending: gains.iter().map(|_| VecDeque::new()).collect(),
+            gains,
+        }
+    }
+
+    /// Queue interleaved stereo samples for the given input.
+    pub fn push(&mut self, input: usize, samples: &[f32]) {
+        if let Some(queue) = self.pending.get_mut(input) {
+            queue.extend(samples);
+        }
+    }
+
+    /// Mix and return all samples available from every input.
+    ///
+    /// When one input runs more than `MAX_SKEW_SAMPLES` ahead of another, the lagging
+    /// input is zero-padded so the leading one is not held back.
+    pub fn pull(&mut self) -> Vec<f32> {
+        let ready = self.pending.iter().map(VecDeque::len).min().unwrap_or(0);
+        let lead = self.pending.iter().map(VecDeque::len).max().unwrap_or(0);
+        let n = if lead - ready > MAX_SKEW_SAMPLES { lead } else { ready };
+        // Keep whole stereo frames so channels never swap.
+        let n = n - n % 2;
+        if n == 0 {
+            return Vec::new();
+        }
+
+        let mut out = vec![0.0f32; n];
+        for (queue, gain) in self.pending.iter_mut().zip(&self.gains) {
+            let take = n.min(queue.len());
+            for (o, s) in out.iter_mut().zip(queue.drain(..take)) {
+                *o += s * gain;
+            }
+        }
+        for o in &mut out {
+            *o = o.clamp(-1.0, 1.0);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_input_applies_gain() {
+        let mut m = Mixer::new(vec![0.5]);
+        m.push(0, &[0.4, -0.4, 0.2, 0.2]);
+        assert_eq!(m.pull(), vec![0.2, -0.2, 0.1, 0.1]);
+        assert!(m.pull().is_empty(), "all samples should have been consumed");
+    }
+
+    #[test]
+    fn mixes_only_span_available_from_all_inputs() {
+        let mut m = Mixer::new(vec![1.0, 1.0]);
+        m.push(0, &[0.1, 0.1, 0.1, 0.1]);
+        m.push(1, &[0.2, 0.2]);
+        let out = m.pull();
+        assert_eq!(out.len(), 2);
+        assert!((out[0] - 0.3).abs() < 1e-6);
+        // The remaining frame of input 0 waits for input 1.
+        m.push(1, &[0.2, 0.2]);
+        assert_eq!(m.pull().len(), 2);
+    }
+
+    #[test]
+    fn silent_input_does_not_stall_mix() {
+        let mut m = Mixer::new(vec![1.0, 1.0]);
+        m.push(0, &vec![0.1; MAX_SKEW_SAMPLES + 2]);
+        let out = m.pull();
+        assert_eq!(out.len(), MAX_SKEW_SAMPLES + 2, "lagging input should be zero-padded");
+    }
+
+    #[test]
+    fn output_is_clamped() {
+        let mut m = Mixer::new(vec![1.0, 1.0]);
+        m.push(0, &[0.8, -0.8]);
+        m.push(1, &[0.8, -0.8]);
+        assert_eq!(m.pull(), vec![1.0, -1.0]);
+    }
+}
@@ -2,7 +2,12 @@
 
 #![allow(dead_code)]
 
+pub mod bridge;
+pub mod file;
+pub mod follow;
+pub mod mixer;
 pub mod resampler;
+pub mod vad;
 
 use anyhow::Context;
 use anyhow::Result;
@@ -10,33 +15,128 @@ use pipewire as pw;
 use pw::properties::properties;
 use ringbuf::HeapRb;
 use ringbuf::traits::{Producer, Split};
+use crate::config::{AudioSource, SourceConfig};
 use std::sync::{Arc, Mutex};
 use std::thread;
 
-/// A discovered PipeWire audio node (sink or application stream).
+/// What kind of PipeWire node an `AudioNode` is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NodeKind {
+    /// Monitor port of a system sink (`Audio/Source` ending in `.monitor`).
+    Monitor,
+    /// Application output stream (`Stream/Output/Audio`).
+    Application,
+    /// Capture device such as a microphone (`Audio/Source`, not a monitor).
+    Input,
+}
+
+/// A discovered PipeWire audio node (sink monitor, application stream or capture device).
 #[derive(Debug, Clone)]
 pub struct AudioNode {
     pub node_id: u32,
     pub name: String,
     pub description: String,
-    /// true = system sink/monitor; false = application output stream
-    pub is_monitor: bool,
+    pub kind: NodeKind,
+    /// `application.name` property, if set.
+    pub app_name: Option<String>,
+    /// `application.process.binary` property, if set.
+    pub binary: Option<String>,
+}
+
+impl AudioNode {
+    /// Build the AudioSource that captures from this node.
+    pub fn to_source(&self) -> crate::config::AudioSource {
+        match self.kind {
+            NodeKind::Input => crate::config::AudioSource::Input {
+                node_id: self.node_id,
+                node_name: self.name.clone(),
+            },
+            NodeKind::Monitor | NodeKind::Application => crate::config::AudioSource::Application {
+                node_id: self.node_id,
+                node_name: self.name.clone(),
+                app_name: self.app_name.clone(),
+                binary: self.binary.clone(),
+            },
+        }
+    }
+
+    /// Whether this node is the source's stable identity (ignoring the node ID hint).
+    ///
+    /// Input devices are matched by node name. Applications are matched on application name,
+    /// process binary and node name: every property known on both sides must agree, and at
+    /// least one must be known.
+    pub fn matches(&self, source: &AudioSource) -> bool {
+        match source {
+            AudioSource::SystemOutput | AudioSource::Auto => false,
+            AudioSource::Input { node_name, .. } => self.kind == NodeKind::Input && self.name == *node_name,
+            AudioSource::Application { node_name, app_name, binary, .. } => {
+                if self.kind == NodeKind::Input {
+                    return false;
+                }
+                let name = (!node_name.is_empty()).then_some(node_name.as_str());
+                let pairs = [
+                    (app_name.as_deref(), self.app_name.as_deref()),
+                    (binary.as_deref(), self.binary.as_deref()),
+                    (name, (!self.name.is_empty()).then_some(self.name.as_str())),
+                ];
+                let mut known = pairs.iter().filter_map(|(a, b)| a.zip(*b)).peekable();
+                known.peek().is_some() && known.all(|(a, b)| a == b)
+            }
+        }
+    }
 }
 
 /// Commands sent to the PipeWire thread for runtime control.
 pub enum AudioCommand {
-    /// Switch to a new audio source.
-    SwitchSource(crate::config::AudioSource),
+    /// Replace the set of captured (and mixed) audio sources.
+    SetSources(Vec<SourceConfig>),
+    /// Change how long an `Auto` source waits before switching to another application.
+    SetAutoFollowHold(std::time::Duration),
     /// Shut down the PipeWire thread.
     Shutdown,
 }
 
-/// Event sent when an audio node disappears and we fall back to system output (AC1.4).
-pub struct FallbackEvent {
-    pub lost_name: String,
-    pub lost_id: u32,
+/// Capture changes reported by the PipeWire thread (AC1.4).
+/// `sources` is the configured source list with refreshed node ID hints, for persisting.
+pub enum SourceEvent {
+    /// A captured node disappeared. Its source stays configured and is reattached when a
+    /// matching node reappears; `fallback` is set when system output is captured meanwhile.
+    Lost {
+        name: String,
+        node_id: u32,
+        fallback: bool,
+        sources: Vec<SourceConfig>,
+    },
+    /// A matching node reappeared (usually under a new ID) and capture resumed on it.
+    Reattached {
+        name: String,
+        node_id: u32,
+        sources: Vec<SourceConfig>,
+    },
+    /// An `Auto` source switched to another application (None: nothing followed yet).
+    Following { description: Option<String> },
+    /// The default output device changed and system output capture moved to it.
+    DefaultSinkChanged { sink: String },
+}
+
+/// Ring buffer of one captured source, drained by the audio bridge thread.
+pub struct SourceFeed {
+    /// The source entry (gain, label) this feed was opened for.
+    pub config: SourceConfig,
+    pub consumer: ringbuf::HeapCons<f32>,
 }
 
+/// Feeds for the sources currently being captured.
+/// `generation` is bumped whenever the set is rebuilt so the bridge can reset its mixer.
+#[derive(Default)]
+pub struct FeedSet {
+    pub generation: u64,
+    pub feeds: Vec<SourceFeed>,
+}
+
+/// Feed set shared between the PipeWire thread (writer) and the audio bridge (reader).
+pub type SharedFeeds = Arc<Mutex<FeedSet>>;
+
 /// Shared list of discovered audio nodes (updated by registry callbacks).
 pub type NodeList = Arc<Mutex<Vec<AudioNode>>>;
 
@@ -48,6 +148,34 @@ struct CaptureStream<'a> {
     _listener: Box<dyn std::any::Any>,
 }
 
+/// Level meter on one application stream, used to decide what an `Auto` source follows.
+struct LevelMeter<'a> {
+    node_id: u32,
+    /// Highest buffer RMS since the last read, as f32 bits (non-negative floats order like u32).
+    level: Arc<std::sync::atomic::AtomicU32>,
+    _capture: CaptureStream<'a>,
+}
+
+impl LevelMeter<'_> {
+    /// Return the peak level since the last call and reset it.
+    fn take_level(&self) -> f32 {
+        f32::from_bits(self.level.swap(0, std::sync::atomic::Ordering::Relaxed))
+    }
+}
+
+/// Metadata key holding the default output device on the `default` metadata object.
+const DEFAULT_SINK_KEY: &str = "default.audio.sink";
+
+/// Extract the node name from a `default` metadata value such as `{ "name": "alsa_output.usb" }`.
+fn parse_metadata_name(value: &str) -> Option<String> {
+    let rest = &value[value.find("\"name\"")? + "\"name\"".len()..];
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start().strip_prefix('"')?;
+    Some(rest[..rest.find('"')?].to_string())
+}
+
+/// How often the level meters are read for auto-follow.
+const METER_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
 /// Ring buffer capacity: 1 second of 48kHz stereo f32 samples.
 /// HeapRb<f32> counts f32 elements, not bytes — so 48000 frames × 2 channels = 96_000 elements.
 const RING_BUF_CAPACITY: usize = 48_000 * 2;
@@ -56,18 +184,19 @@ const RING_BUF_CAPACITY: usize = 48_000 * 2;
 ///
 /// Returns:
 /// - `tx_cmd`: send AudioCommand to the PipeWire thread
-/// - `rx_audio`: receive raw interleaved stereo 48kHz f32 samples (drained by inference thread)
+/// - `feeds`: per-source rings of raw interleaved stereo 48kHz f32 samples (drained by the bridge)
 /// - `node_list`: shared list of available audio nodes (updated by registry)
-/// - `fallback_rx`: receive FallbackEvent when a captured node disappears (AC1.4)
+/// - `event_rx`: receive SourceEvent when a captured node disappears or returns (AC1.4)
 ///
 /// Exits the process if PipeWire is unavailable (AC1.5).
 pub fn start_audio_thread(
-    initial_source: crate::config::AudioSource,
+    initial_sources: Vec<SourceConfig>,
+    auto_follow_hold: std::time::Duration,
 ) -> Result<(
     std::sync::mpsc::SyncSender<AudioCommand>,
-    ringbuf::HeapCons<f32>,
+    SharedFeeds,
     NodeList,
-    std::sync::mpsc::Receiver<FallbackEvent>,
+    std::sync::mpsc::Receiver<SourceEvent>,
 )> {
     // Initialize PipeWire library (must be called before any PW objects).
     pw::init();
@@ -76,26 +205,25 @@ pub fn start_audio_thread(
     // If this fails, PipeWire is unavailable (AC1.5).
     // The actual MainLoop is created on the PipeWire thread below.
 
-    let (ring_producer, ring_consumer) = HeapRb::<f32>::new(RING_BUF_CAPACITY).split();
-    let ring_producer = Arc::new(Mutex::new(ring_producer));
+    let feeds: SharedFeeds = Arc::new(Mutex::new(FeedSet::default()));
+    let feeds_thread = Arc::clone(&feeds);
 
     let node_list: NodeList = Arc::new(Mutex::new(Vec::new()));
     let node_list_clone = Arc::clone(&node_list);
 
     let (tx_cmd, rx_cmd) = std::sync::mpsc::sync_channel::<AudioCommand>(8);
-    let (fallback_tx, fallback_rx) = std::sync::mpsc::sync_channel::<FallbackEvent>(4);
-
-    let ring_producer_thread = Arc::clone(&ring_producer);
+    let (event_tx, event_rx) = std::sync::mpsc::sync_channel::<SourceEvent>(4);
 
     thread::Builder::new()
         .name("pipewire-audio".to_string())
         .spawn(move || {
             if let Err(e) = run_pipewire_loop(
-                initial_source,
-                ring_producer_thread,
+                initial_sources,
+                auto_follow_hold,
+                feeds_thread,
                 node_list_clone,
                 rx_cmd,
-                fallback_tx,
+                event_tx,
             ) {
                 eprintln!("error: PipeWire audio thread exited: {e:#}");
                 std::process::exit(1);
@@ -103,7 +231,7 @@ pub fn start_audio_thread(
         })
         .context("spawning PipeWire thread")?;
 
-    Ok((tx_cmd, ring_consumer, node_list, fallback_rx))
+    Ok((tx_cmd, feeds, node_list, event_rx))
 }
 
 /// Enumerate available audio nodes from the shared node list.
@@ -114,11 +242,12 @@ pub fn list_nodes(node_list: &NodeList) -> Vec<AudioNode> {
 
 /// Main PipeWire event loop (runs on dedicated thread).
 fn run_pipewire_loop(
-    initial_source: crate::config::AudioSource,
-    ring_producer: Arc<Mutex<ringbuf::HeapProd<f32>>>,
+    initial_sources: Vec<SourceConfig>,
+    auto_follow_hold: std::time::Duration,
+    feeds: SharedFeeds,
     node_list: NodeList,
     rx_cmd: std::sync::mpsc::Receiver<AudioCommand>,
-    fallback_tx: std::sync::mpsc::SyncSender<FallbackEvent>,
+    event_tx: std::sync::mpsc::SyncSender<SourceEvent>,
 ) -> Result<()> {
     let mainloop = pw::main_loop::MainLoopRc::new(None)
         .context("creating PipeWire MainLoop — is PipeWire running?")?;
@@ -132,13 +261,31 @@ fn run_pipewire_loop(
     // Collect disappeared node IDs from the registry global_remove callback.
     // Phase 8's NodeDisappeared handler reads this list in the command loop below.
     let disappeared_node_ids: Arc<Mutex<Vec<u32>>> = Arc::new(Mutex::new(Vec::new()));
+    // Set by the global callback so the loop below can reattach sources waiting for a node.
+    let nodes_added = Arc::new(std::sync::atomic::AtomicBool::new(false));
+
+    // The `default` metadata object is handed over by the global callback and bound below,
+    // so we can follow `default.audio.sink` changes (headphones plugged in, HDMI selected).
+    let default_metadata: Arc<Mutex<Option<pw::registry::GlobalObject<pw::properties::PropertiesBox>>>> =
+        Arc::new(Mutex::new(None));
+    let default_sink: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
+    let default_sink_changed = Arc::new(std::sync::atomic::AtomicBool::new(false));
 
     // Listen for node additions/removals to populate node_list.
     let node_list_registry = Arc::clone(&node_list);
+    let nodes_added_registry = Arc::clone(&nodes_added);
+    let default_metadata_registry = Arc::clone(&default_metadata);
     let _registry_listener = registry
         .add_listener_local()
         .global(move |global| {
-            // Filter for audio nodes: application streams and monitor sinks.
+            if global.type_ == pw::types::ObjectType::Metadata {
+                if global.props.as_ref().and_then(|p| p.get("metadata.name")) == Some("default") {
+                    *default_metadata_registry.lock().unwrap() = Some(global.to_owned());
+                }
+                return;
+            }
+
+            // Filter for audio nodes: application streams, monitor sinks and capture devices.
             // global.props contains node properties.
             if let Some(props) = &global.props {
                 let media_class: &str = props.get("media.class").unwrap_or("");
@@ -148,18 +295,24 @@ fn run_pipewire_loop(
                     .unwrap_or(&node_name)
                     .to_string();
 
-                let is_monitor = media_class == "Audio/Source"
-                    && node_name.ends_with(".monitor");
-                let is_app_stream = media_class == "Stream/Output/Audio";
+                let kind = match media_class {
+                    "Audio/Source" if node_name.ends_with(".monitor") => Some(NodeKind::Monitor),
+                    "Audio/Source" => Some(NodeKind::Input),
+                    "Stream/Output/Audio" => Some(NodeKind::Application),
+                    _ => None,
+                };
 
-                if is_monitor || is_app_stream {
+                if let Some(kind) = kind {
                     let node = AudioNode {
                         node_id: global.id,
                         name: node_name,
                         description,
-                        is_monitor,
+                        kind,
+                        app_name: props.get("application.name").map(str::to_string),
+                        binary: props.get("application.process.binary").map(str::to_string),
                     };
                     node_list_registry.lock().unwrap().push(node);
+                    nodes_added_registry.store(true, std::sync::atomic::Ordering::Relaxed);
                 }
             }
         })
@@ -175,125 +328,276 @@ fn run_pipewire_loop(
         })
         .register();
 
-    // Create the capture stream for the initial source.
-    let mut _capture = Some(create_capture_stream(&core, &initial_source, Arc::clone(&ring_producer))?);
-    let mut current_source = initial_source.clone(); // track for fallback check (AC1.4)
+    // Sources as configured (node IDs are only hints), and the resolved subset being captured.
+    // Captures are opened on the first pass, after the registry has reported existing nodes.
+    let mut configured = initial_sources;
+    let mut captured: Vec<SourceConfig> = Vec::new();
+    let mut captures: Vec<CaptureStream> = Vec::new();
+    // Sources whose node disappeared; announced again when they reattach.
+    let mut waiting: Vec<AudioSource> = Vec::new();
+    let mut first_pass = true;
+
+    // Auto-follow: one level meter per application stream while an Auto source is configured.
+    let mut follower = follow::AutoFollow::new(auto_follow_hold);
+    let mut meters: Vec<LevelMeter> = Vec::new();
+    let mut last_meter_read = std::time::Instant::now();
+
+    // Listener and bound `default` metadata proxy, kept alive for the life of the loop
+    // (listener first so it is dropped before the proxy).
+    let mut _default_metadata_binding: Option<(pw::metadata::MetadataListener, pw::metadata::Metadata)> = None;
 
     // Poll for AudioCommands and run the PipeWire event loop.
     // PipeWire Loop::iterate() processes pending events non-blockingly.
     let loop_ref = mainloop.loop_();
     loop {
         let _ = loop_ref.iterate(std::time::Duration::from_millis(10));
+        let mut refresh = std::mem::take(&mut first_pass);
 
         match rx_cmd.try_recv() {
             Ok(AudioCommand::Shutdown) => break,
-            Ok(AudioCommand::SwitchSource(new_source)) => {
-                current_source = new_source.clone(); // track new source for fallback check
-                // Drop the current capture (stream and listener) to disconnect it from PipeWire.
-                _capture = None;
-                // Reconnect to the new source.
-                match create_capture_stream(&core, &new_source, Arc::clone(&ring_producer)) {
-                    Ok(c) => {
-                        _capture = Some(c);
-                        eprintln!("info: audio source switched to {:?}", new_source);
-                    }
-                    Err(e) => {
-                        eprintln!("warn: failed to switch audio source: {e:#}");
-                        // Attempt fallback to system output.
-                        match create_capture_stream(
-                            &core,
-                            &crate::config::AudioSource::SystemOutput,
-                            Arc::clone(&ring_producer),
-                        ) {
-                            Ok(c) => {
-                                _capture = Some(c);
-                                eprintln!("warn: fell back to system output capture");
-                            }
-                            Err(e2) => {
-                                eprintln!("error: failed to reconnect audio: {e2:#}");
-                                return Err(e2);
-                            }
-                        }
-                    }
-                }
+            Ok(AudioCommand::SetSources(new_sources)) => {
+                waiting.retain(|w| new_sources.iter().any(|s| s.source.same_source(w)));
+                configured = new_sources;
+                refresh = true;
             }
+            Ok(AudioCommand::SetAutoFollowHold(hold)) => follower.set_hold(hold),
             Err(std::sync::mpsc::TryRecvError::Empty) => {}
             Err(std::sync::mpsc::TryRecvError::Disconnected) => break,
         }
 
-        // Phase 8: Drain disappeared nodes and check for fallback (AC1.4).
-        {
-            let mut should_reconnect = false;
-            let mut lost_name = String::new();
-            let mut lost_id = 0u32;
-
-            if let Ok(mut ids) = disappeared_node_ids.try_lock() {
-                ids.retain(|&id| {
-                    // Remove from node list so tray doesn't show stale entries.
-                    node_list.lock().unwrap().retain(|n| n.node_id != id);
-
-                    // Check if this is our currently captured application node.
-                    // Use reference pattern (&current_source) instead of cloning to avoid
-                    // allocating on every disappeared node in the loop.
-                    if let crate::config::AudioSource::Application { node_id: active_id, ref node_name } =
-                        &current_source
-                    {
-                        if *active_id == id {
-                            eprintln!(
-                                "warn: audio node {id} ({node_name}) disappeared — falling back to system output"
-                            );
-                            should_reconnect = true;
-                            lost_name = node_name.clone();
-                            lost_id = *active_id;
-                        }
-                    }
-                    false // remove from ids list
-                });
+        // Phase 8: Drain disappeared nodes (AC1.4).
+        let mut lost: Vec<(String, u32)> = Vec::new();
+        if let Ok(mut ids) = disappeared_node_ids.try_lock() {
+            for id in ids.drain(..) {
+                // Remove from node list so tray doesn't show stale entries.
+                node_list.lock().unwrap().retain(|n| n.node_id != id);
+                meters.retain(|m| m.node_id != id);
+
+                // The followed app going away is not a loss: Auto picks the next one.
+                if follower.forget(id) {
+                    let _ = event_tx.send(SourceEvent::Following { description: None });
+                    refresh = true;
+                    continue;
+                }
 
-                // Update current_source after the retain closure to avoid borrow conflicts.
-                if should_reconnect {
-                    current_source = crate::config::AudioSource::SystemOutput;
+                // Check if this is one of our captured application or input nodes.
+                if let Some(entry) = captured.iter().find(|s| s.source.node_id() == Some(id)) {
+                    let node_name = entry.source.node_name().unwrap_or_default().to_string();
+                    eprintln!("warn: audio node {id} ({node_name}) disappeared — waiting for it to return");
+                    waiting.push(entry.source.clone());
+                    lost.push((node_name, id));
                 }
             }
+        }
+        if !lost.is_empty() || nodes_added.swap(false, std::sync::atomic::Ordering::Relaxed) {
+            refresh = true;
+        }
 
-            // Perform reconnect outside the closure
-            if should_reconnect {
-                _capture = None;
-                match create_capture_stream(&core, &crate::config::AudioSource::SystemOutput, Arc::clone(&ring_producer)) {
-                    Ok(s) => {
-                        _capture = Some(s);
-                    }
-                    Err(e) => {
-                        eprintln!("error: failed to reconnect to system output: {e:#}");
+        // Bind the `default` metadata object once the registry has announced it.
+        if let Some(global) = default_metadata.lock().unwrap().take() {
+            match registry.bind::<pw::metadata::Metadata, _>(&global) {
+                Ok(metadata) => {
+                    let sink = Arc::clone(&default_sink);
+                    let changed = Arc::clone(&default_sink_changed);
+                    let listener = metadata
+                        .add_listener_local()
+                        .property(move |subject, key, _type, value| {
+                            // Subject 0 is the core; key None means all keys were cleared.
+                            if subject == 0 && matches!(key, Some(DEFAULT_SINK_KEY) | None) {
+                                let name = value.and_then(parse_metadata_name);
+                                let mut current = sink.lock().unwrap();
+                                if *current != name {
+                                    // The first report is the sink we started on, not a change.
+                                    if current.is_some() {
+                                        changed.store(true, std::sync::atomic::Ordering::Relaxed);
+                                    }
+                                    *current = name;
+                                }
+                            }
+                            0
+                        })
+                        .register();
+                    _default_metadata_binding = Some((listener, metadata));
+                }
+                Err(e) => eprintln!("warn: failed to watch default output device: {e}"),
+            }
+        }
+
+        // Default output changed: reconnect system output capture so it follows the new device.
+        if default_sink_changed.swap(false, std::sync::atomic::Ordering::Relaxed) {
+            let sink = default_sink.lock().unwrap().clone().unwrap_or_default();
+            if captured.iter().any(|s| s.source == AudioSource::SystemOutput) {
+                eprintln!("info: default output changed to {sink} — reconnecting system output capture");
+                // Forces the rebuild below even though the resolved set is unchanged.
+                captured.clear();
+                refresh = true;
+                let _ = event_tx.send(SourceEvent::DefaultSinkChanged { sink });
+            }
+        }
+
+        // Auto-follow: read the meters a few times per second and switch when the follower says so.
+        let auto = configured.iter().any(|s| s.source == AudioSource::Auto);
+        if !auto {
+            meters.clear();
+        } else if last_meter_read.elapsed() >= METER_INTERVAL {
+            let now = std::time::Instant::now();
+            last_meter_read = now;
+            for meter in &meters {
+                follower.observe(meter.node_id, meter.take_level(), now);
+            }
+            if follower.update(now) {
+                let nodes = node_list.lock().unwrap();
+                let followed = follower.followed().and_then(|id| nodes.iter().find(|n| n.node_id == id));
+                let description = followed.map(|n| n.description.clone());
+                eprintln!("info: auto source now following {description:?}");
+                let _ = event_tx.send(SourceEvent::Following { description });
+                refresh = true;
+            }
+        }
+
+        if !refresh {
+            continue;
+        }
+
+        // Re-resolve the configured sources against the live nodes and rebuild the captures
+        // only if the resolved set changed.
+        let nodes = node_list.lock().unwrap().clone();
+        if auto {
+            sync_meters(&core, &nodes, &mut meters);
+        }
+        refresh_node_hints(&mut configured, &nodes);
+        let resolved = resolve_sources(&configured, &nodes, follower.followed());
+        if resolved != captured {
+            // Drop the current captures (streams and listeners) to disconnect them from PipeWire.
+            captures.clear();
+            match open_captures(&core, &resolved, &feeds) {
+                Ok(c) => {
+                    captures = c;
+                    eprintln!("info: capturing audio sources {:?}", resolved);
+                    captured = resolved;
+                }
+                Err(e) => {
+                    eprintln!("warn: failed to open audio sources: {e:#}");
+                    // Attempt fallback to system output.
+                    let fallback = vec![SourceConfig::default()];
+                    match open_captures(&core, &fallback, &feeds) {
+                        Ok(c) => {
+                            captures = c;
+                            captured = fallback;
+                            eprintln!("warn: fell back to system output capture");
+                        }
+                        Err(e2) => {
+                            eprintln!("error: failed to reconnect audio: {e2:#}");
+                            return Err(e2);
+                        }
                     }
                 }
-                let _ = fallback_tx.send(FallbackEvent {
-                    lost_name,
-                    lost_id,
-                });
             }
         }
+
+        let fallback = captured == vec![SourceConfig::default()]
+            && !configured.iter().any(|s| s.source == AudioSource::SystemOutput);
+        for (name, node_id) in lost {
+            let _ = event_tx.send(SourceEvent::Lost {
+                name,
+                node_id,
+                fallback,
+                sources: configured.clone(),
+            });
+        }
+        waiting.retain(|w| {
+            let Some(entry) = captured.iter().find(|s| s.source.same_source(w)) else {
+                return true;
+            };
+            let name = entry.source.node_name().unwrap_or_default().to_string();
+            let node_id = entry.source.node_id().unwrap_or_default();
+            eprintln!("info: audio node {name} reappeared as {node_id} — capture resumed");
+            let _ = event_tx.send(SourceEvent::Reattached {
+                name,
+                node_id,
+                sources: configured.clone(),
+            });
+            false
+        });
     }
 
     Ok(())
 }
 
+/// Open one capture stream per source, each with its own ring buffer, and publish the
+/// ring consumers to `feeds` for the bridge thread to drain.
+fn open_captures<'a>(
+    core: &'a pw::core::CoreRc,
+    sources: &[SourceConfig],
+    feeds: &SharedFeeds,
+) -> Result<Vec<CaptureStream<'a>>> {
+    let mut captures = Vec::with_capacity(sources.len());
+    let mut new_feeds = Vec::with_capacity(sources.len());
+    for entry in sources {
+        let (mut producer, consumer) = HeapRb::<f32>::new(RING_BUF_CAPACITY).split();
+        captures.push(create_capture_stream(core, &entry.source, move |samples| {
+            let _ = producer.push_slice(samples); // drop samples if ring full
+        })?);
+        new_feeds.push(SourceFeed {
+            config: entry.clone(),
+            consumer,
+        });
+    }
+
+    let mut set = feeds.lock().unwrap();
+    set.generation += 1;
+    set.feeds = new_feeds;
+    Ok(captures)
+}
+
+/// Open level meters for application streams that have none, and drop meters whose
+/// stream is gone.
+fn sync_meters<'a>(core: &'a pw::core::CoreRc, nodes: &[AudioNode], meters: &mut Vec<LevelMeter<'a>>) {
+    let apps: Vec<&AudioNode> = nodes.iter().filter(|n| n.kind == NodeKind::Application).collect();
+    meters.retain(|m| apps.iter().any(|n| n.node_id == m.node_id));
+    for node in apps {
+        if meters.iter().any(|m| m.node_id == node.node_id) {
+            continue;
+        }
+        let level = Arc::new(std::sync::atomic::AtomicU32::new(0));
+        let level_cb = Arc::clone(&level);
+        let stream = create_capture_stream(core, &node.to_source(), move |samples| {
+            if samples.is_empty() {
+                return;
+            }
+            let rms = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+            level_cb.fetch_max(rms.to_bits(), std::sync::atomic::Ordering::Relaxed);
+        });
+        match stream {
+            Ok(capture) => meters.push(LevelMeter {
+                node_id: node.node_id,
+                level,
+                _capture: capture,
+            }),
+            Err(e) => eprintln!("warn: failed to meter audio node {}: {e:#}", node.node_id),
+        }
+    }
+}
+
 /// Create a PipeWire capture stream connected to the given AudioSource.
+/// `on_samples` receives each buffer of interleaved stereo 48kHz samples on the real-time
+/// thread, so it must not allocate or block.
 /// Returns a CaptureStream wrapper holding both the stream and its listener,
 /// ensuring proper cleanup when switched or dropped.
-fn create_capture_stream<'a>(
+fn create_capture_stream<'a, F>(
     core: &'a pw::core::CoreRc,
     source: &crate::config::AudioSource,
-    ring_producer: Arc<Mutex<ringbuf::HeapProd<f32>>>,
-) -> Result<CaptureStream<'a>> {
+    on_samples: F,
+) -> Result<CaptureStream<'a>>
+where
+    F: FnMut(&[f32]) + 'static,
+{
     use pw::spa::pod::Pod;
     use pw::spa::param::audio::{AudioFormat, AudioInfoRaw};
 
-    // Build stream properties.
-    let target_node = match source {
-        crate::config::AudioSource::SystemOutput => None,
-        crate::config::AudioSource::Application { node_id, .. } => Some(node_id.to_string()),
-    };
+    // Build stream properties. Application and input nodes are targeted by ID;
+    // system output connects to the default sink's monitor.
+    let target_node = source.node_id().map(|id| id.to_string());
 
     let mut stream_props = properties! {
         *pw::keys::MEDIA_TYPE => "Audio",
@@ -336,10 +640,9 @@ fn create_capture_stream<'a>(
     let mut params = [Pod::from_bytes(&values).context("creating SPA Pod")?];
 
     // Register the process callback (real-time — no allocation, no blocking).
-    let ring_producer_cb = Arc::clone(&ring_producer);
     let _listener = stream
-        .add_local_listener_with_user_data(ring_producer_cb)
-        .process(|stream, ring_producer| {
+        .add_local_listener_with_user_data(on_samples)
+        .process(|stream, on_samples| {
             if let Some(mut buf) = stream.dequeue_buffer() {
                 let datas = buf.datas_mut();
                 if let Some(data) = datas.first_mut() {
@@ -350,10 +653,7 @@ fn create_capture_stream<'a>(
                         let float_bytes = &bytes[offset..offset + size];
                         // Convert bytes to f32 slice (F32LE, native endian on x86).
                         let samples = bytemuck::cast_slice::<u8, f32>(float_bytes);
-                        // Push to ring buffer — never block in RT context.
-                        if let Ok(mut prod) = ring_producer.try_lock() {
-                            let _ = prod.push_slice(samples); // drop samples if ring full
-                        }
+                        on_samples(samples);
                     }
                 }
             }
@@ -379,29 +679,258 @@ fn create_capture_stream<'a>(
     })
 }
 
-/// Validate that a saved audio source is still available.
-/// If an Application source references a node_id that no longer exists,
-/// falls back to SystemOutput (which is always available).
-///
-/// Returns the validated source (either the input source if valid, or SystemOutput as fallback).
-pub fn validate_audio_source(
-    saved_source: crate::config::AudioSource,
-    current_nodes: &[AudioNode],
-) -> crate::config::AudioSource {
-    match &saved_source {
-        crate::config::AudioSource::SystemOutput => {
-            // System output is always available.
-            saved_source
+/// Find the live node for a source: the hinted node ID if it still matches the source's
+/// identity, otherwise the first node that does (e.g. the app restarted under a new ID).
+pub fn find_node<'a>(source: &AudioSource, nodes: &'a [AudioNode]) -> Option<&'a AudioNode> {
+    let hint = source.node_id()?;
+    nodes
+        .iter()
+        .find(|n| n.node_id == hint && n.matches(source))
+        .or_else(|| nodes.iter().find(|n| n.matches(source)))
+}
+
+/// Spec string for a selected source: "system", "auto", or its PipeWire node name.
+pub fn source_spec(source: &AudioSource) -> String {
+    match source {
+        AudioSource::SystemOutput => "system".to_string(),
+        AudioSource::Auto => "auto".to_string(),
+        AudioSource::Application { node_name, .. } | AudioSource::Input { node_name, .. } => node_name.clone(),
+    }
+}
+
+/// Resolve a source spec: "system" or "auto", a node ID, an exact node name, or else a
+/// case-insensitive substring of a node's application name or description. Ambiguous
+/// substrings match nothing.
+pub fn parse_source_spec(spec: &str, nodes: &[AudioNode]) -> Option<AudioSource> {
+    let spec = spec.trim();
+    match spec.to_lowercase().as_str() {
+        "system" | "system_output" => return Some(AudioSource::SystemOutput),
+        "auto" => return Some(AudioSource::Auto),
+        _ => {}
+    }
+    if let Ok(id) = spec.parse::<u32>() {
+        return nodes.iter().find(|n| n.node_id == id).map(AudioNode::to_source);
+    }
+    if let Some(node) = nodes.iter().find(|n| n.name == spec) {
+        return Some(node.to_source());
+    }
+    let needle = spec.to_lowercase();
+    let mut matches = nodes.iter().filter(|n| {
+        n.description.to_lowercase().contains(&needle)
+            || n.app_name.as_deref().is_some_and(|a| a.to_lowercase().contains(&needle))
+    });
+    match (matches.next(), matches.next()) {
+        (Some(node), None) => Some(node.to_source()),
+        _ => None,
+    }
+}
+
+/// Point each configured source's node ID hint at its current live node, if any.
+pub fn refresh_node_hints(sources: &mut [SourceConfig], current_nodes: &[AudioNode]) {
+    for entry in sources {
+        if let Some(node) = find_node(&entry.source, current_nodes) {
+            entry.source.set_node_id(node.node_id);
         }
-        crate::config::AudioSource::Application { node_id, .. } => {
-            // Check if the saved node_id exists in current nodes.
-            if current_nodes.iter().any(|n| n.node_id == *node_id) {
-                saved_source
-            } else {
-                eprintln!("warn: saved audio source (node_id={}) no longer available", node_id);
-                eprintln!("warn: falling back to system output");
-                crate::config::AudioSource::SystemOutput
+    }
+}
+
+/// The subset of configured sources that can be captured right now, with live node IDs.
+/// An `Auto` source becomes the application it currently follows (`followed`), unless that
+/// application is already captured. Sources whose node is missing are left out until it
+/// reappears; if nothing remains, system output is captured instead.
+pub fn resolve_sources(
+    configured: &[SourceConfig],
+    current_nodes: &[AudioNode],
+    followed: Option<u32>,
+) -> Vec<SourceConfig> {
+    let mut resolved: Vec<SourceConfig> = configured
+        .iter()
+        .filter_map(|entry| match entry.source {
+            AudioSource::SystemOutput => Some(entry.clone()),
+            AudioSource::Auto => None,
+            _ => {
+                let node = find_node(&entry.source, current_nodes)?;
+                let mut entry = entry.clone();
+                entry.source.set_node_id(node.node_id);
+                Some(entry)
             }
+        })
+        .collect();
+    let auto = configured.iter().find(|s| s.source == AudioSource::Auto);
+    let followed = followed.and_then(|id| current_nodes.iter().find(|n| n.node_id == id));
+    if let (Some(entry), Some(node)) = (auto, followed) {
+        if !resolved.iter().any(|s| s.source.node_id() == Some(node.node_id)) {
+            resolved.push(SourceConfig { source: node.to_source(), ..entry.clone() });
+        }
+    }
+    if resolved.is_empty() {
+        resolved.push(SourceConfig::default());
+    }
+    resolved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(node_id: u32, kind: NodeKind) -> AudioNode {
+        AudioNode {
+            node_id,
+            name: format!("node-{node_id}"),
+            description: format!("Node {node_id}"),
+            kind,
+            app_name: None,
+            binary: None,
+        }
+    }
+
+    fn app_node(node_id: u32, app: &str) -> AudioNode {
+        AudioNode {
+            node_id,
+            name: app.to_string(),
+            description: app.to_string(),
+            kind: NodeKind::Application,
+            app_name: Some(app.to_string()),
+            binary: Some(app.to_lowercase()),
+        }
+    }
+
+    #[test]
+    fn input_node_maps_to_input_source() {
+        let source = node(42, NodeKind::Input).to_source();
+        assert_eq!(
+            source,
+            AudioSource::Input { node_id: 42, node_name: "node-42".to_string() }
+        );
+    }
+
+    #[test]
+    fn application_and_monitor_nodes_map_to_application_source() {
+        assert!(matches!(node(7, NodeKind::Application).to_source(), AudioSource::Application { node_id: 7, .. }));
+        assert!(matches!(node(8, NodeKind::Monitor).to_source(), AudioSource::Application { node_id: 8, .. }));
+    }
+
+    #[test]
+    fn restarted_application_is_found_under_new_id() {
+        let saved = app_node(7, "Firefox").to_source();
+        let nodes = vec![app_node(31, "mpv"), app_node(93, "Firefox")];
+        assert_eq!(find_node(&saved, &nodes).map(|n| n.node_id), Some(93));
+    }
+
+    #[test]
+    fn node_id_hint_picks_between_identical_streams() {
+        let saved = app_node(12, "Firefox").to_source();
+        let nodes = vec![app_node(11, "Firefox"), app_node(12, "Firefox")];
+        assert_eq!(find_node(&saved, &nodes).map(|n| n.node_id), Some(12));
+    }
+
+    #[test]
+    fn recycled_node_id_of_other_app_does_not_match() {
+        let saved = app_node(7, "Firefox").to_source();
+        let nodes = vec![app_node(7, "mpv")];
+        assert!(find_node(&saved, &nodes).is_none());
+    }
+
+    #[test]
+    fn application_with_disagreeing_binary_does_not_match() {
+        let saved = app_node(7, "Firefox").to_source();
+        let mut other = app_node(8, "Firefox");
+        other.binary = Some("firefox-esr".to_string());
+        assert!(!other.matches(&saved));
+    }
+
+    #[test]
+    fn resolve_skips_missing_and_keeps_gain() {
+        let nodes = vec![app_node(93, "Firefox")];
+        let saved = vec![
+            SourceConfig { gain: 0.8, ..SourceConfig::new(app_node(7, "Firefox").to_source()) },
+            SourceConfig::new(AudioSource::Input { node_id: 42, node_name: "node-42".to_string() }),
+        ];
+        let resolved = resolve_sources(&saved, &nodes, None);
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].source.node_id(), Some(93));
+        assert_eq!(resolved[0].gain, 0.8);
+    }
+
+    #[test]
+    fn resolve_falls_back_to_system_output_when_empty() {
+        let saved = vec![SourceConfig::new(AudioSource::Input { node_id: 42, node_name: "mic".to_string() })];
+        assert_eq!(resolve_sources(&saved, &[], None), vec![SourceConfig::default()]);
+    }
+
+    #[test]
+    fn resolve_auto_captures_followed_app() {
+        let nodes = vec![app_node(31, "mpv"), app_node(93, "Firefox")];
+        let saved = vec![SourceConfig { gain: 0.5, ..SourceConfig::new(AudioSource::Auto) }];
+        let resolved = resolve_sources(&saved, &nodes, Some(31));
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].source, app_node(31, "mpv").to_source());
+        assert_eq!(resolved[0].gain, 0.5);
+    }
+
+    #[test]
+    fn resolve_auto_without_followed_app_uses_system_output() {
+        let saved = vec![SourceConfig::new(AudioSource::Auto)];
+        assert_eq!(resolve_sources(&saved, &[app_node(31, "mpv")], None), vec![SourceConfig::default()]);
+    }
+
+    #[test]
+    fn resolve_auto_does_not_duplicate_explicit_source() {
+        let nodes = vec![app_node(93, "Firefox")];
+        let saved = vec![
+            SourceConfig::new(app_node(93, "Firefox").to_source()),
+            SourceConfig::new(AudioSource::Auto),
+        ];
+        assert_eq!(resolve_sources(&saved, &nodes, Some(93)).len(), 1);
+    }
+
+    #[test]
+    fn parses_default_sink_metadata_value() {
+        assert_eq!(
+            parse_metadata_name(r#"{ "name": "alsa_output.usb-headset.analog-stereo" }"#).as_deref(),
+            Some("alsa_output.usb-headset.analog-stereo")
+        );
+        assert_eq!(parse_metadata_name(r#"{"name":"hdmi"}"#).as_deref(), Some("hdmi"));
+        assert_eq!(parse_metadata_name("{}"), None);
+    }
+
+    #[test]
+    fn refresh_hints_keeps_missing_sources() {
+        let mut sources = vec![
+            SourceConfig::new(app_node(7, "Firefox").to_source()),
+            SourceConfig::new(app_node(8, "mpv").to_source()),
+        ];
+        refresh_node_hints(&mut sources, &[app_node(93, "Firefox")]);
+        assert_eq!(sources.len(), 2);
+        assert_eq!(sources[0].source.node_id(), Some(93));
+        assert_eq!(sources[1].source.node_id(), Some(8));
+    }
+
+    #[test]
+    fn source_specs_resolve_against_nodes() {
+        let mic = AudioNode {
+            name: "alsa_input.usb-mic".to_string(),
+            description: "USB Microphone".to_string(),
+            ..node(52, NodeKind::Input)
+        };
+        let nightly = AudioNode { description: "Firefox Nightly".to_string(), ..app_node(63, "Firefox") };
+        let nodes = vec![app_node(41, "Firefox"), mic, nightly];
+        assert_eq!(parse_source_spec("system", &nodes), Some(AudioSource::SystemOutput));
+        assert_eq!(parse_source_spec("Auto", &nodes), Some(AudioSource::Auto));
+        assert_eq!(parse_source_spec("52", &nodes), Some(nodes[1].to_source()));
+        assert_eq!(parse_source_spec("alsa_input.usb-mic", &nodes), Some(nodes[1].to_source()));
+        assert_eq!(parse_source_spec("microphone", &nodes), Some(nodes[1].to_source()));
+        assert_eq!(parse_source_spec("nightly", &nodes), Some(nodes[2].to_source()));
+        // Missing IDs and ambiguous substrings match nothing.
+        assert_eq!(parse_source_spec("99", &nodes), None);
+        assert_eq!(parse_source_spec("fire", &nodes), None);
+    }
+
+    #[test]
+    fn selected_sources_round_trip_through_specs() {
+        let nodes = vec![node(52, NodeKind::Input)];
+        for source in [AudioSource::SystemOutput, AudioSource::Auto, nodes[0].to_source()] {
+            assert_eq!(parse_source_spec(&source_spec(&source), &nodes), Some(source));
         }
     }
 }
@@ -1,4 +1,5 @@
-//! Audio resampler: 48kHz stereo F32 → 16kHz mono F32 with 160ms chunk output.
+//! Audio resampler: 48kHz stereo (or any rate and channel count) F32 → 16kHz mono F32
+//! with 160ms chunk output.
 
 use anyhow::Context;
 use anyhow::Result;
@@ -11,81 +12,96 @@ pub const INPUT_SAMPLE_RATE: u32 = 48_000;
 pub const OUTPUT_SAMPLE_RATE: u32 = 16_000;
 /// Output chunk size: 160ms at 16kHz = 2560 samples.
 pub const CHUNK_SAMPLES: usize = 2_560;
-/// Corresponding input chunk size at 48kHz: 480ms input for 160ms output.
+/// Corresponding input chunk size at 48kHz: 160ms of input per resampler call.
 /// Fft with FixedSync::Input requires input size = 7680 frames.
 pub const INPUT_FRAMES_PER_CHUNK: usize = 7_680;
 
-/// Resamples 48kHz stereo → 16kHz mono and accumulates 160ms output chunks.
+/// Resamples 48kHz stereo (or any input format) → 16kHz mono and accumulates 160ms output chunks.
 pub struct AudioResampler {
     resampler: Fft<f32>,
+    /// Interleaved input channels, downmixed to mono after resampling.
+    channels: usize,
+    /// Input frames consumed per resampler call (160ms at the input rate).
+    input_frames: usize,
     /// Accumulation buffer: mono 16kHz samples waiting to fill a 160ms chunk.
     accumulator: Vec<f32>,
-    /// Interleaved stereo input buffer waiting to fill one resampler input chunk.
+    /// Interleaved input buffer waiting to fill one resampler input chunk.
     input_buf: Vec<f32>,
 }
 
 impl AudioResampler {
     /// Create a new resampler for 48kHz stereo → 16kHz mono.
     pub fn new() -> Result<Self> {
+        Self::with_input(INPUT_SAMPLE_RATE, 2)
+    }
+
+    /// Create a resampler for interleaved input at `sample_rate` Hz with `channels` channels,
+    /// e.g. audio decoded from a file.
+    pub fn with_input(sample_rate: u32, channels: usize) -> Result<Self> {
+        anyhow::ensure!(
+            sample_rate > 0 && channels > 0,
+            "invalid input format: {sample_rate} Hz, {channels} channels"
+        );
+        // 160ms of input per call; INPUT_FRAMES_PER_CHUNK at 48kHz.
+        let input_frames = (sample_rate as usize * 4 / 25).max(1);
+
         // Fft<f32>: FFT-based synchronous resampler.
         // Parameters: input_rate, output_rate, chunk_size (in input frames), sub_chunks, channels, fixed
-        // chunk_size = INPUT_FRAMES_PER_CHUNK frames, 2 channels (stereo input)
         // FixedSync::Input means input size is fixed, output varies naturally
         let resampler = Fft::<f32>::new(
-            INPUT_SAMPLE_RATE as usize,
+            sample_rate as usize,
             OUTPUT_SAMPLE_RATE as usize,
-            INPUT_FRAMES_PER_CHUNK,
+            input_frames,
             2, // sub-chunks (1 = no sub-chunking)
-            2, // channels: stereo input
+            channels,
             FixedSync::Input,
         )
         .context("creating Fft resampler")?;
 
         Ok(AudioResampler {
             resampler,
+            channels,
+            input_frames,
             accumulator: Vec::with_capacity(CHUNK_SAMPLES * 2),
-            input_buf: Vec::with_capacity(INPUT_FRAMES_PER_CHUNK * 2 * 2),
+            input_buf: Vec::with_capacity(input_frames * channels * 2),
         })
     }
 
-    /// Feed interleaved stereo 48kHz f32 samples. Returns complete 160ms mono chunks as they
-    /// become available. May return zero or more chunks per call.
+    /// Feed interleaved f32 samples in the input format (stereo 48kHz for `new()`). Returns
+    /// complete 160ms mono chunks as they become available. May return zero or more chunks per call.
     ///
-    /// `samples` must be interleaved stereo: [L0, R0, L1, R1, ...]
+    /// `samples` must be interleaved, e.g. stereo: [L0, R0, L1, R1, ...]
     pub fn push_interleaved(&mut self, samples: &[f32]) -> Result<Vec<Vec<f32>>> {
         self.input_buf.extend_from_slice(samples);
         let mut output_chunks = Vec::new();
 
-        // Process full resampler input chunks (INPUT_FRAMES_PER_CHUNK * 2 interleaved samples).
-        let interleaved_chunk = INPUT_FRAMES_PER_CHUNK * 2;
+        // Process full resampler input chunks (input_frames * channels interleaved samples).
+        let channels = self.channels;
+        let interleaved_chunk = self.input_frames * channels;
         while self.input_buf.len() >= interleaved_chunk {
             let chunk: Vec<f32> = self.input_buf.drain(..interleaved_chunk).collect();
 
-            // Deinterleave stereo into two channel vectors.
-            let mut left = Vec::with_capacity(INPUT_FRAMES_PER_CHUNK);
-            let mut right = Vec::with_capacity(INPUT_FRAMES_PER_CHUNK);
-            for pair in chunk.chunks_exact(2) {
-                left.push(pair[0]);
-                right.push(pair[1]);
+            // Deinterleave into one vector per channel.
+            let mut input_vecs = vec![Vec::with_capacity(self.input_frames); channels];
+            for frame in chunk.chunks_exact(channels) {
+                for (channel, sample) in input_vecs.iter_mut().zip(frame) {
+                    channel.push(*sample);
+                }
             }
 
-            // Resample both channels using the process_into_buffer method.
-            // Allocate output buffers sized for the expected output.
-            let expected_output_frames = (INPUT_FRAMES_PER_CHUNK * OUTPUT_SAMPLE_RATE as usize)
-                / INPUT_SAMPLE_RATE as usize;
-            let left_out = vec![0.0f32; expected_output_frames];
-            let right_out = vec![0.0f32; expected_output_frames];
+            // Resample all channels using the process_into_buffer method.
+            // Allocate output buffers sized for the largest possible output.
+            let max_output_frames = self.resampler.output_frames_max();
 
             // Create adapters from the vector slices
-            let input_vecs = vec![left, right];
-            let input_adapter = SequentialSliceOfVecs::new(&input_vecs, 2, INPUT_FRAMES_PER_CHUNK)
+            let input_adapter = SequentialSliceOfVecs::new(&input_vecs, channels, self.input_frames)
                 .context("creating input adapter")?;
 
-            let mut output_vecs = vec![left_out, right_out];
+            let mut output_vecs = vec![vec![0.0f32; max_output_frames]; channels];
             let mut output_adapter = SequentialSliceOfVecs::new_mut(
                 &mut output_vecs,
-                2,
-                expected_output_frames,
+                channels,
+                max_output_frames,
             )
             .context("creating output adapter")?;
 
@@ -97,8 +113,9 @@ impl AudioResampler {
             .context("resampling audio")?;
 
             // Downmix to mono by averaging.
-            for (l, r) in output_vecs[0][..output_count].iter().zip(&output_vecs[1][..output_count]) {
-                self.accumulator.push((l + r) * 0.5);
+            let scale = 1.0 / channels as f32;
+            for i in 0..output_count {
+                self.accumulator.push(output_vecs.iter().map(|c| c[i]).sum::<f32>() * scale);
             }
 
             // Drain full 160ms output chunks.
@@ -160,4 +177,16 @@ mod tests {
         }
         assert_eq!(total_chunks, 1, "one full input chunk should yield one output chunk");
     }
+
+    #[test]
+    fn resampler_accepts_other_rates_and_channel_counts() {
+        for (rate, channels) in [(44_100u32, 2usize), (16_000, 1), (22_050, 1), (8_000, 6)] {
+            let mut r = AudioResampler::with_input(rate, channels).unwrap();
+            // Two seconds of input should yield close to two seconds of 16kHz output.
+            let samples = vec![0.1f32; rate as usize * channels * 2];
+            let out: usize = r.push_interleaved(&samples).unwrap().iter().map(Vec::len).sum();
+            assert!(out >= CHUNK_SAMPLES * 10, "{rate} Hz x{channels}: only {out} samples out");
+            assert!(out <= OUTPUT_SAMPLE_RATE as usize * 2, "{rate} Hz x{channels}: {out} samples out");
+        }
+    }
 }
@@ -0,0 +1,177 @@
+//! Voice activity detection: keeps silent chunks away from the engine.
+//!
+//! Energy based: a chunk is speech when its RMS level clears both a fixed floor and a
+//! multiple of the running noise level. Silence is still passed on for a short hangover so
+//! the engine can finish the last words, then dropped; once the pause outlasts the hangover
+//! the engine is told to flush. A few silent chunks are kept back and replayed when speech
+//! resumes, so word onsets are not clipped.
+
+use crate::config::VadAggressiveness;
+use crate::stt::{AudioChunk, EngineInput};
+use std::collections::VecDeque;
+
+/// Silent chunks (160ms each) replayed ahead of speech onset.
+const PRE_ROLL_CHUNKS: usize = 2;
+/// How far speech must rise above the noise floor.
+const NOISE_FACTOR: f32 = 3.0;
+/// Smoothing for the noise floor estimate (per silent chunk).
+const NOISE_SMOOTHING: f32 = 0.05;
+
+/// Per-source VAD state.
+pub struct Vad {
+    /// None when VAD is off and every chunk is passed through.
+    params: Option<VadParams>,
+    noise_floor: f32,
+    /// Consecutive silent chunks since the last speech.
+    silent_chunks: usize,
+    /// Silence is being dropped and the engine has been flushed.
+    paused: bool,
+    pre_roll: VecDeque<AudioChunk>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct VadParams {
+    /// Minimum RMS level counted as speech.
+    min_level: f32,
+    /// Silent chunks still sent after speech before pausing.
+    hangover_chunks: usize,
+}
+
+impl VadParams {
+    fn for_level(level: VadAggressiveness) -> Option<Self> {
+        let (min_level, hangover_chunks) = match level {
+            VadAggressiveness::Off => return None,
+            // Roughly -50, -44 and -38 dBFS; 1.12s, 0.8s and 0.64s of hangover.
+            VadAggressiveness::Low => (0.003, 7),
+            VadAggressiveness::Medium => (0.006, 5),
+            VadAggressiveness::High => (0.012, 4),
+        };
+        Some(VadParams { min_level, hangover_chunks })
+    }
+}
+
+impl Vad {
+    pub fn new(level: VadAggressiveness) -> Self {
+        Vad {
+            params: VadParams::for_level(level),
+            noise_floor: 0.0,
+            silent_chunks: 0,
+            paused: false,
+            pre_roll: VecDeque::with_capacity(PRE_ROLL_CHUNKS),
+        }
+    }
+
+    /// Classify one chunk. Returns what to send to the inference thread: nothing while
+    /// paused, the held-back pre-roll plus the chunk on speech onset, and a flush when a
+    /// pause begins.
+    pub fn process(&mut self, chunk: AudioChunk) -> Vec<EngineInput> {
+        let Some(params) = self.params else {
+            return vec![EngineInput::Audio(chunk)];
+        };
+        let level = rms(&chunk.pcm);
+        let threshold = params.min_level.max(self.noise_floor * NOISE_FACTOR);
+
+        if level > threshold {
+            self.silent_chunks = 0;
+            let mut out = Vec::new();
+            if self.paused {
+                self.paused = false;
+                out.extend(self.pre_roll.drain(..).map(EngineInput::Audio));
+            }
+            out.push(EngineInput::Audio(chunk));
+            return out;
+        }
+
+        // Only silence updates the noise estimate, so speech cannot raise its own threshold.
+        self.noise_floor += (level - self.noise_floor) * NOISE_SMOOTHING;
+        self.silent_chunks += 1;
+        if self.paused {
+            if self.pre_roll.len() == PRE_ROLL_CHUNKS {
+                self.pre_roll.pop_front();
+            }
+            self.pre_roll.push_back(chunk);
+            return Vec::new();
+        }
+        let mut out = vec![EngineInput::Audio(chunk)];
+        if self.silent_chunks >= params.hangover_chunks {
+            self.paused = true;
+            out.push(EngineInput::Flush);
+        }
+        out
+    }
+}
+
+fn rms(pcm: &[f32]) -> f32 {
+    if pcm.is_empty() {
+        return 0.0;
+    }
+    (pcm.iter().map(|s| s * s).sum::<f32>() / pcm.len() as f32).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(i: u64, amplitude: f32) -> AudioChunk {
+        AudioChunk { start_sample: i * 2560, pcm: vec![amplitude; 2560] }
+    }
+
+    fn kinds(out: &[EngineInput]) -> Vec<&'static str> {
+        out.iter()
+            .map(|i| match i {
+                EngineInput::Audio(_) => "audio",
+                EngineInput::Flush => "flush",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn off_passes_everything() {
+        let mut vad = Vad::new(VadAggressiveness::Off);
+        for i in 0..20 {
+            assert_eq!(kinds(&vad.process(chunk(i, 0.0))), vec!["audio"]);
+        }
+    }
+
+    #[test]
+    fn silence_after_hangover_is_flushed_then_dropped() {
+        let mut vad = Vad::new(VadAggressiveness::High);
+        assert_eq!(kinds(&vad.process(chunk(0, 0.2))), vec!["audio"]);
+        for i in 1..4 {
+            assert_eq!(kinds(&vad.process(chunk(i, 0.0))), vec!["audio"], "hangover chunk {i}");
+        }
+        assert_eq!(kinds(&vad.process(chunk(4, 0.0))), vec!["audio", "flush"]);
+        for i in 5..50 {
+            assert!(vad.process(chunk(i, 0.0)).is_empty(), "silent chunk {i} should be dropped");
+        }
+    }
+
+    #[test]
+    fn speech_onset_replays_pre_roll() {
+        let mut vad = Vad::new(VadAggressiveness::High);
+        for i in 0..20 {
+            vad.process(chunk(i, 0.0));
+        }
+        let out = vad.process(chunk(20, 0.2));
+        let starts: Vec<u64> = out
+            .iter()
+            .map(|i| match i {
+                EngineInput::Audio(c) => c.start_sample / 2560,
+                EngineInput::Flush => panic!("unexpected flush"),
+            })
+            .collect();
+        assert_eq!(starts, vec![18, 19, 20]);
+    }
+
+    #[test]
+    fn steady_noise_raises_threshold() {
+        let mut vad = Vad::new(VadAggressiveness::Low);
+        // Hum just below the fixed floor settles the noise estimate near its level.
+        for i in 0..200 {
+            vad.process(chunk(i, 0.0029));
+        }
+        // A slightly louder hum is still silence; the pause holds.
+        assert!(vad.process(chunk(200, 0.005)).is_empty());
+        assert!(!vad.process(chunk(201, 0.05)).is_empty(), "real speech still gets through");
+    }
+}
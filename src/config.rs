@@ -1,5 +1,6 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use notify_debouncer_mini::{new_debouncer, DebounceEventResult};
 use std::time::Duration;
@@ -20,12 +21,108 @@ pub enum AudioSource {
     /// System-wide monitor sink (default output loopback).
     #[default]
     SystemOutput,
-    /// A specific application's PipeWire node, identified by node ID.
-    Application { node_id: u32, node_name: String },
+    /// A specific application's PipeWire stream. Matched by application name, process binary
+    /// and node name; `node_id` is only a hint, since PipeWire assigns a new ID on restart.
+    Application {
+        node_id: u32,
+        node_name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        app_name: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        binary: Option<String>,
+    },
+    /// A capture device (microphone, line-in), identified by node ID.
+    Input { node_id: u32, node_name: String },
+    /// Whichever application stream is currently producing sound.
+    Auto,
+}
+
+impl AudioSource {
+    /// PipeWire node ID to target, or None for the default system output monitor.
+    pub fn node_id(&self) -> Option<u32> {
+        match self {
+            AudioSource::SystemOutput | AudioSource::Auto => None,
+            AudioSource::Application { node_id, .. } | AudioSource::Input { node_id, .. } => Some(*node_id),
+        }
+    }
+
+    /// PipeWire node name, or None for the default system output monitor.
+    pub fn node_name(&self) -> Option<&str> {
+        match self {
+            AudioSource::SystemOutput | AudioSource::Auto => None,
+            AudioSource::Application { node_name, .. } | AudioSource::Input { node_name, .. } => Some(node_name),
+        }
+    }
+
+    /// Replace the node ID hint (no-op for system output and auto).
+    pub fn set_node_id(&mut self, id: u32) {
+        match self {
+            AudioSource::SystemOutput | AudioSource::Auto => {}
+            AudioSource::Application { node_id, .. } | AudioSource::Input { node_id, .. } => *node_id = id,
+        }
+    }
+
+    /// Whether both refer to the same source, ignoring the node ID hint.
+    pub fn same_source(&self, other: &AudioSource) -> bool {
+        let mut a = self.clone();
+        let mut b = other.clone();
+        a.set_node_id(0);
+        b.set_node_id(0);
+        a == b
+    }
+}
+
+/// One entry in the capture mix: an audio source and the gain applied to it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceConfig {
+    #[serde(flatten)]
+    pub source: AudioSource,
+    /// Linear gain applied before mixing (1.0 = unchanged).
+    #[serde(default = "default_gain")]
+    pub gain: f32,
+    /// Speaker label shown before this source's captions in per-source mode.
+    /// Defaults to "Me" for input devices, the node name for applications.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Pango colour for this source's label, e.g. "#8ab4f8".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+fn default_gain() -> f32 {
+    1.0
+}
+
+impl SourceConfig {
+    /// Wrap a source with unity gain and default labelling.
+    pub fn new(source: AudioSource) -> Self {
+        SourceConfig { source, gain: default_gain(), label: None, color: None }
+    }
+
+    /// Speaker label for captions from this source.
+    pub fn display_label(&self) -> String {
+        if let Some(label) = &self.label {
+            return label.clone();
+        }
+        match &self.source {
+            AudioSource::SystemOutput => "System".to_string(),
+            AudioSource::Auto => "Auto".to_string(),
+            AudioSource::Input { .. } => "Me".to_string(),
+            AudioSource::Application { app_name, node_name, .. } => {
+                app_name.clone().unwrap_or_else(|| node_name.clone())
+            }
+        }
+    }
+}
+
+impl Default for SourceConfig {
+    fn default() -> Self {
+        SourceConfig::new(AudioSource::default())
+    }
 }
 
 /// Overlay display mode.
-#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize, clap::ValueEnum)]
 #[serde(rename_all = "snake_case")]
 pub enum OverlayMode {
     /// Anchored to a screen edge via wlr-layer-shell.
@@ -46,6 +143,90 @@ pub enum ScreenEdge {
     Right,
 }
 
+/// Layer-shell layer the overlay is drawn on, from the bottom of the stack up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlayLayer {
+    /// Below all windows, above the wallpaper.
+    Background,
+    /// Below all windows.
+    Bottom,
+    /// Above windows, below fullscreen ones on most compositors.
+    #[default]
+    Top,
+    /// Above everything, including fullscreen windows.
+    Overlay,
+}
+
+/// How eagerly quiet audio is treated as silence and kept from the engine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VadAggressiveness {
+    /// Every chunk is recognized.
+    Off,
+    /// Only clear silence is skipped.
+    #[default]
+    Low,
+    Medium,
+    /// Quiet speech may be skipped too; saves the most CPU.
+    High,
+}
+
+/// File format for subtitle recordings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubtitleFormat {
+    #[default]
+    Srt,
+    Vtt,
+}
+
+/// File format for transcript logs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscriptFormat {
+    /// One `[HH:MM:SS] text` line per cue.
+    #[default]
+    Text,
+    /// One JSON object per cue.
+    Jsonl,
+}
+
+/// Transcript logging of finalized captions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptConfig {
+    /// Log from startup. Also toggled from the tray.
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub format: TranscriptFormat,
+    /// Start a new file once the current one reaches this many megabytes (0 = never).
+    #[serde(default = "default_rotate_size_mb")]
+    pub rotate_size_mb: u64,
+    /// Start a new file once the current one is this many hours old (0 = never).
+    #[serde(default = "default_rotate_hours")]
+    pub rotate_hours: u64,
+}
+
+fn default_rotate_size_mb() -> u64 {
+    10
+}
+
+fn default_rotate_hours() -> u64 {
+    24
+}
+
+impl Default for TranscriptConfig {
+    fn default() -> Self {
+        TranscriptConfig {
+            enabled: false,
+            format: TranscriptFormat::default(),
+            rotate_size_mb: default_rotate_size_mb(),
+            rotate_hours: default_rotate_hours(),
+        }
+    }
+}
+
 /// Position of the overlay window in floating mode.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct OverlayPosition {
@@ -76,25 +257,90 @@ pub struct AppearanceConfig {
     /// Caption area height in pixels (0 = auto/natural size).
     #[serde(default)]
     pub height: i32,
+    /// Column width in pixels when docked to the left or right edge, in place of `width`.
+    #[serde(default = "default_side_width")]
+    pub side_width: i32,
+    /// Maximum number of caption lines when docked to the left or right edge, in place of
+    /// `max_lines`.
+    #[serde(default = "default_side_max_lines")]
+    pub side_max_lines: u32,
     /// Seconds before an idle caption line expires and is removed.
     #[serde(default = "default_expire_secs")]
     pub expire_secs: u64,
-    /// Fraction of estimated max characters to use per line (0.0–1.0).
-    /// Lower values add more visual padding. Default 0.95 (5% padding).
-    #[serde(default = "default_char_width_fraction")]
-    pub char_width_fraction: f32,
+    /// Font family, e.g. "Inter" (empty = the desktop's UI font).
+    #[serde(default)]
+    pub font_family: String,
+    /// CSS font weight, 100–900 (400 = regular, 700 = bold).
+    #[serde(default = "default_font_weight")]
+    pub font_weight: u16,
+    /// Line height as a multiple of the font size (0 = the font's own spacing).
+    #[serde(default)]
+    pub line_spacing: f32,
+    /// Width in pixels of an outline drawn around the text (0 = none).
+    #[serde(default)]
+    pub outline_width: f32,
+    /// CSS color string for the outline.
+    #[serde(default = "default_outline_color")]
+    pub outline_color: String,
+    /// Extra CSS text-shadow, e.g. "2px 2px 4px rgba(0,0,0,0.8)" (empty = none).
+    #[serde(default)]
+    pub text_shadow: String,
+    /// Corner radius of the overlay background in pixels.
+    #[serde(default = "default_border_radius")]
+    pub border_radius: u32,
+    /// Horizontal padding around the text in pixels.
+    #[serde(default = "default_padding_x")]
+    pub padding_x: i32,
+    /// Vertical padding around the text in pixels.
+    #[serde(default = "default_padding_y")]
+    pub padding_y: i32,
+    #[serde(default)]
+    pub text_align: TextAlign,
+}
+
+/// Horizontal alignment of caption lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
 }
 
 fn default_width() -> i32 {
     600
 }
 
-fn default_expire_secs() -> u64 {
+fn default_side_width() -> i32 {
+    280
+}
+
+fn default_side_max_lines() -> u32 {
+    12
+}
+
+fn default_font_weight() -> u16 {
+    400
+}
+
+fn default_outline_color() -> String {
+    "#000000".to_string()
+}
+
+fn default_border_radius() -> u32 {
+    12
+}
+
+fn default_padding_x() -> i32 {
+    12
+}
+
+fn default_padding_y() -> i32 {
     8
 }
 
-fn default_char_width_fraction() -> f32 {
-    0.95
+fn default_expire_secs() -> u64 {
+    8
 }
 
 impl Default for AppearanceConfig {
@@ -106,8 +352,19 @@ impl Default for AppearanceConfig {
             max_lines: 3,
             width: 600,
             height: 0,
+            side_width: default_side_width(),
+            side_max_lines: default_side_max_lines(),
             expire_secs: 8,
-            char_width_fraction: 0.95,
+            font_family: String::new(),
+            font_weight: default_font_weight(),
+            line_spacing: 0.0,
+            outline_width: 0.0,
+            outline_color: default_outline_color(),
+            text_shadow: String::new(),
+            border_radius: default_border_radius(),
+            padding_x: default_padding_x(),
+            padding_y: default_padding_y(),
+            text_align: TextAlign::default(),
         }
     }
 }
@@ -121,14 +378,156 @@ impl AppearanceConfig {
             self.expire_secs
         }
     }
+}
+
+/// `[appearance]` keys a theme resets to their default when it leaves them out, so switching
+/// themes never keeps parts of the previous one. Other keys (size, line counts, expiry)
+/// keep their current value unless the theme sets them.
+const THEME_STYLE_KEYS: &[&str] = &[
+    "background_color",
+    "text_color",
+    "font_family",
+    "font_weight",
+    "line_spacing",
+    "outline_width",
+    "outline_color",
+    "text_shadow",
+    "border_radius",
+    "padding_x",
+    "padding_y",
+    "text_align",
+];
+
+/// A named style preset: `[appearance]` keys to change, e.g. colours, font and spacing.
+/// See `THEME_STYLE_KEYS` for what happens to the keys it leaves out.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Theme(pub toml::Table);
+
+impl Theme {
+    /// `appearance` restyled with this theme. An invalid theme is ignored with a warning.
+    pub fn apply(&self, appearance: &AppearanceConfig) -> AppearanceConfig {
+        let Ok(toml::Value::Table(defaults)) = toml::Value::try_from(AppearanceConfig::default()) else {
+            return appearance.clone();
+        };
+        let mut changes: toml::Table =
+            defaults.into_iter().filter(|(key, _)| THEME_STYLE_KEYS.contains(&key.as_str())).collect();
+        changes.extend(self.0.clone());
+        merge_appearance(appearance, &changes).unwrap_or_else(|e| {
+            eprintln!("warn: ignoring invalid theme: {e}");
+            appearance.clone()
+        })
+    }
+}
 
-    /// Returns the effective char_width_fraction, clamped to (0.0, 1.0] with default fallback.
-    pub fn effective_char_width_fraction(&self) -> f32 {
-        if self.char_width_fraction <= 0.0 || self.char_width_fraction > 1.0 {
-            default_char_width_fraction()
-        } else {
-            self.char_width_fraction
+/// `base` with some of its `[appearance]` keys replaced.
+fn merge_appearance(base: &AppearanceConfig, changes: &toml::Table) -> Result<AppearanceConfig> {
+    let toml::Value::Table(mut table) = toml::Value::try_from(base)? else {
+        unreachable!("AppearanceConfig serializes to a table");
+    };
+    table.extend(changes.clone());
+    Ok(toml::Value::Table(table).try_into()?)
+}
+
+/// Themes that ship with subtidal, as (config key, menu label).
+pub const BUILTIN_THEMES: &[(&str, &str)] = &[
+    ("high_contrast", "High Contrast"),
+    ("broadcast", "Broadcast"),
+    ("subtle", "Subtle"),
+    ("large_print", "Large Print"),
+];
+
+fn builtin_theme(key: &str) -> Option<Theme> {
+    let theme = match key {
+        // Opaque black behind bold white text.
+        "high_contrast" => toml::toml! {
+            background_color = "#000000"
+            text_color = "#ffffff"
+            font_weight = 700
+        },
+        // TV-style caption box: square, tight and nearly opaque.
+        "broadcast" => toml::toml! {
+            background_color = "rgba(0,0,0,0.9)"
+            text_color = "#ffffff"
+            border_radius = 0
+            padding_x = 8
+            padding_y = 4
+            text_align = "center"
+        },
+        // Faint background; an outline keeps the text readable over bright video.
+        "subtle" => toml::toml! {
+            background_color = "rgba(0,0,0,0.3)"
+            text_color = "rgba(255,255,255,0.95)"
+            outline_width = 1.0
+            outline_color = "rgba(0,0,0,0.6)"
+            border_radius = 16
+        },
+        "large_print" => toml::toml! {
+            font_size = 28.0
+            font_weight = 600
+            line_spacing = 1.3
+            padding_x = 16
+            padding_y = 12
+        },
+        _ => return None,
+    };
+    Some(Theme(theme))
+}
+
+/// An extra overlay window showing the same captions on another monitor, e.g. a projector.
+/// Mirrors are click-through; their placement is set here rather than by dragging.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MirrorConfig {
+    /// Connector name of the monitor, e.g. "HDMI-A-1". The mirror is hidden while it is
+    /// disconnected.
+    pub monitor: String,
+    #[serde(default)]
+    pub overlay_mode: OverlayMode,
+    #[serde(default)]
+    pub screen_edge: ScreenEdge,
+    #[serde(default)]
+    pub dock_position: DockPosition,
+    /// Position in floating mode.
+    #[serde(default)]
+    pub position: OverlayPosition,
+    /// `[appearance]` keys to change for this window, e.g. a larger font_size. Other keys
+    /// follow the main appearance.
+    #[serde(default, skip_serializing_if = "toml::Table::is_empty")]
+    pub appearance: toml::Table,
+}
+
+impl MirrorConfig {
+    /// The main appearance with this mirror's changes applied. Invalid changes are ignored
+    /// with a warning.
+    pub fn appearance(&self, base: &AppearanceConfig) -> AppearanceConfig {
+        if self.appearance.is_empty() {
+            return base.clone();
         }
+        merge_appearance(base, &self.appearance).unwrap_or_else(|e| {
+            eprintln!("warn: ignoring appearance of mirror on {}: {e}", self.monitor);
+            base.clone()
+        })
+    }
+}
+
+/// Local caption server for OBS browser sources.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebConfig {
+    /// Serve the caption page and WebSocket stream on 127.0.0.1. Applied at startup.
+    #[serde(default)]
+    pub enabled: bool,
+    /// TCP port to listen on.
+    #[serde(default = "default_web_port")]
+    pub port: u16,
+}
+
+fn default_web_port() -> u16 {
+    8765
+}
+
+impl Default for WebConfig {
+    fn default() -> Self {
+        WebConfig { enabled: false, port: default_web_port() }
     }
 }
 
@@ -152,9 +551,27 @@ pub struct Config {
     #[serde(default)]
     pub engine: Engine,
 
-    /// Active audio source.
+    /// Audio sources captured and mixed into one caption stream.
+    #[serde(default)]
+    pub audio_sources: Vec<SourceConfig>,
+
+    /// Run one recognizer per audio source and label captions by source instead of
+    /// mixing all sources into one stream. Loads one model instance per source.
     #[serde(default)]
-    pub audio_source: AudioSource,
+    pub separate_sources: bool,
+
+    /// How long another application must keep playing, while the followed one is silent,
+    /// before an `auto` source switches to it.
+    #[serde(default = "default_auto_follow_hold_secs")]
+    pub auto_follow_hold_secs: u64,
+
+    /// Voice activity detection: skip inference on silence. Applied at startup.
+    #[serde(default)]
+    pub vad_aggressiveness: VadAggressiveness,
+
+    /// Single-source setting from older configs; migrated into `audio_sources` on load.
+    #[serde(default, skip_serializing)]
+    audio_source: Option<AudioSource>,
 
     /// Overlay display mode.
     #[serde(default)]
@@ -164,10 +581,20 @@ pub struct Config {
     #[serde(default)]
     pub screen_edge: ScreenEdge,
 
-    /// Window position in floating mode.
+    /// Window position in floating mode: the last one dragged to, used on monitors without
+    /// a position of their own.
     #[serde(default)]
     pub position: OverlayPosition,
 
+    /// Floating positions per monitor, by connector name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub monitor_positions: BTreeMap<String, OverlayPosition>,
+
+    /// Connector name of the monitor to show the overlay on, e.g. "DP-1" (unset = the
+    /// compositor's choice). While it is disconnected the compositor chooses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monitor: Option<String>,
+
     /// Whether the floating overlay is locked (click-through).
     #[serde(default = "default_locked")]
     pub locked: bool,
@@ -176,10 +603,45 @@ pub struct Config {
     #[serde(default)]
     pub dock_position: DockPosition,
 
+    /// In docked mode, reserve the overlay's space at its edge so that tiling compositors
+    /// shrink other windows instead of letting the overlay cover them.
+    #[serde(default)]
+    pub reserve_space: bool,
+
+    /// Layer-shell layer of the overlay.
+    #[serde(default)]
+    pub layer: OverlayLayer,
+
+    /// Hide the captions while the focused window is fullscreen on the overlay's monitor.
+    /// Needs a compositor with wlr-foreign-toplevel-management (e.g. Sway, Hyprland).
+    #[serde(default)]
+    pub auto_hide_fullscreen: bool,
+
     /// Caption text appearance.
     #[serde(default)]
     pub appearance: AppearanceConfig,
 
+    /// Format of subtitle files recorded from the tray.
+    #[serde(default)]
+    pub subtitle_format: SubtitleFormat,
+
+    /// Transcript logging of finalized captions.
+    #[serde(default)]
+    pub transcript: TranscriptConfig,
+
+    /// Caption server for browser sources.
+    #[serde(default)]
+    pub web: WebConfig,
+
+    /// Extra overlay windows mirroring the captions on other monitors.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mirrors: Vec<MirrorConfig>,
+
+    /// User-defined themes (`[themes.<name>]`), listed in the tray's Style menu after the
+    /// built-in ones. A theme named like a built-in one replaces it.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub themes: BTreeMap<String, Theme>,
+
     /// Path to config file, set by load_from(). Used by save().
     #[serde(skip)]
     pub config_file_path: Option<PathBuf>,
@@ -189,17 +651,35 @@ fn default_locked() -> bool {
     true
 }
 
+fn default_auto_follow_hold_secs() -> u64 {
+    3
+}
+
 impl Default for Config {
     fn default() -> Self {
         Config {
             engine: Engine::default(),
-            audio_source: AudioSource::default(),
+            audio_sources: vec![SourceConfig::default()],
+            separate_sources: false,
+            auto_follow_hold_secs: default_auto_follow_hold_secs(),
+            vad_aggressiveness: VadAggressiveness::default(),
+            audio_source: None,
             overlay_mode: OverlayMode::default(),
             screen_edge: ScreenEdge::default(),
             position: OverlayPosition::default(),
+            monitor_positions: BTreeMap::new(),
+            monitor: None,
             locked: true,
             dock_position: DockPosition::default(),
+            reserve_space: false,
+            layer: OverlayLayer::default(),
+            auto_hide_fullscreen: false,
             appearance: AppearanceConfig::default(),
+            subtitle_format: SubtitleFormat::default(),
+            transcript: TranscriptConfig::default(),
+            web: WebConfig::default(),
+            mirrors: Vec::new(),
+            themes: BTreeMap::new(),
             config_file_path: None,
         }
     }
@@ -224,6 +704,72 @@ impl Config {
         }
     }
 
+    /// Whether the overlay is docked to the left or right edge.
+    pub fn is_side_docked(&self) -> bool {
+        self.overlay_mode == OverlayMode::Docked
+            && matches!(self.screen_edge, ScreenEdge::Left | ScreenEdge::Right)
+    }
+
+    /// Appearance the overlay is laid out with. Docked to the left or right edge, captions
+    /// fill a narrow column that grows downwards: `side_width` and `side_max_lines` stand in
+    /// for `width` and `max_lines`, and `height` is ignored.
+    pub fn layout_appearance(&self) -> AppearanceConfig {
+        let mut appearance = self.appearance.clone();
+        if self.is_side_docked() {
+            appearance.width = appearance.side_width;
+            appearance.max_lines = appearance.side_max_lines;
+            appearance.height = 0;
+        }
+        appearance
+    }
+
+    /// Settings for a mirror's window: this config with the mirror's monitor, placement and
+    /// appearance. Mirrors are always locked.
+    pub fn for_mirror(&self, mirror: &MirrorConfig) -> Config {
+        Config {
+            monitor: Some(mirror.monitor.clone()),
+            overlay_mode: mirror.overlay_mode.clone(),
+            screen_edge: mirror.screen_edge.clone(),
+            dock_position: mirror.dock_position.clone(),
+            position: mirror.position.clone(),
+            monitor_positions: BTreeMap::new(),
+            locked: true,
+            appearance: mirror.appearance(&self.appearance),
+            mirrors: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    /// Floating position on the monitor with the given connector.
+    pub fn position_on(&self, connector: Option<&str>) -> OverlayPosition {
+        connector
+            .and_then(|c| self.monitor_positions.get(c))
+            .unwrap_or(&self.position)
+            .clone()
+    }
+
+    /// Remember a floating position for the monitor with the given connector.
+    pub fn set_position_on(&mut self, connector: Option<&str>, position: OverlayPosition) {
+        if let Some(c) = connector {
+            self.monitor_positions.insert(c.to_string(), position.clone());
+        }
+        self.position = position;
+    }
+
+    /// All themes as (key, menu label, theme): the built-in ones, then those from the config.
+    pub fn themes(&self) -> Vec<(String, String, Theme)> {
+        let builtin = BUILTIN_THEMES.iter().map(|(key, label)| {
+            let theme = self.themes.get(*key).cloned().or_else(|| builtin_theme(key)).unwrap_or_default();
+            (key.to_string(), label.to_string(), theme)
+        });
+        let custom = self
+            .themes
+            .iter()
+            .filter(|(key, _)| !BUILTIN_THEMES.iter().any(|(k, _)| k == key))
+            .map(|(key, theme)| (key.clone(), key.clone(), theme.clone()));
+        builtin.chain(custom).collect()
+    }
+
     /// Load config from disk. If the file does not exist, returns `Default::default()`.
     /// If the file exists but is malformed, logs a warning and returns `Default::default()`.
     pub fn load() -> Config {
@@ -245,10 +791,24 @@ impl Config {
         let text = std::fs::read_to_string(path)
             .with_context(|| format!("reading {}", path.display()))?;
         let mut cfg: Config = toml::from_str(&text).with_context(|| format!("parsing {}", path.display()))?;
+        cfg.migrate_audio_sources();
         cfg.config_file_path = Some(path.to_path_buf());
         Ok(cfg)
     }
 
+    /// Move a legacy `audio_source` into `audio_sources`, and make sure at least one
+    /// source is configured (system output if nothing else).
+    fn migrate_audio_sources(&mut self) {
+        if let Some(legacy) = self.audio_source.take() {
+            if self.audio_sources.is_empty() {
+                self.audio_sources.push(SourceConfig::new(legacy));
+            }
+        }
+        if self.audio_sources.is_empty() {
+            self.audio_sources.push(SourceConfig::default());
+        }
+    }
+
     /// Persist the current config to disk. Creates parent directories if needed.
     /// If config_file_path is set, saves to that path; otherwise uses default config_path().
     pub fn save(&self) -> Result<()> {
@@ -269,7 +829,8 @@ impl Config {
 }
 
 /// Start watching config.toml for changes. When config changes on disk,
-/// sends UpdateAppearance to the overlay and updates the tray state.
+/// sends UpdateAppearance to the overlay, re-applies the audio source mix
+/// (e.g. edited gains) and updates the tray state.
 ///
 /// Returns the debouncer watcher (must be kept alive for the lifetime of the watch).
 /// Drop the returned watcher to stop watching.
@@ -279,6 +840,7 @@ impl Config {
 /// so this is accepted as a trade-off for simplicity.
 pub fn start_hot_reload(
     overlay_tx: std::sync::mpsc::Sender<crate::overlay::OverlayCommand>,
+    audio_tx: std::sync::mpsc::SyncSender<crate::audio::AudioCommand>,
     tray_handle: ksni::Handle<crate::tray::TrayState>,
     tokio_handle: tokio::runtime::Handle,
 ) -> anyhow::Result<notify_debouncer_mini::Debouncer<notify::RecommendedWatcher>> {
@@ -296,6 +858,13 @@ pub fn start_hot_reload(
     let prev_appearance = std::sync::Mutex::new(initial_cfg.appearance.clone());
     let prev_mode = std::sync::Mutex::new(initial_cfg.overlay_mode);
     let prev_locked = std::sync::Mutex::new(initial_cfg.locked);
+    let prev_sources = std::sync::Mutex::new(initial_cfg.audio_sources);
+    let prev_auto_hold = std::sync::Mutex::new(initial_cfg.auto_follow_hold_secs);
+    let prev_monitor = std::sync::Mutex::new(initial_cfg.monitor);
+    let prev_reserve_space = std::sync::Mutex::new(initial_cfg.reserve_space);
+    let prev_layer = std::sync::Mutex::new(initial_cfg.layer);
+    let prev_auto_hide = std::sync::Mutex::new(initial_cfg.auto_hide_fullscreen);
+    let prev_mirrors = std::sync::Mutex::new(initial_cfg.mirrors);
 
     // Debounce at 500ms: multiple rapid writes (e.g. from an editor) collapse into one event.
     let mut debouncer = new_debouncer(Duration::from_millis(500), move |result: DebounceEventResult| {
@@ -331,6 +900,62 @@ pub fn start_hot_reload(
                                 *prev = new_cfg.locked;
                             }
                         }
+                        if let Ok(mut prev) = prev_monitor.lock() {
+                            if *prev != new_cfg.monitor {
+                                let _ = overlay_tx.send(
+                                    crate::overlay::OverlayCommand::SetMonitor(new_cfg.monitor.clone())
+                                );
+                                *prev = new_cfg.monitor.clone();
+                            }
+                        }
+                        if let Ok(mut prev) = prev_reserve_space.lock() {
+                            if *prev != new_cfg.reserve_space {
+                                let _ = overlay_tx.send(
+                                    crate::overlay::OverlayCommand::SetReserveSpace(new_cfg.reserve_space)
+                                );
+                                *prev = new_cfg.reserve_space;
+                            }
+                        }
+                        if let Ok(mut prev) = prev_layer.lock() {
+                            if *prev != new_cfg.layer {
+                                let _ = overlay_tx.send(
+                                    crate::overlay::OverlayCommand::SetLayer(new_cfg.layer)
+                                );
+                                *prev = new_cfg.layer;
+                            }
+                        }
+                        if let Ok(mut prev) = prev_auto_hide.lock() {
+                            if *prev != new_cfg.auto_hide_fullscreen {
+                                let _ = overlay_tx.send(
+                                    crate::overlay::OverlayCommand::SetAutoHideFullscreen(new_cfg.auto_hide_fullscreen)
+                                );
+                                *prev = new_cfg.auto_hide_fullscreen;
+                            }
+                        }
+                        if let Ok(mut prev) = prev_mirrors.lock() {
+                            if *prev != new_cfg.mirrors {
+                                let _ = overlay_tx.send(
+                                    crate::overlay::OverlayCommand::SetMirrors(new_cfg.mirrors.clone())
+                                );
+                                *prev = new_cfg.mirrors.clone();
+                            }
+                        }
+                        if let Ok(mut prev) = prev_sources.lock() {
+                            if *prev != new_cfg.audio_sources {
+                                let _ = audio_tx.send(
+                                    crate::audio::AudioCommand::SetSources(new_cfg.audio_sources.clone())
+                                );
+                                *prev = new_cfg.audio_sources.clone();
+                            }
+                        }
+                        if let Ok(mut prev) = prev_auto_hold.lock() {
+                            if *prev != new_cfg.auto_follow_hold_secs {
+                                let _ = audio_tx.send(crate::audio::AudioCommand::SetAutoFollowHold(
+                                    Duration::from_secs(new_cfg.auto_follow_hold_secs),
+                                ));
+                                *prev = new_cfg.auto_follow_hold_secs;
+                            }
+                        }
                         // Update tray to reflect new config state.
                         let tray_handle = tray_handle.clone();
                         tokio_handle.block_on(async {
@@ -338,6 +963,8 @@ pub fn start_hot_reload(
                                 tray.active_engine = new_cfg.engine.clone();
                                 tray.overlay_mode = new_cfg.overlay_mode.clone();
                                 tray.locked = new_cfg.locked;
+                                tray.monitor = new_cfg.monitor.clone();
+                                tray.set_active_sources(new_cfg.audio_sources.clone());
                             }).await;
                         });
                     }
@@ -411,7 +1038,114 @@ mod tests {
         let cfg = Config::load_from(&path).unwrap();
         assert_eq!(cfg.engine, Engine::Nemotron);
         assert!(cfg.locked);
+        assert!(!cfg.reserve_space);
+        assert_eq!(cfg.layer, OverlayLayer::Top);
+        assert!(!cfg.auto_hide_fullscreen);
         assert_eq!(cfg.screen_edge, ScreenEdge::Bottom);
+        assert_eq!(cfg.web, WebConfig { enabled: false, port: 8765 });
+    }
+
+    #[test]
+    fn audio_sources_roundtrip_with_gain() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let original = Config {
+            audio_sources: vec![
+                SourceConfig::new(AudioSource::SystemOutput),
+                SourceConfig {
+                    source: AudioSource::Input { node_id: 42, node_name: "alsa_input.usb".to_string() },
+                    gain: 0.5,
+                    label: Some("Me".to_string()),
+                    color: Some("#8ab4f8".to_string()),
+                },
+                SourceConfig::new(AudioSource::Application {
+                    node_id: 93,
+                    node_name: "Firefox".to_string(),
+                    app_name: Some("Firefox".to_string()),
+                    binary: Some("firefox".to_string()),
+                }),
+            ],
+            ..Config::default()
+        };
+        fs::write(&path, toml::to_string_pretty(&original).unwrap()).unwrap();
+        let loaded = Config::load_from(&path).unwrap();
+        assert_eq!(loaded.audio_sources, original.audio_sources);
+    }
+
+    #[test]
+    fn legacy_audio_source_is_migrated() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("legacy.toml");
+        fs::write(
+            &path,
+            "[audio_source]\ntype = \"application\"\nnode_id = 7\nnode_name = \"firefox\"\n",
+        )
+        .unwrap();
+        let cfg = Config::load_from(&path).unwrap();
+        assert_eq!(
+            cfg.audio_sources,
+            vec![SourceConfig::new(AudioSource::Application {
+                node_id: 7,
+                node_name: "firefox".to_string(),
+                app_name: None,
+                binary: None,
+            })]
+        );
+    }
+
+    #[test]
+    fn missing_audio_sources_defaults_to_system_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("empty.toml");
+        fs::write(&path, "").unwrap();
+        let cfg = Config::load_from(&path).unwrap();
+        assert_eq!(cfg.audio_sources, vec![SourceConfig::default()]);
+    }
+
+    #[test]
+    fn source_display_label_defaults() {
+        assert_eq!(SourceConfig::default().display_label(), "System");
+        let mic = SourceConfig::new(AudioSource::Input { node_id: 1, node_name: "alsa_input".to_string() });
+        assert_eq!(mic.display_label(), "Me");
+        let app = SourceConfig::new(AudioSource::Application {
+            node_id: 2,
+            node_name: "Firefox".to_string(),
+            app_name: None,
+            binary: None,
+        });
+        assert_eq!(app.display_label(), "Firefox");
+        let named = SourceConfig { label: Some("Remote".to_string()), ..app };
+        assert_eq!(named.display_label(), "Remote");
+    }
+
+    #[test]
+    fn same_source_ignores_node_id_hint() {
+        let firefox = |node_id| AudioSource::Application {
+            node_id,
+            node_name: "Firefox".to_string(),
+            app_name: Some("Firefox".to_string()),
+            binary: Some("firefox".to_string()),
+        };
+        assert!(firefox(7).same_source(&firefox(93)));
+        let mpv = AudioSource::Application {
+            node_id: 7,
+            node_name: "mpv".to_string(),
+            app_name: Some("mpv".to_string()),
+            binary: Some("mpv".to_string()),
+        };
+        assert!(!firefox(7).same_source(&mpv));
+        assert!(AudioSource::SystemOutput.same_source(&AudioSource::SystemOutput));
+    }
+
+    #[test]
+    fn auto_source_parses_with_hold_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("auto.toml");
+        fs::write(&path, "auto_follow_hold_secs = 5\n\n[[audio_sources]]\ntype = \"auto\"\n").unwrap();
+        let cfg = Config::load_from(&path).unwrap();
+        assert_eq!(cfg.audio_sources, vec![SourceConfig::new(AudioSource::Auto)]);
+        assert_eq!(cfg.auto_follow_hold_secs, 5);
+        assert_eq!(Config::default().auto_follow_hold_secs, 3);
     }
 
     /// AC2.1: Unknown engine value in TOML defaults to Nemotron.
@@ -520,4 +1254,130 @@ mod tests {
             "effective_expire_secs() should return 8 for default value"
         );
     }
+
+    #[test]
+    fn appearance_style_options_parse_and_default() {
+        let toml_content = "[appearance]\nbackground_color = \"rgba(0,0,0,0.7)\"\ntext_color = \"#ffffff\"\nfont_size = 16.0\nmax_lines = 3\nfont_family = \"Inter\"\nfont_weight = 700\noutline_width = 2.0\ntext_align = \"center\"\n";
+        let cfg: Config = toml::from_str(toml_content).unwrap();
+        assert_eq!(cfg.appearance.font_family, "Inter");
+        assert_eq!(cfg.appearance.font_weight, 700);
+        assert_eq!(cfg.appearance.outline_width, 2.0);
+        assert_eq!(cfg.appearance.text_align, TextAlign::Center);
+        // Unset options keep the previous look.
+        assert_eq!(cfg.appearance.outline_color, "#000000");
+        assert_eq!(cfg.appearance.border_radius, 12);
+        assert_eq!((cfg.appearance.padding_x, cfg.appearance.padding_y), (12, 8));
+        assert_eq!(cfg.appearance.line_spacing, 0.0);
+    }
+
+    #[test]
+    fn floating_positions_are_kept_per_monitor() {
+        let mut cfg = Config::default();
+        cfg.set_position_on(Some("DP-1"), OverlayPosition { x: 10, y: 20 });
+        cfg.set_position_on(Some("HDMI-A-1"), OverlayPosition { x: 300, y: 40 });
+        assert_eq!(cfg.position_on(Some("DP-1")), OverlayPosition { x: 10, y: 20 });
+        // Unknown monitors, and an unknown current monitor, get the last position.
+        assert_eq!(cfg.position_on(Some("DP-2")), OverlayPosition { x: 300, y: 40 });
+        assert_eq!(cfg.position_on(None), OverlayPosition { x: 300, y: 40 });
+
+        cfg.monitor = Some("DP-1".to_string());
+        let text = toml::to_string_pretty(&cfg).unwrap();
+        let reloaded: Config = toml::from_str(&text).unwrap();
+        assert_eq!(reloaded.monitor.as_deref(), Some("DP-1"));
+        assert_eq!(reloaded.monitor_positions, cfg.monitor_positions);
+    }
+
+    #[test]
+    fn side_dock_lays_out_a_column() {
+        let toml_content = "overlay_mode = \"docked\"\nscreen_edge = \"right\"\n\n[appearance]\nbackground_color = \"rgba(0,0,0,0.7)\"\ntext_color = \"#ffffff\"\nfont_size = 16.0\nmax_lines = 3\nwidth = 800\nheight = 120\nside_width = 300\n";
+        let mut cfg: Config = toml::from_str(toml_content).unwrap();
+        assert!(cfg.is_side_docked());
+        let layout = cfg.layout_appearance();
+        assert_eq!((layout.width, layout.max_lines, layout.height), (300, 12, 0));
+
+        cfg.screen_edge = ScreenEdge::Bottom;
+        assert_eq!(cfg.layout_appearance(), cfg.appearance);
+        cfg.screen_edge = ScreenEdge::Left;
+        cfg.overlay_mode = OverlayMode::Floating;
+        assert_eq!(cfg.layout_appearance(), cfg.appearance);
+    }
+
+    #[test]
+    fn layer_and_fullscreen_options_parse() {
+        let cfg: Config = toml::from_str("layer = \"overlay\"\nauto_hide_fullscreen = true\n").unwrap();
+        assert_eq!(cfg.layer, OverlayLayer::Overlay);
+        assert!(cfg.auto_hide_fullscreen);
+        assert!(toml::from_str::<Config>("layer = \"front\"\n").is_err());
+    }
+
+    #[test]
+    fn mirrors_override_placement_and_appearance() {
+        let toml_content = "[appearance]\nbackground_color = \"rgba(0,0,0,0.7)\"\ntext_color = \"#ffffff\"\nfont_size = 16.0\nmax_lines = 3\nwidth = 800\n\n[[mirrors]]\nmonitor = \"HDMI-A-1\"\nscreen_edge = \"top\"\n\n[mirrors.appearance]\nfont_size = 32.0\nmax_lines = 2\n\n[[mirrors]]\nmonitor = \"eDP-1\"\noverlay_mode = \"floating\"\nposition = { x = 5, y = 6 }\n";
+        let cfg: Config = toml::from_str(toml_content).unwrap();
+        assert_eq!(cfg.mirrors.len(), 2);
+
+        let projector = cfg.for_mirror(&cfg.mirrors[0]);
+        assert_eq!(projector.monitor.as_deref(), Some("HDMI-A-1"));
+        assert_eq!(projector.screen_edge, ScreenEdge::Top);
+        assert!(projector.locked);
+        assert_eq!(projector.appearance.font_size, 32.0);
+        assert_eq!(projector.appearance.max_lines, 2);
+        assert_eq!(projector.appearance.width, 800, "keys not overridden follow [appearance]");
+
+        let laptop = cfg.for_mirror(&cfg.mirrors[1]);
+        assert_eq!(laptop.overlay_mode, OverlayMode::Floating);
+        assert_eq!(laptop.position_on(Some("eDP-1")), OverlayPosition { x: 5, y: 6 });
+        assert_eq!(laptop.appearance, cfg.appearance);
+
+        // A bad override is ignored rather than failing the whole config.
+        let mut bad = cfg.mirrors[0].clone();
+        bad.appearance.insert("font_size".to_string(), toml::Value::String("huge".to_string()));
+        assert_eq!(bad.appearance(&cfg.appearance), cfg.appearance);
+    }
+
+    #[test]
+    fn custom_themes_follow_builtins_and_can_replace_them() {
+        let toml_content = "[themes.solarized]\nbackground_color = \"#002b36\"\ntext_color = \"#93a1a1\"\n\n[themes.large_print]\nfont_size = 32.0\n";
+        let cfg: Config = toml::from_str(toml_content).unwrap();
+        let themes = cfg.themes();
+        let labels: Vec<&str> = themes.iter().map(|(_, label, _)| label.as_str()).collect();
+        assert_eq!(labels, vec!["High Contrast", "Broadcast", "Subtle", "Large Print", "solarized"]);
+        assert_eq!(themes[3].2.0["font_size"].as_float(), Some(32.0));
+        assert_eq!(themes[4].2.0["background_color"].as_str(), Some("#002b36"));
+
+        // Themes survive a save.
+        let text = toml::to_string_pretty(&cfg).unwrap();
+        let reloaded: Config = toml::from_str(&text).unwrap();
+        assert_eq!(reloaded.themes, cfg.themes);
+    }
+
+    #[test]
+    fn theme_resets_style_but_keeps_size() {
+        let current = AppearanceConfig {
+            width: 900,
+            max_lines: 2,
+            font_size: 22.0,
+            font_weight: 700,
+            outline_width: 2.0,
+            ..AppearanceConfig::default()
+        };
+        let subtle = Config::default().themes().into_iter().find(|(key, _, _)| key == "subtle").unwrap().2;
+        let styled = subtle.apply(&current);
+        assert_eq!((styled.width, styled.max_lines, styled.font_size), (900, 2, 22.0));
+        assert_eq!((styled.side_width, styled.side_max_lines), (current.side_width, current.side_max_lines));
+        assert_eq!(styled.font_weight, 400, "options the theme leaves out are reset");
+        assert_eq!(styled.outline_width, 1.0);
+        assert_eq!(styled.background_color, "rgba(0,0,0,0.3)");
+        // Applying the same theme again changes nothing.
+        assert_eq!(subtle.apply(&styled), styled);
+        // A theme that sets the font size does change it.
+        let large = Config::default().themes().into_iter().find(|(key, _, _)| key == "large_print").unwrap().2;
+        assert_eq!(large.apply(&current).font_size, 28.0);
+    }
+
+    #[test]
+    fn invalid_theme_is_ignored() {
+        let bad = Theme(toml::toml! { font_weight = "heavy" });
+        assert_eq!(bad.apply(&AppearanceConfig::default()), AppearanceConfig::default());
+    }
 }
@@ -0,0 +1,209 @@
+//! D-Bus control interface (`org.subtidal.Captions` on the session bus).
+//!
+//! Methods drive the running instance the same way the tray menu does: each one runs a
+//! `TrayState` method through the ksni handle, which sends the `OverlayCommand` or
+//! `AudioCommand` and persists the change. Every caption fragment is also broadcast as a
+//! `Caption` signal, for scripts that want the text.
+
+use crate::audio::{parse_source_spec, source_spec, NodeKind};
+use crate::config::{AudioSource, OverlayMode};
+use crate::stt::{samples_to_ms, Caption};
+use crate::tray::TrayState;
+use anyhow::{Context, Result};
+use zbus::fdo;
+use zbus::object_server::SignalEmitter;
+
+/// Well-known bus name, also used as the interface name.
+pub const BUS_NAME: &str = "org.subtidal.Captions";
+/// Object path the interface is served at.
+pub const OBJECT_PATH: &str = "/org/subtidal/Captions";
+
+/// The exported object. All state lives in the tray; this only forwards to it.
+struct CaptionsService {
+    tray: ksni::Handle<TrayState>,
+}
+
+impl CaptionsService {
+    /// Run `f` on the tray state, refreshing the menu afterwards.
+    async fn with_tray<R>(&self, f: impl FnOnce(&mut TrayState) -> R) -> fdo::Result<R> {
+        self.tray
+            .update(f)
+            .await
+            .ok_or_else(|| fdo::Error::Failed("tray service has shut down".to_string()))
+    }
+
+    /// Resolve a source spec against the live node list.
+    async fn resolve_source(&self, spec: &str) -> fdo::Result<AudioSource> {
+        let nodes = self.with_tray(|t| crate::audio::list_nodes(&t.node_list)).await?;
+        parse_source_spec(spec, &nodes)
+            .ok_or_else(|| fdo::Error::InvalidArgs(format!("no audio source matches '{spec}'")))
+    }
+}
+
+#[zbus::interface(name = "org.subtidal.Captions")]
+impl CaptionsService {
+    /// Show or hide captions, like left-clicking the tray icon.
+    async fn toggle_captions(&self) -> fdo::Result<()> {
+        self.with_tray(|t| t.toggle_captions()).await
+    }
+
+    async fn set_captions_enabled(&self, enabled: bool) -> fdo::Result<()> {
+        self.with_tray(move |t| {
+            if t.captions_enabled.load(std::sync::atomic::Ordering::Relaxed) != enabled {
+                t.toggle_captions();
+            }
+        })
+        .await
+    }
+
+    /// Capture only the given source. `spec` is "system", "auto", a PipeWire node ID, or
+    /// (part of) a node's name, application name or description.
+    async fn select_audio_source(&self, spec: &str) -> fdo::Result<()> {
+        let source = self.resolve_source(spec).await?;
+        self.with_tray(move |t| t.select_source(source)).await
+    }
+
+    /// Add the given source to the capture mix, or remove it if already selected.
+    async fn toggle_audio_source(&self, spec: &str) -> fdo::Result<()> {
+        let source = self.resolve_source(spec).await?;
+        self.with_tray(move |t| t.toggle_source(source)).await
+    }
+
+    /// Audio nodes available for capture, as (node ID, kind, description).
+    async fn list_audio_sources(&self) -> fdo::Result<Vec<(u32, String, String)>> {
+        let nodes = self.with_tray(|t| crate::audio::list_nodes(&t.node_list)).await?;
+        Ok(nodes
+            .iter()
+            .map(|n| (n.node_id, node_kind_name(&n.kind).to_string(), n.description.clone()))
+            .collect())
+    }
+
+    /// Switch the overlay to "docked" or "floating".
+    async fn set_overlay_mode(&self, mode: &str) -> fdo::Result<()> {
+        let mode = match mode {
+            "docked" => OverlayMode::Docked,
+            "floating" => OverlayMode::Floating,
+            other => {
+                return Err(fdo::Error::InvalidArgs(format!(
+                    "unknown overlay mode '{other}' (expected docked or floating)"
+                )))
+            }
+        };
+        self.with_tray(move |t| t.set_overlay_mode(mode)).await
+    }
+
+    async fn toggle_overlay_mode(&self) -> fdo::Result<()> {
+        self.with_tray(|t| {
+            let mode = match t.overlay_mode {
+                OverlayMode::Docked => OverlayMode::Floating,
+                OverlayMode::Floating => OverlayMode::Docked,
+            };
+            t.set_overlay_mode(mode);
+        })
+        .await
+    }
+
+    /// Lock or unlock the floating overlay. Ignored in docked mode.
+    async fn set_locked(&self, locked: bool) -> fdo::Result<()> {
+        self.with_tray(move |t| t.set_locked(locked)).await
+    }
+
+    async fn toggle_locked(&self) -> fdo::Result<()> {
+        self.with_tray(|t| t.set_locked(!t.locked)).await
+    }
+
+    // State is read on demand; it also changes from the tray and config reloads, so no
+    // PropertiesChanged signals are sent.
+
+    #[zbus(property(emits_changed_signal = "false"))]
+    async fn captions_enabled(&self) -> fdo::Result<bool> {
+        self.with_tray(|t| t.captions_enabled.load(std::sync::atomic::Ordering::Relaxed)).await
+    }
+
+    #[zbus(property(emits_changed_signal = "false"))]
+    async fn overlay_mode(&self) -> fdo::Result<String> {
+        self.with_tray(|t| match t.overlay_mode {
+            OverlayMode::Docked => "docked".to_string(),
+            OverlayMode::Floating => "floating".to_string(),
+        })
+        .await
+    }
+
+    #[zbus(property(emits_changed_signal = "false"))]
+    async fn locked(&self) -> fdo::Result<bool> {
+        self.with_tray(|t| t.locked).await
+    }
+
+    /// Selected sources, in the spec form accepted by SelectAudioSource.
+    #[zbus(property(emits_changed_signal = "false"))]
+    async fn audio_sources(&self) -> fdo::Result<Vec<String>> {
+        self.with_tray(|t| t.active_sources.iter().map(|s| source_spec(&s.source)).collect()).await
+    }
+
+    /// A caption fragment. `source` is the speaker label, empty when sources are mixed.
+    /// Partial hypotheses (`is_final` false) are replaced by the next fragment from the
+    /// same source. Times are milliseconds since startup.
+    #[zbus(signal)]
+    async fn caption(
+        emitter: &SignalEmitter<'_>,
+        text: &str,
+        source: &str,
+        is_final: bool,
+        start_ms: u64,
+        end_ms: u64,
+    ) -> zbus::Result<()>;
+}
+
+/// Handle for broadcasting captions on the bus. Dropping it releases the bus name.
+pub struct CaptionSignals {
+    conn: zbus::Connection,
+    runtime: tokio::runtime::Handle,
+}
+
+impl CaptionSignals {
+    /// Emit `caption` as a `Caption` signal. Does not block; failures are logged.
+    pub fn emit(&self, caption: &Caption) {
+        let conn = self.conn.clone();
+        let caption = caption.clone();
+        self.runtime.spawn(async move {
+            let result = async {
+                let emitter = SignalEmitter::new(&conn, OBJECT_PATH)?;
+                let source = caption.source.as_ref().map(|s| s.label.as_str()).unwrap_or("");
+                CaptionsService::caption(
+                    &emitter,
+                    &caption.text,
+                    source,
+                    caption.is_final,
+                    samples_to_ms(caption.start_sample),
+                    samples_to_ms(caption.end_sample),
+                )
+                .await
+            }
+            .await;
+            if let Err(e) = result {
+                eprintln!("warn: failed to emit D-Bus caption signal: {e}");
+            }
+        });
+    }
+}
+
+/// Claim `org.subtidal.Captions` on the session bus and serve the control interface.
+pub fn start(runtime: &tokio::runtime::Runtime, tray: ksni::Handle<TrayState>) -> Result<CaptionSignals> {
+    let conn = runtime.block_on(async {
+        zbus::connection::Builder::session()?
+            .name(BUS_NAME)?
+            .serve_at(OBJECT_PATH, CaptionsService { tray })?
+            .build()
+            .await
+    })
+    .with_context(|| format!("registering {BUS_NAME} on the session bus"))?;
+    Ok(CaptionSignals { conn, runtime: runtime.handle().clone() })
+}
+
+fn node_kind_name(kind: &NodeKind) -> &'static str {
+    match kind {
+        NodeKind::Monitor => "monitor",
+        NodeKind::Application => "application",
+        NodeKind::Input => "input",
+    }
+}
@@ -0,0 +1,379 @@
+//! Local control socket (`$XDG_RUNTIME_DIR/subtidal.sock`) behind `subtidal ctl`.
+//!
+//! One command per connection: the client writes a single line, the running instance
+//! applies it to the tray state (the same path as the tray menu and D-Bus) and writes back
+//! its reply, or a line starting with `error: `, then closes the connection.
+//!
+//! The socket also keeps subtidal single-instance: it is claimed early at startup, and a
+//! second launch that finds it taken hands its request to the owner instead. Until the
+//! owner's tray is up (the model may still be downloading), every command is refused with
+//! [`StillStarting`]. Claims are serialized by an `flock` on `subtidal.lock`, so two
+//! launches after a crash cannot both replace the stale socket.
+
+use crate::audio::{parse_source_spec, source_spec};
+use crate::config::{Config, OverlayMode};
+use crate::tray::TrayState;
+use anyhow::{bail, Context, Result};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// How long the server waits for a client to send its command.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a client waits for the reply.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(30);
+/// Error reply while the owner is starting up.
+const STARTING_REPLY: &str = "still starting";
+
+/// Applies one command; see [`execute`].
+type Handler = Box<dyn Fn(CtlCommand) -> Result<String, String> + Send>;
+
+/// The running instance has claimed the socket but cannot take commands yet.
+#[derive(Debug)]
+pub struct StillStarting;
+
+impl std::fmt::Display for StillStarting {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("subtidal is still starting; try again shortly")
+    }
+}
+
+impl std::error::Error for StillStarting {}
+
+/// A `subtidal ctl` command.
+#[derive(clap::Subcommand, Debug, Clone, PartialEq)]
+pub enum CtlCommand {
+    /// Show captions if hidden, hide them if shown
+    Toggle,
+    /// Show captions
+    Show,
+    /// Hide captions
+    Hide,
+    /// Capture only this source: "system", "auto", a node ID, or part of an app's name
+    Source { name: String },
+    /// Switch the overlay mode
+    Mode {
+        #[arg(value_enum)]
+        mode: OverlayMode,
+    },
+    /// Lock the floating overlay in place
+    Lock,
+    /// Unlock the floating overlay
+    Unlock,
+    /// Switch the STT engine
+    Engine { name: String },
+    /// Print the current state
+    Status,
+}
+
+impl CtlCommand {
+    /// Wire form of the command, as sent over the socket.
+    fn to_line(&self) -> String {
+        match self {
+            CtlCommand::Toggle => "toggle".to_string(),
+            CtlCommand::Show => "show".to_string(),
+            CtlCommand::Hide => "hide".to_string(),
+            CtlCommand::Source { name } => format!("source {name}"),
+            CtlCommand::Mode { mode: OverlayMode::Docked } => "mode docked".to_string(),
+            CtlCommand::Mode { mode: OverlayMode::Floating } => "mode floating".to_string(),
+            CtlCommand::Lock => "lock".to_string(),
+            CtlCommand::Unlock => "unlock".to_string(),
+            CtlCommand::Engine { name } => format!("engine {name}"),
+            CtlCommand::Status => "status".to_string(),
+        }
+    }
+
+    fn parse_line(line: &str) -> Result<Self, String> {
+        let line = line.trim();
+        let (verb, arg) = line.split_once(' ').map_or((line, ""), |(v, a)| (v, a.trim()));
+        match (verb, arg) {
+            ("toggle", "") => Ok(CtlCommand::Toggle),
+            ("show", "") => Ok(CtlCommand::Show),
+            ("hide", "") => Ok(CtlCommand::Hide),
+            ("source", name) if !name.is_empty() => Ok(CtlCommand::Source { name: name.to_string() }),
+            ("mode", "docked") => Ok(CtlCommand::Mode { mode: OverlayMode::Docked }),
+            ("mode", "floating") => Ok(CtlCommand::Mode { mode: OverlayMode::Floating }),
+            ("lock", "") => Ok(CtlCommand::Lock),
+            ("unlock", "") => Ok(CtlCommand::Unlock),
+            ("engine", name) if !name.is_empty() => Ok(CtlCommand::Engine { name: name.to_string() }),
+            ("status", "") => Ok(CtlCommand::Status),
+            _ => Err(format!("unknown command '{line}'")),
+        }
+    }
+}
+
+/// Path of the control socket.
+pub fn socket_path() -> PathBuf {
+    dirs::runtime_dir().unwrap_or_else(std::env::temp_dir).join("subtidal.sock")
+}
+
+/// Apply `cmd` to the tray state. Returns the text to print on success.
+fn execute(tray: &mut TrayState, cmd: CtlCommand) -> Result<String, String> {
+    let enabled = tray.captions_enabled.load(Ordering::Relaxed);
+    match cmd {
+        CtlCommand::Toggle => tray.toggle_captions(),
+        CtlCommand::Show if !enabled => tray.toggle_captions(),
+        CtlCommand::Hide if enabled => tray.toggle_captions(),
+        CtlCommand::Show | CtlCommand::Hide => {}
+        CtlCommand::Source { name } => {
+            let nodes = crate::audio::list_nodes(&tray.node_list);
+            let source = parse_source_spec(&name, &nodes).ok_or_else(|| format!("no audio source matches '{name}'"))?;
+            tray.select_source(source);
+        }
+        CtlCommand::Mode { mode } => tray.set_overlay_mode(mode),
+        CtlCommand::Lock | CtlCommand::Unlock if tray.overlay_mode == OverlayMode::Docked => {
+            return Err("the docked overlay is always locked; switch to floating first".to_string());
+        }
+        CtlCommand::Lock => tray.set_locked(true),
+        CtlCommand::Unlock => tray.set_locked(false),
+        CtlCommand::Engine { name } => {
+            let engine = Config::parse_engine(&name).ok_or_else(|| format!("unknown engine '{name}'"))?;
+            tray.set_engine(engine);
+        }
+        CtlCommand::Status => return Ok(status(tray)),
+    }
+    Ok(String::new())
+}
+
+fn status(tray: &TrayState) -> String {
+    let on_off = |b: bool| if b { "on" } else { "off" };
+    let mode = match tray.overlay_mode {
+        OverlayMode::Docked => "docked",
+        OverlayMode::Floating => "floating",
+    };
+    let sources: Vec<String> = tray.active_sources.iter().map(|s| source_spec(&s.source)).collect();
+    let mut out = format!(
+        "captions: {}\nmode: {mode}\nlocked: {}\nsources: {}\n",
+        on_off(tray.captions_enabled.load(Ordering::Relaxed)),
+        on_off(tray.locked),
+        sources.join(", "),
+    );
+    if let Some(app) = &tray.following {
+        out.push_str(&format!("following: {app}\n"));
+    }
+    out
+}
+
+/// Result of claiming the control socket at startup.
+pub enum Instance {
+    /// No other instance is running; this one owns the socket.
+    Primary(ControlSocket),
+    /// Another instance owns the socket; send it commands with [`send`].
+    Running,
+}
+
+/// The bound control socket, refusing commands until [`ControlSocket::serve`].
+pub struct ControlSocket {
+    handler_tx: mpsc::Sender<Handler>,
+    path: PathBuf,
+}
+
+impl ControlSocket {
+    /// Start applying commands to the tray state. Returns the socket path, to remove on exit.
+    pub fn serve(self, tray: ksni::Handle<TrayState>, runtime: tokio::runtime::Handle) -> PathBuf {
+        self.answer_with(Box::new(move |cmd| {
+            runtime
+                .block_on(tray.update(move |t| execute(t, cmd)))
+                .unwrap_or_else(|| Err("tray service has shut down".to_string()))
+        }))
+    }
+
+    fn answer_with(self, handler: Handler) -> PathBuf {
+        // Fails only if the serving thread has died, in which case nobody is listening.
+        let _ = self.handler_tx.send(handler);
+        self.path
+    }
+}
+
+/// Claim the control socket, or find that another instance holds it.
+pub fn claim() -> Result<Instance> {
+    claim_at(socket_path())
+}
+
+/// Remove the socket file on exit, so the next start does not have to probe it.
+pub fn remove_socket(path: &Path) {
+    if let Err(e) = std::fs::remove_file(path) {
+        eprintln!("warn: failed to remove {}: {e}", path.display());
+    }
+}
+
+fn claim_at(path: PathBuf) -> Result<Instance> {
+    // Held until the socket is bound: between finding a stale socket and replacing it,
+    // no other launch may probe or unlink it. Never removed, so it cannot be swapped out
+    // from under a waiting launch.
+    let lock_path = path.with_extension("lock");
+    let lock = File::create(&lock_path).with_context(|| format!("opening {}", lock_path.display()))?;
+    lock.lock().with_context(|| format!("locking {}", lock_path.display()))?;
+
+    let listener = match UnixListener::bind(&path) {
+        Ok(listener) => listener,
+        Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => {
+            if UnixStream::connect(&path).is_ok() {
+                return Ok(Instance::Running);
+            }
+            // Left behind by an instance that did not exit cleanly.
+            std::fs::remove_file(&path).with_context(|| format!("removing stale {}", path.display()))?;
+            UnixListener::bind(&path).with_context(|| format!("binding {}", path.display()))?
+        }
+        Err(e) => return Err(e).with_context(|| format!("binding {}", path.display())),
+    };
+    drop(lock);
+    let (handler_tx, handler_rx) = mpsc::channel();
+    serve(listener, handler_rx);
+    Ok(Instance::Primary(ControlSocket { handler_tx, path }))
+}
+
+/// Answer clients one at a time on a background thread, with the handler once it arrives.
+fn serve(listener: UnixListener, handler_rx: mpsc::Receiver<Handler>) {
+    std::thread::spawn(move || {
+        let mut handler: Option<Handler> = None;
+        for stream in listener.incoming() {
+            if handler.is_none() {
+                handler = handler_rx.try_recv().ok();
+            }
+            let result = stream.and_then(|s| handle_client(s, handler.as_deref()));
+            if let Err(e) = result {
+                eprintln!("warn: control socket: {e}");
+            }
+        }
+    });
+}
+
+fn handle_client(
+    stream: UnixStream,
+    handler: Option<&(dyn Fn(CtlCommand) -> Result<String, String> + Send)>,
+) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut line = String::new();
+    if BufReader::new(&stream).read_line(&mut line)? == 0 {
+        // A starting instance checking whether this one is alive.
+        return Ok(());
+    }
+    let Some(handler) = handler else {
+        return (&stream).write_all(format!("error: {STARTING_REPLY}\n").as_bytes());
+    };
+    let reply = match CtlCommand::parse_line(&line).and_then(handler) {
+        Ok(reply) => reply,
+        Err(msg) => format!("error: {msg}\n"),
+    };
+    (&stream).write_all(reply.as_bytes())
+}
+
+/// Send `cmd` to the running instance and return its reply.
+pub fn send(cmd: &CtlCommand) -> Result<String> {
+    send_to(&socket_path(), cmd)
+}
+
+fn send_to(path: &Path, cmd: &CtlCommand) -> Result<String> {
+    let mut stream = UnixStream::connect(path)
+        .with_context(|| format!("subtidal is not running (cannot connect to {})", path.display()))?;
+    stream.set_read_timeout(Some(REPLY_TIMEOUT))?;
+    writeln!(stream, "{}", cmd.to_line())?;
+    stream.shutdown(std::net::Shutdown::Write)?;
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).context("reading reply")?;
+    match reply.strip_prefix("error: ") {
+        Some(msg) if msg.trim_end() == STARTING_REPLY => Err(StillStarting.into()),
+        Some(msg) => bail!("{}", msg.trim_end()),
+        None => Ok(reply),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_round_trip_through_wire_form() {
+        let commands = [
+            CtlCommand::Toggle,
+            CtlCommand::Show,
+            CtlCommand::Hide,
+            CtlCommand::Source { name: "USB Microphone".to_string() },
+            CtlCommand::Mode { mode: OverlayMode::Floating },
+            CtlCommand::Lock,
+            CtlCommand::Unlock,
+            CtlCommand::Engine { name: "nemotron".to_string() },
+            CtlCommand::Status,
+        ];
+        for cmd in commands {
+            assert_eq!(CtlCommand::parse_line(&cmd.to_line()), Ok(cmd));
+        }
+        assert!(CtlCommand::parse_line("source").is_err());
+        assert!(CtlCommand::parse_line("mode sideways").is_err());
+    }
+
+    fn claim_primary(path: &Path) -> ControlSocket {
+        match claim_at(path.to_path_buf()).unwrap() {
+            Instance::Primary(socket) => socket,
+            Instance::Running => panic!("socket unexpectedly held"),
+        }
+    }
+
+    #[test]
+    fn client_gets_replies_and_errors_from_server() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("subtidal.sock");
+        claim_primary(&path).answer_with(Box::new(|cmd| match cmd {
+            CtlCommand::Status => Ok("captions: on\n".to_string()),
+            other => Err(format!("cannot {}", other.to_line())),
+        }));
+
+        assert_eq!(send_to(&path, &CtlCommand::Status).unwrap(), "captions: on\n");
+        let err = send_to(&path, &CtlCommand::Lock).unwrap_err();
+        assert_eq!(err.to_string(), "cannot lock");
+        // A second instance finds the first one instead of taking over.
+        assert!(matches!(claim_at(path.clone()).unwrap(), Instance::Running));
+    }
+
+    #[test]
+    fn commands_are_refused_while_starting() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("subtidal.sock");
+        let socket = claim_primary(&path);
+
+        let err = send_to(&path, &CtlCommand::Show).unwrap_err();
+        assert!(err.downcast_ref::<StillStarting>().is_some(), "{err}");
+        socket.answer_with(Box::new(|_| Ok("ok\n".to_string())));
+        assert_eq!(send_to(&path, &CtlCommand::Show).unwrap(), "ok\n");
+    }
+
+    #[test]
+    fn stale_socket_is_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("subtidal.sock");
+        drop(UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+        claim_primary(&path);
+    }
+
+    #[test]
+    fn launches_racing_for_a_stale_socket_have_one_winner() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("subtidal.sock");
+        for _ in 0..50 {
+            let _ = std::fs::remove_file(&path);
+            drop(UnixListener::bind(&path).unwrap());
+            let start = std::sync::Arc::new(std::sync::Barrier::new(8));
+            let claims: Vec<_> = (0..8)
+                .map(|_| {
+                    let (path, start) = (path.clone(), std::sync::Arc::clone(&start));
+                    std::thread::spawn(move || {
+                        start.wait();
+                        claim_at(path).unwrap()
+                    })
+                })
+                .collect();
+            let primaries = claims
+                .into_iter()
+                .map(|claim| claim.join().unwrap())
+                .filter(|claim| matches!(claim, Instance::Primary(_)))
+                .count();
+            assert_eq!(primaries, 1);
+        }
+    }
+}
@@ -1,13 +1,18 @@
 mod audio;
 mod config;
+mod dbus;
+mod ipc;
 mod models;
 mod stt;
 mod overlay;
+mod subtitles;
 mod tray;
+mod transcribe;
+mod transcript;
+mod web;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use config::Config;
-use ringbuf::traits::Consumer;
 use std::sync::{Arc, Mutex};
 use std::sync::atomic::{AtomicBool, Ordering};
 
@@ -59,6 +64,149 @@ struct Args {
     /// Reset config to defaults before starting
     #[arg(long)]
     reset_config: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Transcribe an audio file (WAV, FLAC, Ogg Vorbis or raw PCM) to stdout and exit
+    Transcribe {
+        /// Audio file to transcribe
+        file: std::path::PathBuf,
+
+        /// Read the file as raw PCM at this sample rate (required for .raw/.pcm files)
+        #[arg(long)]
+        rate: Option<u32>,
+
+        /// Channel count of raw PCM input
+        #[arg(long, default_value_t = 1)]
+        channels: usize,
+
+        /// Sample format of raw PCM input
+        #[arg(long, value_enum, default_value_t = audio::file::RawFormat::S16le)]
+        format: audio::file::RawFormat,
+
+        /// Also write subtitles to this file (.srt or .vtt)
+        #[arg(long)]
+        subtitles: Option<std::path::PathBuf>,
+    },
+
+    /// Control the running instance (for keybindings and scripts)
+    Ctl {
+        #[command(subcommand)]
+        command: ipc::CtlCommand,
+    },
+}
+
+/// Download the Nemotron model files if they are missing. Exits the process on failure.
+/// Returns the tokio runtime used for the download, which the tray reuses.
+fn ensure_models_present() -> tokio::runtime::Runtime {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap_or_else(|e| {
+            eprintln!("error: failed to build tokio runtime: {e}");
+            std::process::exit(1);
+        });
+
+    runtime.block_on(async {
+        if !models::nemotron_models_present() {
+            eprintln!("Downloading Nemotron model files (first run)...");
+            models::ensure_nemotron_models().await
+                .unwrap_or_else(|e| {
+                    eprintln!("error: failed to download Nemotron model: {e:#}");
+                    eprintln!("hint: check network connectivity and disk space in ~/.local/share/subtidal/models/");
+                    std::process::exit(1);
+                });
+            eprintln!("Nemotron models ready.");
+        } else {
+            eprintln!("Nemotron models already present, skipping download.");
+        }
+    });
+    runtime
+}
+
+/// `subtidal transcribe`: decode a file and print its transcript, without the overlay,
+/// tray or PipeWire. Status goes to stderr so stdout carries only the transcript.
+fn run_transcribe(
+    file: &std::path::Path,
+    rate: Option<u32>,
+    channels: usize,
+    format: audio::file::RawFormat,
+    subtitles_path: Option<&std::path::Path>,
+) -> anyhow::Result<()> {
+    use anyhow::Context as _;
+    use std::io::Write as _;
+
+    let mut subtitles = match subtitles_path {
+        Some(path) => {
+            let subtitle_format = config::SubtitleFormat::from_path(path)
+                .with_context(|| format!("{}: subtitle file must end in .srt or .vtt", path.display()))?;
+            let out = std::fs::File::create(path).with_context(|| format!("creating {}", path.display()))?;
+            Some(subtitles::SubtitleWriter::new(subtitle_format, std::io::BufWriter::new(out))?)
+        }
+        None => None,
+    };
+
+    let is_raw_ext = file
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("raw") || e.eq_ignore_ascii_case("pcm"));
+    let raw = match rate {
+        Some(sample_rate) => Some(audio::file::RawSpec { sample_rate, channels, format }),
+        None if is_raw_ext => anyhow::bail!("raw PCM input needs --rate (and --channels/--format if not mono s16le)"),
+        None => None,
+    };
+    let mut audio_file = audio::file::AudioFile::open(file, raw)?;
+    eprintln!("info: transcribing {} ({} Hz, {} channels)", file.display(), audio_file.sample_rate, audio_file.channels);
+
+    ensure_models_present();
+    let model_dir = models::nemotron_model_dir();
+    let use_cuda = stt::cuda_available(&model_dir);
+    eprintln!("{}", cuda_status_message(use_cuda));
+    let mut engine = stt::nemotron::NemotronEngine::new(&model_dir, use_cuda)?;
+
+    let mut stdout = std::io::stdout().lock();
+    transcribe::transcribe(&mut audio_file, &mut engine, |result| {
+        write!(stdout, "{}", result.text)?;
+        stdout.flush()?;
+        if let Some(writer) = subtitles.as_mut() {
+            writer.push(result.start_sample, result.end_sample, &result.text, None)?;
+        }
+        Ok(())
+    })?;
+    writeln!(stdout)?;
+    if let (Some(writer), Some(path)) = (subtitles, subtitles_path) {
+        writer.finish().with_context(|| format!("writing {}", path.display()))?;
+        eprintln!("info: subtitles written to {}", path.display());
+    }
+    Ok(())
+}
+
+/// Hand a second launch over to the instance already running: bring its captions up and
+/// apply `--engine`. Options that only matter at startup cannot be applied.
+fn forward_to_running_instance(args: &Args) -> anyhow::Result<()> {
+    if args.config.is_some() || args.reset_config {
+        eprintln!("warn: --config and --reset-config only apply at startup; ignored");
+    }
+    match ipc::send(&ipc::CtlCommand::Show) {
+        // It may be downloading or loading its model; it shows captions once that is done.
+        Err(e) if e.downcast_ref::<ipc::StillStarting>().is_some() => {
+            if args.engine.is_some() {
+                eprintln!("warn: --engine not applied; use `subtidal ctl engine` once it is up");
+            }
+            eprintln!("info: subtidal is already running and still starting up");
+            return Ok(());
+        }
+        result => result?,
+    };
+    if let Some(name) = &args.engine {
+        ipc::send(&ipc::CtlCommand::Engine { name: name.clone() })?;
+    }
+    eprintln!("info: subtidal is already running; showing its captions");
+    Ok(())
 }
 
 fn main() {
@@ -67,8 +215,60 @@ fn main() {
         stt::run_cuda_probe();
     }
 
+    // The local time zone can only be read safely while the process is single-threaded.
+    let utc_offset = time::UtcOffset::current_local_offset().unwrap_or_else(|_| {
+        eprintln!("warn: local time zone unavailable; transcript times are UTC");
+        time::UtcOffset::UTC
+    });
+
     let args = Args::parse();
 
+    match &args.command {
+        Some(Command::Transcribe { file, rate, channels, format, subtitles }) => {
+            if let Err(e) = run_transcribe(file, *rate, *channels, *format, subtitles.as_deref()) {
+                eprintln!("error: {e:#}");
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::Ctl { command }) => {
+            match ipc::send(command) {
+                Ok(reply) => print!("{reply}"),
+                Err(e) => {
+                    eprintln!("error: {e:#}");
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        None => {}
+    }
+
+    let engine_override = args.engine.as_deref().map(|engine_str| {
+        Config::parse_engine(engine_str).unwrap_or_else(|| {
+            eprintln!("Unknown engine '{}'. Valid engines: nemotron, parakeet.", engine_str);
+            std::process::exit(1);
+        })
+    });
+
+    // Only one instance captures and shows captions. Claim the control socket before
+    // touching config, models or PipeWire; if it is taken, hand this launch to its owner.
+    let control_socket = match ipc::claim() {
+        Ok(ipc::Instance::Primary(socket)) => Some(socket),
+        Ok(ipc::Instance::Running) => {
+            if let Err(e) = forward_to_running_instance(&args) {
+                eprintln!("error: subtidal is already running, but forwarding to it failed: {e:#}");
+                std::process::exit(1);
+            }
+            return;
+        }
+        Err(e) => {
+            eprintln!("warn: control socket unavailable: {e:#}");
+            eprintln!("warn: `subtidal ctl` and single-instance detection are disabled");
+            None
+        }
+    };
+
     ensure_desktop_entry_installed();
 
     // Load or reset config. --config overrides the default XDG path.
@@ -86,14 +286,8 @@ fn main() {
     };
 
     // CLI engine override
-    if let Some(engine_str) = args.engine {
-        match Config::parse_engine(&engine_str) {
-            Some(engine) => cfg.engine = engine,
-            None => {
-                eprintln!("Unknown engine '{}'. Valid engines: nemotron, parakeet.", engine_str);
-                std::process::exit(1);
-            }
-        };
+    if let Some(engine) = engine_override {
+        cfg.engine = engine;
     }
 
     // Persist the config (creates file on first run)
@@ -103,54 +297,27 @@ fn main() {
 
     println!("Config loaded: {:?}", Config::config_path());
     println!("Engine: {:?}", cfg.engine);
-    println!("Audio source: {:?}", cfg.audio_source);
+    println!("Audio sources: {:?}", cfg.audio_sources);
     println!("Model dir: {:?}", models::models_dir());
 
     // Phase 2: Ensure model files are present before starting
-    let runtime = tokio::runtime::Builder::new_multi_thread()
-        .enable_all()
-        .build()
-        .unwrap_or_else(|e| {
-            eprintln!("error: failed to build tokio runtime: {e}");
-            std::process::exit(1);
-        });
-
-    runtime.block_on(async {
-        if !models::nemotron_models_present() {
-            println!("Downloading Nemotron model files (first run)...");
-            models::ensure_nemotron_models().await
-                .unwrap_or_else(|e| {
-                    eprintln!("error: failed to download Nemotron model: {e:#}");
-                    eprintln!("hint: check network connectivity and disk space in ~/.local/share/subtidal/models/");
-                    std::process::exit(1);
-                });
-            println!("Nemotron models ready.");
-        } else {
-            println!("Nemotron models already present, skipping download.");
-        }
-    });
+    let runtime = ensure_models_present();
 
     // Phase 3: Start audio capture
-    let (audio_cmd_tx, ring_consumer, node_list, fallback_rx) =
-        audio::start_audio_thread(cfg.audio_source.clone())
+    let (audio_cmd_tx, audio_feeds, node_list, source_event_rx) =
+        audio::start_audio_thread(
+            cfg.audio_sources.clone(),
+            std::time::Duration::from_secs(cfg.auto_follow_hold_secs),
+        )
             .unwrap_or_else(|e| {
                 eprintln!("error: failed to start audio capture: {e:#}");
                 eprintln!("hint: is PipeWire running? (`systemctl --user status pipewire`)");
                 std::process::exit(1);
             });
 
-    // Validate the loaded audio source against available nodes; if invalid, fall back to SystemOutput.
-    // This ensures that if a saved Application source's node_id disappears (e.g. app restarted),
-    // we gracefully switch to the always-available SystemOutput instead of failing.
-    let validated_source = {
-        let nodes = node_list.lock().unwrap();
-        audio::validate_audio_source(cfg.audio_source.clone(), &nodes)
-    };
-    if validated_source != cfg.audio_source {
-        cfg.audio_source = validated_source.clone();
-        // Notify audio thread of the fallback source if needed.
-        let _ = audio_cmd_tx.send(audio::AudioCommand::SwitchSource(validated_source));
-    }
+    // Saved sources are matched to live nodes by the audio thread itself (by application
+    // name, binary and node name), so an app that restarted under a new node ID is picked up
+    // again, and one that is not running yet is attached as soon as it appears.
 
     // Probe CUDA availability by attempting a full model load in a subprocess.
     // This catches segfaults from CUDA version mismatches during session creation.
@@ -161,81 +328,67 @@ fn main() {
     // Create audio chunk channel (connects Phase 3 ring buffer drain to inference).
     // Wrap the SyncSender in Arc<Mutex<>> so Phase 8 engine switching can replace it
     // at runtime without restarting the bridge thread.
-    let (chunk_tx_inner, chunk_rx) = std::sync::mpsc::sync_channel::<Vec<f32>>(32);
+    let (chunk_tx_inner, chunk_rx) = std::sync::mpsc::sync_channel::<stt::EngineInput>(32);
     let chunk_tx = std::sync::Arc::new(std::sync::Mutex::new(chunk_tx_inner));
-    let (caption_tx, caption_rx) = std::sync::mpsc::sync_channel::<String>(64);
+    let (caption_tx, caption_rx) = std::sync::mpsc::sync_channel::<stt::Caption>(64);
 
     // Create shutdown flag for audio bridge thread.
     let bridge_shutdown = Arc::new(AtomicBool::new(false));
 
+    // Start of the session timeline: chunk and caption timestamps count from here.
+    let session_epoch = std::time::Instant::now();
+    let subtitle_recorder = subtitles::Recorder::new(session_epoch);
+    let transcript_log = transcript::TranscriptLog::new(session_epoch, utc_offset, &cfg.transcript);
+    transcript_log.set_sources(&cfg.audio_sources);
+
     // Shared captions-enabled flag — used by bridge thread to skip inference when disabled,
     // and by tray/overlay for UI state.
     let captions_enabled = Arc::new(std::sync::atomic::AtomicBool::new(true));
 
-    // Spawn the audio→chunk bridge thread.
-    // Drains the ring buffer, resamples, and sends 160ms chunks to the inference thread.
-    // Locks chunk_tx on each send so Phase 8 can atomically swap the inner SyncSender.
-    let mut ring_consumer_arc = ring_consumer;
-    let chunk_tx_for_bridge = std::sync::Arc::clone(&chunk_tx);
-    let bridge_shutdown_for_thread = Arc::clone(&bridge_shutdown);
-    let captions_enabled_for_bridge = Arc::clone(&captions_enabled);
-    std::thread::spawn(move || {
-        let mut resampler = audio::resampler::AudioResampler::new()
-            .expect("creating resampler");
-        let mut raw = vec![0f32; 4096];
-        loop {
-            if bridge_shutdown_for_thread.load(Ordering::Relaxed) {
-                break;
-            }
-            let n = ring_consumer_arc.pop_slice(&mut raw);
-            if n > 0 {
-                // When captions are disabled, drain the ring buffer but skip
-                // resampling and inference to save CPU/GPU. The model stays loaded.
-                if !captions_enabled_for_bridge.load(Ordering::Relaxed) {
-                    continue;
-                }
-                match resampler.push_interleaved(&raw[..n]) {
-                    Ok(chunks) => {
-                        for chunk in chunks {
-                            let tx = chunk_tx_for_bridge.lock().unwrap();
-                            if tx.send(chunk).is_err() {
-                                drop(tx); // release lock before sleep
-                                std::thread::sleep(std::time::Duration::from_millis(10));
-                                break; // engine switching — wait for new tx
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("warn: resampler error: {e}");
-                    }
-                }
-            }
-            std::thread::sleep(std::time::Duration::from_millis(5));
-        }
-    });
-
-    // Instantiate the STT engine.
-    let engine: Box<dyn stt::SttEngine> = {
-        Box::new(
-            stt::nemotron::NemotronEngine::new(&model_dir, use_cuda)
-                .unwrap_or_else(|e| {
-                    eprintln!("error: failed to load Nemotron model: {e:#}");
-                    std::process::exit(1);
-                })
-        )
-    };
-
     // Clone caption_tx for engine switching before spawning the inference thread.
     let caption_tx_for_switch = caption_tx.clone();
 
-    // Spawn the inference thread.
-    let _inference_handle = stt::spawn_inference_thread(engine, chunk_rx, caption_tx);
+    let bridge_mode = if cfg.separate_sources {
+        // One engine per source, loaded by the bridge as sources come and go.
+        let engine_factory: stt::EngineFactory = Box::new(move || {
+            let engine = stt::nemotron::NemotronEngine::new(&model_dir, use_cuda)?;
+            Ok(Box::new(engine) as Box<dyn stt::SttEngine>)
+        });
+        audio::bridge::BridgeMode::PerSource { engine_factory, caption_tx }
+    } else {
+        // Instantiate the STT engine.
+        let engine: Box<dyn stt::SttEngine> = {
+            Box::new(
+                stt::nemotron::NemotronEngine::new(&model_dir, use_cuda)
+                    .unwrap_or_else(|e| {
+                        eprintln!("error: failed to load Nemotron model: {e:#}");
+                        std::process::exit(1);
+                    })
+            )
+        };
+
+        // Spawn the inference thread.
+        let _inference_handle = stt::spawn_inference_thread(engine, chunk_rx, caption_tx, None);
+        audio::bridge::BridgeMode::Mixed(std::sync::Arc::clone(&chunk_tx))
+    };
+
+    // Spawn the audio→chunk bridge thread.
+    // Drains each source's ring buffer, mixes them (or keeps them apart per source), resamples,
+    // and sends 160ms chunks to the inference thread(s).
+    audio::bridge::spawn_bridge(
+        audio_feeds,
+        bridge_mode,
+        Arc::clone(&captions_enabled),
+        Arc::clone(&bridge_shutdown),
+        session_epoch,
+        cfg.vad_aggressiveness,
+    );
 
     // Phase 6: Set up engine-switch channel.
     let (engine_switch_tx, engine_switch_rx) = std::sync::mpsc::sync_channel::<tray::EngineCommand>(4);
 
     // Phase 8: Wire engine-switch receiver (restarts inference thread on switch).
-    // chunk_tx is Arc<Mutex<SyncSender<Vec<f32>>>> from Phase 4 Task 4.
+    // chunk_tx is Arc<Mutex<SyncSender<EngineInput>>> from Phase 4 Task 4.
     // The audio bridge thread calls chunk_tx.lock().unwrap().send(chunk) on every chunk.
     // When we replace *chunk_tx.lock(), the very next chunk goes to the new inference engine.
     // We store old inference thread handles in a Vec to prevent JoinHandle leaks.
@@ -243,10 +396,14 @@ fn main() {
     {
         let chunk_tx_for_switch = std::sync::Arc::clone(&chunk_tx); // Phase 4's Arc<Mutex<SyncSender>>
         let inference_handles = Arc::clone(&inference_handles);
+        let separate_sources = cfg.separate_sources;
 
         std::thread::spawn(move || {
             for cmd in engine_switch_rx.iter() {
                 match cmd {
+                    tray::EngineCommand::Switch(_) if separate_sources => {
+                        eprintln!("warn: engine switching is not supported with separate_sources; restart to apply");
+                    }
                     tray::EngineCommand::Switch(new_engine_choice) => {
                         eprintln!("info: switching STT engine to {new_engine_choice:?}");
 
@@ -291,73 +448,171 @@ fn main() {
     // Phase 5: Set up channels for caption and command delivery.
     // We use std::sync::mpsc because glib::channel is not available in glib 0.19.
     // The glib main loop will poll these channels via timeout_add.
-    let (caption_tx_to_gtk, caption_rx_from_inference) = std::sync::mpsc::channel::<String>();
+    let (caption_tx_to_gtk, caption_rx_from_inference) = std::sync::mpsc::channel::<stt::Caption>();
     let (cmd_tx_to_gtk, cmd_rx) = std::sync::mpsc::channel::<overlay::OverlayCommand>();
-
-    // Bridge: forward inference thread captions directly.
-    let caption_rx_from_inference_out = caption_rx; // from Phase 4 spawn_inference_thread
-    std::thread::spawn(move || {
-        for caption in caption_rx_from_inference_out.iter() {
-            if caption_tx_to_gtk.send(caption).is_err() {
-                break;
-            }
-        }
-    });
+    let (monitor_tx, monitor_rx) = std::sync::mpsc::channel::<Vec<overlay::MonitorInfo>>();
 
     // Spawn the system tray (Phase 6).
     let tray_state = tray::TrayState {
         captions_enabled: Arc::clone(&captions_enabled),
-        active_source: cfg.audio_source.clone(),
+        active_sources: cfg.audio_sources.clone(),
+        following: None,
         overlay_mode: cfg.overlay_mode.clone(),
         locked: cfg.locked,
+        monitors: Vec::new(),
+        monitor: cfg.monitor.clone(),
         active_engine: cfg.engine.clone(),
         overlay_tx: cmd_tx_to_gtk.clone(),
         audio_tx: audio_cmd_tx.clone(),
         engine_tx: engine_switch_tx,
         node_list: Arc::clone(&node_list),
+        recorder: subtitle_recorder.clone(),
+        transcript: transcript_log.clone(),
     };
 
     // Use the already-built tokio runtime (from Phase 2 model download).
     let tray_handle = tray::spawn_tray(tray_state, &runtime);
 
-    // Phase 8: Handle FallbackEvent from audio thread (AC1.4).
+    // D-Bus control interface; drives the tray state, so it starts once the tray is up.
+    // Without a session bus (or with the name taken) the app runs on without it.
+    let caption_signals = match dbus::start(&runtime, tray_handle.clone()) {
+        Ok(signals) => Some(signals),
+        Err(e) => {
+            eprintln!("warn: D-Bus interface unavailable: {e:#}");
+            None
+        }
+    };
+
+    // Answer `subtidal ctl` and later launches; like D-Bus, they drive the tray state.
+    let control_socket = control_socket.map(|socket| socket.serve(tray_handle.clone(), runtime.handle().clone()));
+
+    // Caption server for OBS browser sources (off by default, localhost only).
+    let web_server = if cfg.web.enabled {
+        match web::start(&runtime, cfg.web.port, &cfg.appearance) {
+            Ok(server) => Some(server),
+            Err(e) => {
+                eprintln!("warn: caption server unavailable: {e:#}");
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    // Bridge: forward inference thread captions directly, copying them into the
+    // subtitle recording while one is running and the transcript log, and broadcasting
+    // them on D-Bus and to caption server clients.
+    let caption_rx_from_inference_out = caption_rx; // from Phase 4 spawn_inference_thread
+    let recorder_for_captions = subtitle_recorder.clone();
+    let transcript_for_captions = transcript_log.clone();
+    let web_for_captions = web_server.clone();
+    std::thread::spawn(move || {
+        for caption in caption_rx_from_inference_out.iter() {
+            recorder_for_captions.push(&caption);
+            transcript_for_captions.push(&caption);
+            if let Some(signals) = &caption_signals {
+                signals.emit(&caption);
+            }
+            if let Some(web) = &web_for_captions {
+                web.publish_caption(&caption);
+            }
+            if caption_tx_to_gtk.send(caption).is_err() {
+                break;
+            }
+        }
+    });
+
+    // Phase 8: Handle SourceEvent from audio thread (AC1.4).
     // Capture a Tokio Handle from the runtime before spawning the plain OS thread.
     // tokio::runtime::Handle::current() panics in plain threads; we must pass the
     // Handle in from a scope where the runtime is live.
     let tokio_handle = runtime.handle().clone();
     let tray_handle_for_fallback = tray_handle.clone();
     std::thread::spawn(move || {
-        for event in fallback_rx.iter() {
+        for event in source_event_rx.iter() {
             // Desktop notification (AC1.4).
+            let (summary, body, sources) = match event {
+                audio::SourceEvent::Lost { name, node_id, fallback, sources } => {
+                    let outcome = if fallback {
+                        "capturing System Output until it returns"
+                    } else {
+                        "it will be reattached when it returns"
+                    };
+                    (
+                        "Live Captions: Audio Source Lost",
+                        format!("'{name}' (id:{node_id}) disconnected — {outcome}."),
+                        sources,
+                    )
+                }
+                audio::SourceEvent::Reattached { name, node_id, sources } => (
+                    "Live Captions: Audio Source Restored",
+                    format!("'{name}' is back (id:{node_id}) — capture resumed."),
+                    sources,
+                ),
+                audio::SourceEvent::DefaultSinkChanged { sink } => {
+                    let _ = notify_rust::Notification::new()
+                        .summary("Live Captions: Output Device Changed")
+                        .body(&format!("System output capture moved to '{sink}'."))
+                        .timeout(notify_rust::Timeout::Milliseconds(5000))
+                        .show();
+                    continue;
+                }
+                audio::SourceEvent::Following { description } => {
+                    // Auto-follow switches are frequent: show them in the tray only.
+                    tokio_handle.block_on(async {
+                        tray_handle_for_fallback.update(|tray: &mut tray::TrayState| {
+                            tray.following = description;
+                        }).await;
+                    });
+                    continue;
+                }
+            };
             let _ = notify_rust::Notification::new()
-                .summary("Live Captions: Audio Source Lost")
-                .body(&format!(
-                    "'{}' (id:{}) disconnected — switched to System Output.",
-                    event.lost_name, event.lost_id
-                ))
+                .summary(summary)
+                .body(&body)
                 .timeout(notify_rust::Timeout::Milliseconds(5000))
                 .show();
 
-            // Update tray to reflect fallback source.
+            // Update tray with the refreshed node IDs.
             // Uses the captured Handle to run the async update on the Tokio runtime.
+            let tray_sources = sources.clone();
             tokio_handle.block_on(async {
                 tray_handle_for_fallback.update(|tray: &mut tray::TrayState| {
-                    tray.active_source = crate::config::AudioSource::SystemOutput;
+                    tray.set_active_sources(tray_sources);
                 }).await;
             });
 
             // Update config.
             let mut cfg = crate::config::Config::load();
-            cfg.audio_source = crate::config::AudioSource::SystemOutput;
+            cfg.audio_sources = sources;
             let _ = cfg.save();
         }
     });
 
+    // Connected monitors, reported by the overlay at startup and on hotplug, for the tray's
+    // Monitor menu.
+    let tokio_handle = runtime.handle().clone();
+    let tray_handle_for_monitors = tray_handle.clone();
+    std::thread::spawn(move || {
+        for monitors in monitor_rx.iter() {
+            tokio_handle.block_on(async {
+                tray_handle_for_monitors.update(|tray: &mut tray::TrayState| {
+                    tray.monitors = monitors;
+                }).await;
+            });
+        }
+    });
+
     // Phase 7: Start config hot-reload watcher.
     // _config_watcher must stay in scope until process exit (drop = stop watching).
     // Typed as Option so the failure path compiles without a dummy Debouncer.
     let _config_watcher: Option<notify_debouncer_mini::Debouncer<notify::RecommendedWatcher>> =
-        match config::start_hot_reload(cmd_tx_to_gtk.clone(), tray_handle.clone(), runtime.handle().clone()) {
+        match config::start_hot_reload(
+            cmd_tx_to_gtk.clone(),
+            audio_cmd_tx.clone(),
+            tray_handle.clone(),
+            runtime.handle().clone(),
+        ) {
             Ok(watcher) => {
                 eprintln!("info: config hot-reload active (watching config.toml)");
                 Some(watcher)
@@ -387,7 +642,25 @@ fn main() {
     .expect("setting Ctrl-C handler");
 
     // Run GTK4 main loop (blocks until application exits).
-    overlay::run_gtk_app(cfg, caption_rx_from_inference, cmd_rx, Arc::clone(&captions_enabled));
+    overlay::run_gtk_app(
+        cfg,
+        caption_rx_from_inference,
+        cmd_rx,
+        Arc::clone(&captions_enabled),
+        web_server,
+        monitor_tx,
+    );
+
+    // Write out the last cue of a subtitle recording left running at quit.
+    match subtitle_recorder.stop() {
+        Ok(Some(path)) => eprintln!("info: subtitles saved to {}", path.display()),
+        Ok(None) => {}
+        Err(e) => eprintln!("warn: failed to finish subtitle recording: {e:#}"),
+    }
+    transcript_log.close();
+    if let Some(path) = control_socket {
+        ipc::remove_socket(&path);
+    }
 }
 
 /// Returns the appropriate CUDA status message based on availability.
@@ -0,0 +1,236 @@
+//! Fullscreen detection for `auto_hide_fullscreen`: a second Wayland connection follows the
+//! compositor's windows through wlr-foreign-toplevel-management and reports the outputs
+//! where the focused window is fullscreen. Compositors without the protocol (e.g. GNOME)
+//! report nothing, so captions are never hidden there.
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use wayland_client::backend::ObjectId;
+use wayland_client::protocol::wl_output::{self, WlOutput};
+use wayland_client::protocol::wl_registry::{self, WlRegistry};
+use wayland_client::{event_created_child, Connection, Dispatch, Proxy, QueueHandle};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::zwlr_foreign_toplevel_handle_v1::{
+    self as handle, ZwlrForeignToplevelHandleV1,
+};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::zwlr_foreign_toplevel_manager_v1::{
+    self as manager, ZwlrForeignToplevelManagerV1,
+};
+
+/// Watch the compositor's windows on a background thread. The receiver gets the connector
+/// names of the outputs showing the focused window fullscreen whenever they change.
+pub fn watch() -> Receiver<Vec<String>> {
+    let (tx, rx) = mpsc::channel();
+    let spawned = std::thread::Builder::new()
+        .name("fullscreen".to_string())
+        .spawn(move || {
+            if let Err(e) = run(tx) {
+                eprintln!("warn: fullscreen detection stopped: {e:#}");
+            }
+        });
+    if let Err(e) = spawned {
+        eprintln!("warn: failed to start fullscreen detection: {e}");
+    }
+    rx
+}
+
+fn run(tx: Sender<Vec<String>>) -> Result<()> {
+    let conn = Connection::connect_to_env().context("connecting to the Wayland display")?;
+    let mut queue = conn.new_event_queue();
+    conn.display().get_registry(&queue.handle(), ());
+    let mut state = State::default();
+    queue.roundtrip(&mut state).context("listing Wayland globals")?;
+    if state.manager.is_none() {
+        eprintln!("info: compositor lacks wlr-foreign-toplevel-management; captions are not hidden over fullscreen windows");
+        return Ok(());
+    }
+
+    let mut sent = Vec::new();
+    while !state.finished {
+        queue.blocking_dispatch(&mut state).context("reading Wayland events")?;
+        let outputs = state.fullscreen_outputs();
+        if outputs != sent {
+            if tx.send(outputs.clone()).is_err() {
+                break; // overlay gone
+            }
+            sent = outputs;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+struct State {
+    /// Outputs by registry name, with their connector names once announced.
+    outputs: HashMap<u32, (WlOutput, Option<String>)>,
+    manager: Option<ZwlrForeignToplevelManagerV1>,
+    toplevels: HashMap<ObjectId, Toplevel>,
+    /// The compositor stopped sending toplevel events.
+    finished: bool,
+}
+
+/// A window as of its last `done` event, and the changes announced since.
+#[derive(Default)]
+struct Toplevel {
+    current: ToplevelState,
+    pending: ToplevelState,
+}
+
+#[derive(Default, Clone)]
+struct ToplevelState {
+    outputs: Vec<ObjectId>,
+    activated: bool,
+    fullscreen: bool,
+}
+
+impl State {
+    /// Connectors of the outputs where the focused window is fullscreen, sorted.
+    fn fullscreen_outputs(&self) -> Vec<String> {
+        let mut connectors: Vec<String> = self
+            .toplevels
+            .values()
+            .filter(|t| t.current.activated && t.current.fullscreen)
+            .flat_map(|t| t.current.outputs.iter())
+            .filter_map(|id| {
+                let (_, connector) = self.outputs.values().find(|(output, _)| output.id() == *id)?;
+                connector.clone()
+            })
+            .collect();
+        connectors.sort();
+        connectors.dedup();
+        connectors
+    }
+}
+
+/// Whether a window's state array (native-endian u32 values) marks it as focused and as
+/// fullscreen.
+fn parse_states(raw: &[u8]) -> (bool, bool) {
+    let states: Vec<u32> = raw
+        .chunks_exact(4)
+        .map(|c| u32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+        .collect();
+    (
+        states.contains(&(handle::State::Activated as u32)),
+        states.contains(&(handle::State::Fullscreen as u32)),
+    )
+}
+
+impl Dispatch<WlRegistry, ()> for State {
+    fn event(
+        state: &mut Self,
+        registry: &WlRegistry,
+        event: wl_registry::Event,
+        _: &(),
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_registry::Event::Global { name, interface, version } => match interface.as_str() {
+                // Connector names come with version 4.
+                "wl_output" => {
+                    let output = registry.bind::<WlOutput, _, _>(name, version.min(4), qh, ());
+                    state.outputs.insert(name, (output, None));
+                }
+                "zwlr_foreign_toplevel_manager_v1" => {
+                    state.manager = Some(registry.bind(name, version.min(3), qh, ()));
+                }
+                _ => {}
+            },
+            wl_registry::Event::GlobalRemove { name } => {
+                if let Some((output, _)) = state.outputs.remove(&name) {
+                    if output.version() >= 3 {
+                        output.release();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WlOutput, ()> for State {
+    fn event(
+        state: &mut Self,
+        output: &WlOutput,
+        event: wl_output::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wl_output::Event::Name { name } = event {
+            if let Some((_, connector)) = state.outputs.values_mut().find(|(o, _)| o == output) {
+                *connector = Some(name);
+            }
+        }
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &ZwlrForeignToplevelManagerV1,
+        event: manager::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            manager::Event::Toplevel { toplevel } => {
+                state.toplevels.insert(toplevel.id(), Toplevel::default());
+            }
+            manager::Event::Finished => state.finished = true,
+            _ => {}
+        }
+    }
+
+    event_created_child!(State, ZwlrForeignToplevelManagerV1, [
+        manager::EVT_TOPLEVEL_OPCODE => (ZwlrForeignToplevelHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        toplevel: &ZwlrForeignToplevelHandleV1,
+        event: handle::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let handle::Event::Closed = event {
+            state.toplevels.remove(&toplevel.id());
+            toplevel.destroy();
+            return;
+        }
+        let Some(entry) = state.toplevels.get_mut(&toplevel.id()) else {
+            return;
+        };
+        match event {
+            handle::Event::OutputEnter { output } => entry.pending.outputs.push(output.id()),
+            handle::Event::OutputLeave { output } => entry.pending.outputs.retain(|id| *id != output.id()),
+            handle::Event::State { state: raw } => {
+                (entry.pending.activated, entry.pending.fullscreen) = parse_states(&raw);
+            }
+            handle::Event::Done => entry.current = entry.pending.clone(),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw(states: &[u32]) -> Vec<u8> {
+        states.iter().flat_map(|s| s.to_ne_bytes()).collect()
+    }
+
+    #[test]
+    fn parse_states_finds_focus_and_fullscreen() {
+        // maximized = 0, minimized = 1, activated = 2, fullscreen = 3
+        assert_eq!(parse_states(&raw(&[2, 3])), (true, true));
+        assert_eq!(parse_states(&raw(&[3])), (false, true));
+        assert_eq!(parse_states(&raw(&[0, 2])), (true, false));
+        assert_eq!(parse_states(&[]), (false, false));
+    }
+}
@@ -0,0 +1,280 @@
+//! Scrollback window: every final caption of the session with timestamps, for lines that
+//! expired from the overlay before they were read. Opened from the tray or by clicking the
+//! unlocked floating overlay; the text can be selected and copied.
+
+use crate::stt::{Caption, SAMPLES_PER_SEC};
+use gtk4::prelude::*;
+use gtk4::{glib, Application};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// A pause at least this long starts a new paragraph (2s), in session samples.
+const PARAGRAPH_GAP_SAMPLES: u64 = 2 * SAMPLES_PER_SEC;
+/// A paragraph this long ends at the next sentence end, so long monologues still get
+/// regular timestamps.
+const MAX_PARAGRAPH_CHARS: usize = 300;
+
+/// One timestamped paragraph.
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    /// Local time the paragraph started, "HH:MM:SS".
+    stamp: String,
+    speaker: Option<String>,
+    color: Option<String>,
+    text: String,
+    end_sample: u64,
+}
+
+/// How a caption changed the history.
+#[derive(Debug, PartialEq)]
+enum Change {
+    /// Text was added to the last paragraph.
+    Extended(String),
+    /// A new paragraph was started.
+    Started,
+}
+
+/// Final captions grouped into paragraphs by speaker and pauses.
+#[derive(Default)]
+struct History {
+    entries: Vec<Entry>,
+}
+
+impl History {
+    /// Add a caption. `stamp` gives the current time, for a new paragraph. Partial
+    /// hypotheses are not kept.
+    fn push(&mut self, caption: &Caption, stamp: impl FnOnce() -> String) -> Option<Change> {
+        if !caption.is_final || caption.text.trim().is_empty() {
+            return None;
+        }
+        let speaker = caption.source.as_ref().map(|s| s.label.as_str());
+        if let Some(last) = self.entries.last_mut() {
+            let breaks = last.speaker.as_deref() != speaker
+                || caption.start_sample.saturating_sub(last.end_sample) >= PARAGRAPH_GAP_SAMPLES
+                || (last.text.chars().count() >= MAX_PARAGRAPH_CHARS && last.text.ends_with(['.', '?', '!']));
+            if !breaks {
+                last.text.push_str(&caption.text);
+                last.end_sample = caption.end_sample;
+                return Some(Change::Extended(caption.text.clone()));
+            }
+        }
+        self.entries.push(Entry {
+            stamp: stamp(),
+            speaker: speaker.map(str::to_string),
+            color: caption.source.as_ref().and_then(|s| s.color.clone()),
+            text: caption.text.trim_start().to_string(),
+            end_sample: caption.end_sample,
+        });
+        Some(Change::Started)
+    }
+}
+
+/// The window's widgets, built the first time it is opened.
+struct View {
+    window: gtk4::Window,
+    text_view: gtk4::TextView,
+    follow: gtk4::ToggleButton,
+}
+
+/// Session history and its window. Captions are collected while the window is closed, so
+/// opening it later shows everything since startup.
+pub struct HistoryWindow {
+    app: Application,
+    history: RefCell<History>,
+    view: RefCell<Option<View>>,
+}
+
+impl HistoryWindow {
+    pub fn new(app: &Application) -> Rc<Self> {
+        Rc::new(HistoryWindow {
+            app: app.clone(),
+            history: RefCell::new(History::default()),
+            view: RefCell::new(None),
+        })
+    }
+
+    /// Record a caption from the caption stream.
+    pub fn push(&self, caption: &Caption) {
+        let change = self.history.borrow_mut().push(caption, local_stamp);
+        let (Some(change), Some(view)) = (change, self.view.borrow().as_ref().map(|v| v.text_view.clone())) else {
+            return;
+        };
+        let buffer = view.buffer();
+        match change {
+            Change::Extended(text) => buffer.insert(&mut buffer.end_iter(), &text),
+            Change::Started => {
+                let history = self.history.borrow();
+                let entry = history.entries.last().expect("entry just added");
+                append_entry(&buffer, entry, history.entries.len() > 1);
+            }
+        }
+        self.scroll_if_following();
+    }
+
+    /// Show the window, building it on first use.
+    pub fn present(&self) {
+        if self.view.borrow().is_none() {
+            let view = self.build();
+            *self.view.borrow_mut() = Some(view);
+        }
+        if let Some(view) = self.view.borrow().as_ref() {
+            view.window.present();
+        }
+        self.scroll_if_following();
+    }
+
+    fn build(&self) -> View {
+        let buffer = gtk4::TextBuffer::new(None);
+        let tags = buffer.tag_table();
+        tags.add(&gtk4::TextTag::builder().name("timestamp").foreground("gray").build());
+        tags.add(&gtk4::TextTag::builder().name("speaker").weight(700).build());
+        buffer.create_mark(Some("end"), &buffer.end_iter(), false);
+        for (i, entry) in self.history.borrow().entries.iter().enumerate() {
+            append_entry(&buffer, entry, i > 0);
+        }
+
+        // Read-only but selectable, so text can be copied with the mouse or Ctrl+C.
+        let text_view = gtk4::TextView::builder()
+            .buffer(&buffer)
+            .editable(false)
+            .cursor_visible(false)
+            .wrap_mode(gtk4::WrapMode::WordChar)
+            .left_margin(12)
+            .right_margin(12)
+            .top_margin(8)
+            .bottom_margin(8)
+            .build();
+        let scrolled = gtk4::ScrolledWindow::builder()
+            .hscrollbar_policy(gtk4::PolicyType::Never)
+            .child(&text_view)
+            .build();
+
+        let follow = gtk4::ToggleButton::builder()
+            .label("Follow")
+            .tooltip_text("Scroll to new captions as they arrive")
+            .active(true)
+            .build();
+        let text_view_for_follow = text_view.clone();
+        follow.connect_toggled(move |button| {
+            if button.is_active() {
+                scroll_to_end(&text_view_for_follow);
+            }
+        });
+        let header = gtk4::HeaderBar::new();
+        header.pack_end(&follow);
+
+        let window = gtk4::Window::builder()
+            .application(&self.app)
+            .title("Caption History")
+            .default_width(600)
+            .default_height(400)
+            .hide_on_close(true)
+            .child(&scrolled)
+            .build();
+        window.set_titlebar(Some(&header));
+        View { window, text_view, follow }
+    }
+
+    fn scroll_if_following(&self) {
+        if let Some(view) = self.view.borrow().as_ref() {
+            if view.follow.is_active() {
+                scroll_to_end(&view.text_view);
+            }
+        }
+    }
+}
+
+fn local_stamp() -> String {
+    glib::DateTime::now_local()
+        .and_then(|now| now.format("%H:%M:%S"))
+        .map(|s| s.to_string())
+        .unwrap_or_default()
+}
+
+/// Append a paragraph as "[HH:MM:SS] Speaker: text".
+fn append_entry(buffer: &gtk4::TextBuffer, entry: &Entry, new_line: bool) {
+    let mut end = buffer.end_iter();
+    if new_line {
+        buffer.insert(&mut end, "\n");
+    }
+    buffer.insert_with_tags_by_name(&mut end, &format!("[{}] ", entry.stamp), &["timestamp"]);
+    if let Some(speaker) = &entry.speaker {
+        let tag = speaker_tag(buffer, entry.color.as_deref());
+        buffer.insert_with_tags(&mut end, &format!("{speaker}: "), &[&tag]);
+    }
+    buffer.insert(&mut end, &entry.text);
+}
+
+/// Bold tag for speaker labels, in the source's colour if it has one.
+fn speaker_tag(buffer: &gtk4::TextBuffer, color: Option<&str>) -> gtk4::TextTag {
+    let table = buffer.tag_table();
+    let Some(color) = color else {
+        return table.lookup("speaker").expect("created with the buffer");
+    };
+    let name = format!("speaker-{color}");
+    table.lookup(&name).unwrap_or_else(|| {
+        let tag = gtk4::TextTag::builder().name(name.as_str()).weight(700).build();
+        // An unparsable colour leaves the label in the default colour.
+        if let Ok(rgba) = gtk4::gdk::RGBA::parse(color) {
+            tag.set_foreground_rgba(Some(&rgba));
+        }
+        table.add(&tag);
+        tag
+    })
+}
+
+fn scroll_to_end(text_view: &gtk4::TextView) {
+    if let Some(mark) = text_view.buffer().mark("end") {
+        text_view.scroll_mark_onscreen(&mark);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stt::SAMPLES_PER_SEC as SEC;
+
+    fn texts(history: &History) -> Vec<(Option<&str>, &str)> {
+        history.entries.iter().map(|e| (e.speaker.as_deref(), e.text.as_str())).collect()
+    }
+
+    #[test]
+    fn fragments_join_until_speaker_change_or_pause() {
+        let mut history = History::default();
+        let stamp = || "12:00:00".to_string();
+        assert_eq!(history.push(&Caption::test_final(0, " Hello", None), stamp), Some(Change::Started));
+        assert_eq!(
+            history.push(&Caption::test_final(SEC / 2, " there.", None), stamp),
+            Some(Change::Extended(" there.".to_string()))
+        );
+        history.push(&Caption::test_final(SEC, " Hi.", Some("Me")), stamp);
+        history.push(&Caption::test_final(2 * SEC, " Anyway", Some("Me")), stamp);
+        // A long pause starts a new paragraph from the same speaker.
+        history.push(&Caption::test_final(10 * SEC, " Later.", Some("Me")), stamp);
+
+        assert_eq!(
+            texts(&history),
+            vec![(None, "Hello there."), (Some("Me"), "Hi. Anyway"), (Some("Me"), "Later.")]
+        );
+        assert_eq!(history.entries[0].stamp, "12:00:00");
+    }
+
+    #[test]
+    fn partials_are_skipped() {
+        let mut history = History::default();
+        let partial = Caption { is_final: false, ..Caption::test_final(0, " Hel", None) };
+        assert_eq!(history.push(&partial, || panic!("no paragraph for a partial")), None);
+        assert!(history.entries.is_empty());
+    }
+
+    #[test]
+    fn long_paragraphs_break_at_sentence_end() {
+        let mut history = History::default();
+        let sentence = " This sentence is about fifty characters in length.";
+        for i in 0..8 {
+            history.push(&Caption::test_final(i * SEC / 2, sentence, None), String::new);
+        }
+        assert_eq!(history.entries.len(), 2);
+        assert!(history.entries[0].text.chars().count() >= MAX_PARAGRAPH_CHARS);
+    }
+}
@@ -0,0 +1,148 @@
+//! Mirror overlays: click-through copies of the captions on other monitors (`[[mirrors]]`),
+//! e.g. on a projector and the laptop panel at once. Each has its own placement and
+//! appearance, and lays out the shared caption feed for its own width and font.
+
+use super::{apply_appearance, apply_exclusive_zone, apply_window_appearance, build_overlay_window, find_caption_label, line_width_px};
+use super::{monitors, pango_measure, remove_appearance, set_fullscreen_hidden, shell_layer, CaptionBuffer};
+use crate::config::{AppearanceConfig, Config, MirrorConfig, OverlayLayer};
+use crate::stt::Caption;
+use gtk4::prelude::*;
+use gtk4::{Application, ApplicationWindow, Label};
+use gtk4_layer_shell::LayerShell;
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+struct Mirror {
+    settings: MirrorConfig,
+    /// The mirror's effective config, from `Config::for_mirror`.
+    config: Config,
+    window: ApplicationWindow,
+    label: Label,
+    buffer: CaptionBuffer,
+}
+
+/// The mirror windows. They are shown while the main overlay is and their monitor is
+/// connected.
+pub struct Mirrors {
+    app: Application,
+    primary: ApplicationWindow,
+    list: RefCell<Vec<Mirror>>,
+}
+
+impl Mirrors {
+    pub fn new(app: &Application, primary: &ApplicationWindow, cfg: &Config) -> Rc<Self> {
+        let mirrors = Rc::new(Mirrors {
+            app: app.clone(),
+            primary: primary.clone(),
+            list: RefCell::new(Vec::new()),
+        });
+        let weak = Rc::downgrade(&mirrors);
+        primary.connect_visible_notify(move |_| {
+            if let Some(mirrors) = weak.upgrade() {
+                mirrors.sync();
+            }
+        });
+        mirrors.set(cfg);
+        mirrors
+    }
+
+    /// Replace the mirror windows with those configured in `cfg`.
+    pub fn set(self: &Rc<Self>, cfg: &Config) {
+        for old in self.list.borrow_mut().drain(..) {
+            remove_appearance(&old.window.widget_name());
+            old.window.destroy();
+        }
+        let built: Vec<Mirror> = cfg
+            .mirrors
+            .iter()
+            .enumerate()
+            .map(|(i, settings)| self.build(i, settings, cfg))
+            .collect();
+        *self.list.borrow_mut() = built;
+        self.sync();
+    }
+
+    fn build(self: &Rc<Self>, index: usize, settings: &MirrorConfig, cfg: &Config) -> Mirror {
+        let mirror_cfg = cfg.for_mirror(settings);
+        let name = format!("mirror-{index}");
+        let window = build_overlay_window(&self.app, &mirror_cfg, &name);
+        apply_appearance(&mirror_cfg.appearance, &name);
+        let label = find_caption_label(&window);
+        let layout = mirror_cfg.layout_appearance();
+        let buffer = CaptionBuffer::with_measure(
+            layout.max_lines as usize,
+            line_width_px(&layout),
+            layout.effective_expire_secs(),
+            pango_measure(&label, &layout),
+        );
+
+        let weak: Weak<Self> = Rc::downgrade(self);
+        monitors::reopen_when_closed(&window, move |_, _| {
+            if let Some(mirrors) = weak.upgrade() {
+                mirrors.sync();
+            }
+        });
+        Mirror { settings: settings.clone(), config: mirror_cfg, window, label, buffer }
+    }
+
+    /// Put each mirror on its monitor, and show it if the main overlay is shown and the
+    /// monitor is connected.
+    pub fn sync(&self) {
+        let visible = self.primary.is_visible();
+        let display = WidgetExt::display(&self.primary);
+        for mirror in self.list.borrow().iter() {
+            let monitor = monitors::find(&display, &mirror.settings.monitor);
+            if monitor.is_some() && mirror.window.monitor() != monitor {
+                mirror.window.set_monitor(monitor.as_ref());
+            }
+            mirror.window.set_visible(visible && monitor.is_some());
+        }
+    }
+
+    pub fn push_caption(&self, caption: &Caption) {
+        for mirror in self.list.borrow_mut().iter_mut() {
+            mirror.buffer.push_caption(caption.clone());
+            mirror.label.set_markup(&mirror.buffer.display_markup());
+        }
+    }
+
+    pub fn expire(&self) {
+        for mirror in self.list.borrow_mut().iter_mut() {
+            if mirror.buffer.expire() {
+                mirror.label.set_markup(&mirror.buffer.display_markup());
+            }
+        }
+    }
+
+    /// Follow a change of the main appearance, keeping each mirror's own changes.
+    pub fn update_appearance(&self, base: &AppearanceConfig) {
+        for mirror in self.list.borrow_mut().iter_mut() {
+            mirror.config.appearance = mirror.settings.appearance(base);
+            apply_window_appearance(&mirror.window, &mut mirror.buffer, &mirror.config.layout_appearance());
+            mirror.label.set_markup(&mirror.buffer.display_markup());
+            apply_exclusive_zone(&mirror.window, &mirror.config);
+        }
+    }
+
+    pub fn set_reserve_space(&self, reserve: bool) {
+        for mirror in self.list.borrow_mut().iter_mut() {
+            mirror.config.reserve_space = reserve;
+            apply_exclusive_zone(&mirror.window, &mirror.config);
+        }
+    }
+
+    pub fn set_layer(&self, layer: OverlayLayer) {
+        for mirror in self.list.borrow_mut().iter_mut() {
+            mirror.config.layer = layer;
+            mirror.window.set_layer(shell_layer(layer));
+        }
+    }
+
+    /// Hide the mirrors on `outputs`, where the focused window is fullscreen, if `enabled`.
+    pub fn hide_over_fullscreen(&self, outputs: &[String], enabled: bool) {
+        for mirror in self.list.borrow().iter() {
+            let covered = outputs.contains(&mirror.settings.monitor);
+            set_fullscreen_hidden(&mirror.window, &mirror.config, enabled && covered);
+        }
+    }
+}
@@ -1,15 +1,30 @@
 //! GTK4 overlay window: docked (wlr-layer-shell) and floating modes with caption display.
 
-use crate::config::{AppearanceConfig, Config, DockPosition, OverlayMode, ScreenEdge};
+use crate::config::{AppearanceConfig, Config, DockPosition, MirrorConfig, OverlayLayer, OverlayMode, ScreenEdge, TextAlign};
+use crate::stt::{Caption, SourceTag};
+use crate::web::{Line, WebServer};
 use gtk4::prelude::*;
 use gtk4::{Application, ApplicationWindow, Label};
 use gtk4::glib;
 use gtk4_layer_shell::{Edge, KeyboardMode, Layer, LayerShell};
 use std::sync::{Arc, atomic::{AtomicBool, AtomicI32, Ordering}};
 use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::rc::Rc;
 use std::sync::OnceLock;
 use std::time::Instant;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Widget name of the main overlay window; mirror windows are "mirror-<n>".
+const OVERLAY_NAME: &str = "overlay";
+/// Width lines are filled to when the overlay width is 0 (natural size).
+const AUTO_WIDTH_PX: i32 = 600;
+/// Graphemes of recent text kept for overlap detection.
+const TAIL_GRAPHEMES: usize = 60;
+/// Shortest re-emitted prefix treated as overlap, in characters; shorter matches are
+/// usually coincidence.
+const MIN_OVERLAP_CHARS: usize = 4;
 
 /// Whether the compositor shifts widget-local coordinates when layer-shell margins change mid-drag.
 /// KDE, Sway, and Hyprland do this (GTK's drag offset shrinks as the surface moves), requiring
@@ -28,33 +43,119 @@ fn compositor_shifts_coords_on_margin_change() -> bool {
 struct CaptionLine {
     text: String,
     last_active: Instant,
+    /// Source whose speech fills this line (None for the mixed stream).
+    speaker: Option<SourceTag>,
+    /// The line opens a speaker turn and starts with the "Label:" prefix.
+    labelled: bool,
 }
 
+/// Measures the rendered width of a line of text.
+type Measure = Box<dyn Fn(&str) -> usize>;
+
 /// Buffer that accumulates caption text in lines with fill-and-shift model.
-/// Lines are filled word-by-word up to max_chars_per_line. When all lines are full
+/// Lines are filled word-by-word up to max_line_width. When all lines are full
 /// and new text arrives, the oldest line is removed, all lines shift up, and new
 /// text fills the freed bottom line. Individual lines expire after idle_secs of silence.
 struct CaptionBuffer {
     /// Ordered lines from oldest (top, shown first) to newest (bottom, shown last).
     lines: Vec<CaptionLine>,
     max_lines: usize,
-    max_chars_per_line: usize,
+    /// Widest a line may measure, in the units of `measure`.
+    max_line_width: usize,
+    /// Line width as rendered: pixels from a Pango layout in the overlay, columns in tests.
+    measure: Measure,
     expire_secs: u64,
     /// Track the last few words to detect and skip repeated output from the RNNT decoder.
     last_tail: String,
+    /// Source of the most recent fragment, when captions are tagged per source.
+    speaker: Option<SourceTag>,
+    /// Latest partial hypothesis of each source, shown after that speaker's committed text
+    /// until replaced. Few sources are captured at once, so a list is enough.
+    partials: Vec<(Option<SourceTag>, String)>,
+}
+
+/// A line as displayed: committed text, followed by its speaker's partial hypothesis when
+/// this is the speaker's last line.
+struct ShownLine<'a> {
+    /// None for a speaker whose partial arrived before any of their text was committed.
+    line: Option<&'a CaptionLine>,
+    speaker: Option<&'a SourceTag>,
+    partial: Option<&'a str>,
+}
+
+impl ShownLine<'_> {
+    /// The committed text, or the bare label of a speaker with only a partial.
+    fn text(&self) -> String {
+        match (self.line, self.speaker) {
+            (Some(line), _) => line.text.clone(),
+            (None, Some(tag)) => format!("{}:", tag.label),
+            (None, None) => String::new(),
+        }
+    }
 }
 
 impl CaptionBuffer {
-    fn new(max_lines: usize, max_chars_per_line: usize, expire_secs: u64) -> Self {
+    /// Buffer whose lines are measured in columns: one per character, two for wide ones.
+    #[cfg(test)]
+    fn new(max_lines: usize, max_line_width: usize, expire_secs: u64) -> Self {
+        Self::with_measure(max_lines, max_line_width, expire_secs, Box::new(text_width))
+    }
+
+    fn with_measure(max_lines: usize, max_line_width: usize, expire_secs: u64, measure: Measure) -> Self {
         CaptionBuffer {
             lines: Vec::new(),
             max_lines,
-            max_chars_per_line,
+            max_line_width,
+            measure,
             expire_secs,
             last_tail: String::new(),
+            speaker: None,
+            partials: Vec::new(),
         }
     }
 
+    /// Add a caption from the inference thread. A partial hypothesis is shown provisionally
+    /// until the next caption from the same source replaces it; final text is committed.
+    fn push_caption(&mut self, caption: Caption) {
+        self.partials.retain(|(source, _)| *source != caption.source);
+        if !caption.is_final {
+            let text = caption.text.trim();
+            if !text.is_empty() {
+                self.partials.push((caption.source, text.to_string()));
+            }
+            return;
+        }
+        if !caption.may_overlap {
+            // The engine never repeats itself: a repeated word was really said twice.
+            self.last_tail.clear();
+        }
+        self.push_from(caption.source.as_ref(), caption.text);
+    }
+
+    /// Add a fragment heard on `source`. When the speaker changes, their turn starts on a
+    /// fresh line prefixed with the source label, e.g. "Me: ...".
+    fn push_from(&mut self, source: Option<&SourceTag>, text: String) {
+        if text.trim().is_empty() {
+            return;
+        }
+        if let Some(tag) = source {
+            // A new turn also starts once earlier lines have expired, so the label is never lost.
+            if self.speaker.as_ref() != Some(tag) || self.lines.is_empty() {
+                self.speaker = Some(tag.clone());
+                // Overlap detection only applies within one decoder's output.
+                self.last_tail.clear();
+                self.add_new_line(format!("{}:", tag.label));
+                if let Some(line) = self.lines.last_mut() {
+                    line.labelled = true;
+                }
+                // The new turn never continues the previous speaker's last word.
+                self.push(format!(" {}", text.trim_start()));
+                return;
+            }
+        }
+        self.push(text);
+    }
+
     /// Add a new caption fragment, deduplicating overlapping text from streaming RNNT.
     /// Preserves leading/trailing whitespace from the engine — these signal word
     /// boundaries (e.g. " ve" = new word, "ve" = continuation of previous word).
@@ -87,7 +188,7 @@ impl CaptionBuffer {
             let idx = self.lines.len() - 1;
             let combined = format!("{}{}", self.lines[idx].text.clone(), fragment);
 
-            if combined.len() <= self.max_chars_per_line {
+            if (self.measure)(&combined) <= self.max_line_width {
                 // Fits on current line: append directly.
                 self.lines[idx].text = combined;
                 self.lines[idx].last_active = Instant::now();
@@ -100,13 +201,13 @@ impl CaptionBuffer {
                     self.lines[idx].text = self.lines[idx].text[..=last_space_pos].trim_end().to_string();
 
                     // Add new line with partial + continuation joined.
-                    self.add_new_line(format!("{}{}", partial_word, fragment));
+                    self.add_wrapped_lines(&format!("{}{}", partial_word, fragment));
                 } else {
                     // Entire line is one word with no space: start fresh on new line.
                     // Remove the old line before calling add_new_line to avoid stale index
                     // if add_new_line shifts (when buffer is at max_lines capacity).
                     let old_text = self.lines.remove(idx).text;
-                    self.add_new_line(format!("{}{}", old_text, fragment));
+                    self.add_wrapped_lines(&format!("{}{}", old_text, fragment));
                 }
             }
         } else {
@@ -119,20 +220,20 @@ impl CaptionBuffer {
 
                 if self.lines.is_empty() {
                     // Start a new line with this word.
-                    self.add_new_line(word.to_string());
+                    self.add_wrapped_lines(word);
                 } else {
                     let idx = self.lines.len() - 1;
 
                     if self.lines[idx].text.is_empty() {
                         // Current line is empty: place word directly (no space prefix).
                         self.lines[idx].text = word.to_string();
-                    } else if self.lines[idx].text.len() + 1 + word.len() <= self.max_chars_per_line {
+                    } else if (self.measure)(&format!("{} {word}", self.lines[idx].text)) <= self.max_line_width {
                         // Room on current line: append with space.
                         self.lines[idx].text.push(' ');
                         self.lines[idx].text.push_str(word);
                     } else {
                         // Overflow: start new line (shifts if at max_lines).
-                        self.add_new_line(word.to_string());
+                        self.add_wrapped_lines(word);
                     }
                 }
             }
@@ -145,9 +246,7 @@ impl CaptionBuffer {
         }
 
         // Rebuild tail for overlap detection.
-        let display = self.all_text();
-        let tail_start = display.len().saturating_sub(60);
-        self.last_tail = display[tail_start..].to_string();
+        self.last_tail = tail_of(&self.all_text());
     }
 
     /// Add a new line, shifting off the oldest line if at max_lines capacity.
@@ -158,9 +257,33 @@ impl CaptionBuffer {
         self.lines.push(CaptionLine {
             text,
             last_active: Instant::now(),
+            speaker: self.speaker.clone(),
+            labelled: false,
         });
     }
 
+    /// Add `text` on new lines. Runs of CJK text have no spaces to wrap at, so where it is
+    /// wider than a line it is broken next to a wide character; a long word in other scripts
+    /// stays whole.
+    fn add_wrapped_lines(&mut self, text: &str) {
+        let mut line = String::new();
+        let mut prev_wide = false;
+        for grapheme in text.graphemes(true) {
+            let wide = text_width(grapheme) > 1;
+            if !line.is_empty()
+                && (wide || prev_wide)
+                && (self.measure)(&format!("{line}{grapheme}")) > self.max_line_width
+            {
+                self.add_new_line(std::mem::take(&mut line));
+            }
+            line.push_str(grapheme);
+            prev_wide = wide;
+        }
+        if !line.is_empty() {
+            self.add_new_line(line);
+        }
+    }
+
     /// Join all line text with empty string. Each line's text is properly spaced already.
     fn all_text(&self) -> String {
         self.lines.iter().map(|l| l.text.as_str()).collect::<Vec<_>>().join("")
@@ -174,15 +297,18 @@ impl CaptionBuffer {
             return new.to_string();
         }
         let tail_lower = tail.to_lowercase();
-        let new_lower = new.to_lowercase();
-
-        // Only consider overlaps of 4+ characters to avoid false positives.
-        let max_check = tail_lower.len().min(new_lower.len());
-        for overlap_len in (4..=max_check).rev() {
-            let tail_suffix = &tail_lower[tail_lower.len() - overlap_len..];
-            let new_prefix = &new_lower[..overlap_len];
-            if tail_suffix == new_prefix {
-                let remainder = new[overlap_len..].trim_start();
+
+        // Try prefixes of `new` ending at grapheme boundaries, longest first. Each one is
+        // lowercased on its own: lowercasing can change byte lengths, so offsets into a
+        // lowercased copy do not carry over to `new`.
+        let ends: Vec<usize> = new.grapheme_indices(true).map(|(i, g)| i + g.len()).collect();
+        for &end in ends.iter().rev() {
+            let prefix = &new[..end];
+            if prefix.chars().count() < MIN_OVERLAP_CHARS {
+                break;
+            }
+            if tail_lower.ends_with(&prefix.to_lowercase()) {
+                let remainder = new[end..].trim_start();
                 if !remainder.is_empty() {
                     return remainder.to_string();
                 }
@@ -202,30 +328,137 @@ impl CaptionBuffer {
         if self.lines[0].last_active <= cutoff {
             self.lines.remove(0);
             // Rebuild tail after removal.
-            let display = self.all_text();
-            let tail_start = display.len().saturating_sub(60);
-            self.last_tail = display[tail_start..].to_string();
+            self.last_tail = tail_of(&self.all_text());
             true
         } else {
             false
         }
     }
 
-    /// Join all lines with newline separators for display.
+    /// The committed lines with each speaker's partial hypothesis after their last line. A
+    /// speaker with nothing committed yet gets a line of their own at the bottom.
+    fn shown_lines(&self) -> Vec<ShownLine<'_>> {
+        let mut shown: Vec<ShownLine> = self
+            .lines
+            .iter()
+            .map(|line| ShownLine { line: Some(line), speaker: line.speaker.as_ref(), partial: None })
+            .collect();
+        for (speaker, partial) in &self.partials {
+            match shown.iter_mut().rev().find(|s| s.speaker == speaker.as_ref()) {
+                Some(s) => s.partial = Some(partial),
+                None => shown.push(ShownLine { line: None, speaker: speaker.as_ref(), partial: Some(partial) }),
+            }
+        }
+        shown
+    }
+
+    /// Join all lines with newline separators (plain-text view of `display_markup`).
+    #[cfg(test)]
     fn display_text(&self) -> String {
-        self.lines.iter().map(|l| l.text.as_str()).collect::<Vec<_>>().join("\n")
+        self.shown_lines()
+            .iter()
+            .map(|shown| {
+                let text = shown.text();
+                match shown.partial {
+                    Some(partial) if text.is_empty() => partial.to_string(),
+                    Some(partial) => format!("{text} {partial}"),
+                    None => text,
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Like `display_text`, as Pango markup with speaker labels emphasised and coloured.
+    fn display_markup(&self) -> String {
+        self.shown_lines()
+            .iter()
+            .map(|shown| {
+                let markup = match shown.line {
+                    Some(line) => line_markup(line),
+                    None => shown.speaker.map(|tag| label_markup(tag, &shown.text())).unwrap_or_default(),
+                };
+                // The partial hypothesis trails its speaker's text, in italics until it is final.
+                match shown.partial {
+                    Some(partial) if markup.is_empty() => format!("<i>{}</i>", glib::markup_escape_text(partial)),
+                    Some(partial) => format!("{markup} <i>{}</i>", glib::markup_escape_text(partial)),
+                    None => markup,
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Mirror the lines to the caption server, if one is running.
+    fn publish(&self, web: Option<&WebServer>) {
+        if let Some(web) = web {
+            let lines: Vec<Line> = self
+                .shown_lines()
+                .iter()
+                .map(|shown| Line { text: shown.text(), partial: shown.partial.map(str::to_string) })
+                .collect();
+            web.publish_lines(&lines);
+        }
     }
 
-    /// Update the buffer's configuration (max_chars_per_line and expire_secs).
+    /// Update the buffer's configuration (max_line_width and expire_secs).
     /// Called when appearance config changes via hot-reload.
-    fn update_config(&mut self, max_lines: usize, max_chars_per_line: usize, expire_secs: u64) {
+    fn update_config(&mut self, max_lines: usize, max_line_width: usize, expire_secs: u64) {
         self.max_lines = max_lines;
-        self.max_chars_per_line = max_chars_per_line;
+        self.max_line_width = max_line_width;
         self.expire_secs = expire_secs;
     }
+
+    /// Measure lines with a new font.
+    fn set_measure(&mut self, measure: Measure) {
+        self.measure = measure;
+    }
+}
+
+/// Width of `text` in columns: wide characters (CJK, most emoji) take two.
+/// Pango markup for a committed line, with the label of a speaker turn emphasised.
+fn line_markup(line: &CaptionLine) -> String {
+    let label_len = match (&line.speaker, line.labelled) {
+        (Some(tag), true) if line.text.starts_with(&tag.label) => tag.label.len() + 1,
+        _ => 0,
+    };
+    match &line.speaker {
+        Some(tag) if label_len > 0 && label_len <= line.text.len() => {
+            let (label, rest) = line.text.split_at(label_len);
+            format!("{}{}", label_markup(tag, label), glib::markup_escape_text(rest))
+        }
+        _ => glib::markup_escape_text(&line.text).to_string(),
+    }
+}
+
+/// Bold `label`, in the speaker's colour when they have one.
+fn label_markup(tag: &SourceTag, label: &str) -> String {
+    let label = format!("<b>{}</b>", glib::markup_escape_text(label));
+    match tag.color.as_deref() {
+        Some(color) => format!("<span foreground=\"{}\">{label}</span>", glib::markup_escape_text(color)),
+        None => label,
+    }
+}
+
+fn text_width(text: &str) -> usize {
+    text.width()
+}
+
+/// The last `TAIL_GRAPHEMES` graphemes of `text`.
+fn tail_of(text: &str) -> String {
+    let start = text.grapheme_indices(true).rev().nth(TAIL_GRAPHEMES - 1).map_or(0, |(i, _)| i);
+    text[start..].to_string()
 }
 
 pub mod input_region;
+mod fullscreen;
+mod history;
+mod mirrors;
+mod monitors;
+
+use history::HistoryWindow;
+use mirrors::Mirrors;
+pub use monitors::MonitorInfo;
 
 /// Commands sent to the overlay from the tray / main integration.
 #[derive(Debug, Clone)]
@@ -241,6 +474,18 @@ pub enum OverlayCommand {
     /// Update caption text (also sent as plain String via glib channel in normal flow).
     #[allow(dead_code)]
     SetCaption(String),
+    /// Open the caption history window.
+    ShowHistory,
+    /// Show the overlay on the monitor with this connector name (None = compositor's choice).
+    SetMonitor(Option<String>),
+    /// Reserve screen space for the docked overlay, or stop doing so.
+    SetReserveSpace(bool),
+    /// Move the overlay to another layer-shell layer.
+    SetLayer(OverlayLayer),
+    /// Hide the captions over a focused fullscreen window, or stop doing so.
+    SetAutoHideFullscreen(bool),
+    /// Replace the mirror windows on other monitors.
+    SetMirrors(Vec<MirrorConfig>),
     /// Quit the application cleanly (sent by tray Quit and SIGTERM handler).
     Quit,
 }
@@ -255,14 +500,18 @@ pub type CaptionsEnabled = Arc<AtomicBool>;
 ///
 /// Parameters:
 /// - `config`: initial configuration
-/// - `caption_rx`: mpsc channel receiver delivering caption strings from inference thread
+/// - `caption_rx`: mpsc channel receiver delivering captions from the inference thread(s)
 /// - `cmd_rx`: mpsc channel receiver delivering OverlayCommand from tray
 /// - `captions_enabled`: shared bool for left-click tray toggle
+/// - `web`: caption server mirroring the overlay, if enabled
+/// - `monitor_tx`: receives the connected monitors at startup and whenever they change
 pub fn run_gtk_app(
     config: Config,
-    caption_rx: std::sync::mpsc::Receiver<String>,
+    caption_rx: std::sync::mpsc::Receiver<Caption>,
     cmd_rx: std::sync::mpsc::Receiver<OverlayCommand>,
     captions_enabled: CaptionsEnabled,
+    web: Option<WebServer>,
+    monitor_tx: std::sync::mpsc::Sender<Vec<MonitorInfo>>,
 ) {
     let app = Application::builder()
         .application_id("com.subtidal.app")
@@ -278,10 +527,45 @@ pub fn run_gtk_app(
 
     app.connect_activate(move |app| {
         let cfg = config_clone.lock().unwrap().clone();
-        let window = build_overlay_window(app, &cfg);
+        let window = build_overlay_window(app, &cfg, OVERLAY_NAME);
+        // Collects the session's captions from the start; its window opens on request.
+        let history = HistoryWindow::new(app);
 
         // Apply initial appearance.
-        apply_appearance(&cfg.appearance);
+        apply_appearance(&cfg.appearance, OVERLAY_NAME);
+
+        // Copies of the overlay on other monitors, fed the same captions.
+        let mirrors = Mirrors::new(app, &window, &cfg);
+
+        // Follow the configured monitor through hotplug; a floating overlay takes the
+        // position saved for the monitor it lands on. Mirrors follow their own monitors.
+        let window_for_monitors = window.clone();
+        let config_for_monitors = Arc::clone(&config_clone);
+        let mirrors_for_monitors = Rc::clone(&mirrors);
+        monitors::watch(&window, &config_clone, monitor_tx.clone(), move |moved| {
+            if moved && config_for_monitors.lock().unwrap().overlay_mode == OverlayMode::Floating {
+                restore_floating_position(&window_for_monitors);
+            }
+            mirrors_for_monitors.sync();
+        });
+
+        // Fade the overlays out while the focused window is fullscreen on their monitor.
+        let fullscreen_rx = fullscreen::watch();
+        let mut fullscreen_outputs: Vec<String> = Vec::new();
+        let window_for_fullscreen = window.clone();
+        let config_for_fullscreen = Arc::clone(&config_clone);
+        let mirrors_for_fullscreen = Rc::clone(&mirrors);
+        glib::timeout_add_local(std::time::Duration::from_millis(250), move || {
+            while let Ok(latest) = fullscreen_rx.try_recv() {
+                fullscreen_outputs = latest;
+            }
+            let cfg = config_for_fullscreen.lock().unwrap();
+            let connector = monitors::current_connector(&window_for_fullscreen);
+            let covered = connector.is_some_and(|c| fullscreen_outputs.contains(&c));
+            set_fullscreen_hidden(&window_for_fullscreen, &cfg, cfg.auto_hide_fullscreen && covered);
+            mirrors_for_fullscreen.hide_over_fullscreen(&fullscreen_outputs, cfg.auto_hide_fullscreen);
+            glib::ControlFlow::Continue
+        });
 
         // Dragging flag: when true, suppress all GTK mutations except margin updates.
         // Any relayout (caption text, CSS reload, widget resize) during a drag causes
@@ -290,7 +574,7 @@ pub fn run_gtk_app(
 
         // Initial drag handler for floating + unlocked.
         if cfg.overlay_mode == OverlayMode::Floating && !cfg.locked {
-            add_drag_handler(&window, &is_dragging);
+            add_drag_handler(&window, &is_dragging, &history);
         }
 
         // Wire up caption receiver using glib timeout_add to poll.
@@ -298,11 +582,12 @@ pub fn run_gtk_app(
         let window_clone = window.clone();
         let enabled = Arc::clone(&captions_enabled_clone);
         let caption_rx_clone = Arc::clone(&caption_rx);
-        let max_chars_per_line = estimate_max_chars(cfg.appearance.width, cfg.appearance.font_size, cfg.appearance.effective_char_width_fraction()) as usize;
-        let caption_buffer = Rc::new(RefCell::new(CaptionBuffer::new(
-            cfg.appearance.max_lines as usize,
-            max_chars_per_line,
-            cfg.appearance.effective_expire_secs(),
+        let layout = cfg.layout_appearance();
+        let caption_buffer = Rc::new(RefCell::new(CaptionBuffer::with_measure(
+            layout.max_lines as usize,
+            line_width_px(&layout),
+            layout.effective_expire_secs(),
+            pango_measure(&label, &layout),
         )));
 
         // Poll for new captions and append to buffer.
@@ -310,18 +595,28 @@ pub fn run_gtk_app(
         let label_for_poll = label.clone();
         let window_for_poll = window_clone.clone();
         let dragging_for_caption = Rc::clone(&is_dragging);
+        let web_for_poll = web.clone();
+        let history_for_poll = Rc::clone(&history);
+        let mirrors_for_poll = Rc::clone(&mirrors);
         glib::timeout_add_local(std::time::Duration::from_millis(100), move || {
             if let Ok(rx) = caption_rx_clone.try_lock() {
                 let mut buf = buf_for_poll.borrow_mut();
-                while let Ok(text) = rx.try_recv() {
+                let mut changed = false;
+                while let Ok(caption) = rx.try_recv() {
                     if enabled.load(Ordering::Relaxed) {
-                        buf.push(text);
+                        history_for_poll.push(&caption);
+                        mirrors_for_poll.push_caption(&caption);
+                        buf.push_caption(caption);
+                        changed = true;
                         if !dragging_for_caption.get() {
-                            label_for_poll.set_text(&buf.display_text());
+                            label_for_poll.set_markup(&buf.display_markup());
                             window_for_poll.set_visible(true);
                         }
                     }
                 }
+                if changed {
+                    buf.publish(web_for_poll.as_ref());
+                }
             }
             glib::ControlFlow::Continue
         });
@@ -330,11 +625,15 @@ pub fn run_gtk_app(
         let buf_for_expire = Rc::clone(&caption_buffer);
         let label_for_expire = label.clone();
         let dragging_for_expire = Rc::clone(&is_dragging);
+        let web_for_expire = web.clone();
+        let mirrors_for_expire = Rc::clone(&mirrors);
         glib::timeout_add_local(std::time::Duration::from_secs(1), move || {
+            mirrors_for_expire.expire();
             if !dragging_for_expire.get() {
                 let mut buf = buf_for_expire.borrow_mut();
                 if buf.expire() {
-                    label_for_expire.set_text(&buf.display_text());
+                    label_for_expire.set_markup(&buf.display_markup());
+                    buf.publish(web_for_expire.as_ref());
                 }
             }
             glib::ControlFlow::Continue
@@ -346,12 +645,24 @@ pub fn run_gtk_app(
         let cmd_rx_clone = Arc::clone(&cmd_rx);
         let dragging_for_cmd = Rc::clone(&is_dragging);
         let buf_for_cmd = Rc::clone(&caption_buffer);
+        let web_for_cmd = web.clone();
+        let history_for_cmd = Rc::clone(&history);
+        let mirrors_for_cmd = Rc::clone(&mirrors);
 
         glib::timeout_add_local(std::time::Duration::from_millis(100), move || {
             if let Ok(rx) = cmd_rx_clone.try_lock() {
                 while let Ok(cmd) = rx.try_recv() {
                     if !dragging_for_cmd.get() {
-                        handle_overlay_command(&window_clone2, cmd, &config_for_cmd, &dragging_for_cmd, &buf_for_cmd);
+                        handle_overlay_command(
+                            &window_clone2,
+                            cmd,
+                            &config_for_cmd,
+                            &dragging_for_cmd,
+                            &buf_for_cmd,
+                            web_for_cmd.as_ref(),
+                            &history_for_cmd,
+                            &mirrors_for_cmd,
+                        );
                     }
                 }
             }
@@ -364,42 +675,44 @@ pub fn run_gtk_app(
     app.run_with_args::<&str>(&[]);
 }
 
-/// Build the overlay window for the given config.
-/// Uses gtk4-layer-shell for both docked and floating modes (Layer::Top).
-fn build_overlay_window(app: &Application, cfg: &Config) -> ApplicationWindow {
+/// Build an overlay window for the given config, named `name` for styling.
+/// Uses gtk4-layer-shell for both docked and floating modes, on the configured layer.
+fn build_overlay_window(app: &Application, cfg: &Config, name: &str) -> ApplicationWindow {
     let window = ApplicationWindow::builder()
         .application(app)
         .decorated(false)
         .resizable(false)
         .title("subtidal")
         .build();
+    // Overlay styling applies to this window only, not the history window or other overlays.
+    window.set_widget_name(name);
 
     // Initialize layer shell.
     window.init_layer_shell();
-    window.set_layer(Layer::Top);
-    window.set_exclusive_zone(0); // don't push other windows aside
+    window.set_layer(shell_layer(cfg.layer));
+    monitors::bind(&window, cfg.monitor.as_deref());
 
     match cfg.overlay_mode {
         OverlayMode::Docked => configure_docked(&window, &cfg.screen_edge, &cfg.dock_position),
         OverlayMode::Floating => configure_floating(&window, cfg),
     }
 
-    // Build caption label with wrapping.
-    // max_width_chars caps the label's natural width, forcing GTK to wrap text
-    // instead of expanding the label/window to fit one long line.
-    let max_chars = estimate_max_chars(cfg.appearance.width, cfg.appearance.font_size, cfg.appearance.effective_char_width_fraction());
+    // Build caption label. CaptionBuffer breaks lines to fit the label's width as Pango
+    // measures them, so wrapping is only a safety net.
+    let layout = cfg.layout_appearance();
     let label = Label::builder()
         .label("")
         .wrap(true)
         .wrap_mode(gtk4::pango::WrapMode::WordChar)
-        .max_width_chars(max_chars)
-        .lines(cfg.appearance.max_lines as i32)
-        .xalign(0.0) // left-align text
+        .lines(layout.max_lines as i32)
         .build();
+    apply_text_align(&label, layout.text_align);
     label.set_ellipsize(gtk4::pango::EllipsizeMode::End);
     label.set_widget_name("caption-label");
     window.set_child(Some(&label));
-    window.set_width_request(cfg.appearance.width);
+    window.set_width_request(layout.width);
+    // Other windows are only pushed aside in docked mode with reserve_space.
+    apply_exclusive_zone(&window, cfg);
 
     // Set click-through after window maps.
     let is_locked = cfg.locked || cfg.overlay_mode == OverlayMode::Docked;
@@ -414,6 +727,29 @@ fn build_overlay_window(app: &Application, cfg: &Config) -> ApplicationWindow {
     window
 }
 
+fn shell_layer(layer: OverlayLayer) -> Layer {
+    match layer {
+        OverlayLayer::Background => Layer::Background,
+        OverlayLayer::Bottom => Layer::Bottom,
+        OverlayLayer::Top => Layer::Top,
+        OverlayLayer::Overlay => Layer::Overlay,
+    }
+}
+
+/// Hide an overlay's captions while a fullscreen window is focused on its monitor. The
+/// window stays mapped, but transparent and click-through, so it keeps its place.
+fn set_fullscreen_hidden(window: &ApplicationWindow, cfg: &Config, hidden: bool) {
+    if (window.opacity() == 0.0) == hidden {
+        return;
+    }
+    window.set_opacity(if hidden { 0.0 } else { 1.0 });
+    if hidden || cfg.locked || cfg.overlay_mode == OverlayMode::Docked {
+        input_region::set_empty_input_region(window);
+    } else {
+        input_region::clear_input_region(window);
+    }
+}
+
 fn configure_docked(window: &ApplicationWindow, edge: &ScreenEdge, dock_pos: &DockPosition) {
     // Always anchor to the selected edge.
     let anchor_edge = match edge {
@@ -476,51 +812,102 @@ fn configure_floating(window: &ApplicationWindow, cfg: &Config) {
     });
 
     // Position the window via margins from the anchored edges.
-    window.set_margin(Edge::Left, cfg.position.x);
-    window.set_margin(Edge::Top, cfg.position.y);
+    let position = cfg.position_on(monitors::current_connector(window).as_deref());
+    window.set_margin(Edge::Left, position.x);
+    window.set_margin(Edge::Top, position.y);
+}
+
+/// Move the floating overlay to the position saved for the monitor it is on.
+fn restore_floating_position(window: &ApplicationWindow) {
+    let position = Config::load().position_on(monitors::current_connector(window).as_deref());
+    window.set_margin(Edge::Left, position.x);
+    window.set_margin(Edge::Top, position.y);
 }
 
 /// Build CSS string from appearance config.
 /// AC3.7: Verify CSS contains configured appearance settings.
 /// This is a pure function that can be tested without GTK display.
-fn build_css(appearance: &AppearanceConfig) -> String {
+/// Rules are scoped to the window named `window_name`, so mirrors can be styled apart.
+fn build_css(appearance: &AppearanceConfig, window_name: &str) -> String {
+    // Options left at their defaults are omitted, so the theme's font and spacing apply.
+    let mut optional = Vec::new();
+    let family = appearance.font_family.trim();
+    if !family.is_empty() {
+        optional.push(format!("font-family: \"{}\";", family.replace('\\', "\\\\").replace('"', "\\\"")));
+    }
+    if appearance.line_spacing > 0.0 {
+        optional.push(format!("line-height: {};", appearance.line_spacing));
+    }
+    let shadows = text_shadows(appearance);
+    if !shadows.is_empty() {
+        optional.push(format!("text-shadow: {};", shadows.join(", ")));
+    }
     format!(
         r#"
-        window {{
+        window#{window_name} {{
             background-color: {bg};
-            border-radius: 12px;
+            border-radius: {radius}px;
         }}
-        #caption-label {{
+        window#{window_name} #caption-label {{
             color: {fg};
             font-size: {fs}pt;
-            padding: 8px 12px;
+            font-weight: {weight};
+            padding: {pad_y}px {pad_x}px;
+            {optional}
         }}
         "#,
         bg = appearance.background_color,
+        radius = appearance.border_radius,
         fg = appearance.text_color,
         fs = appearance.font_size,
+        weight = appearance.font_weight.clamp(100, 900),
+        pad_y = appearance.padding_y.max(0),
+        pad_x = appearance.padding_x.max(0),
+        optional = optional.join("\n            "),
     )
 }
 
-/// Set CSS on the caption label and window to reflect appearance config.
-///
-/// Uses a thread-local provider to avoid resource leaks: old provider is removed
-/// before creating a new one on each call.
-pub fn apply_appearance(appearance: &AppearanceConfig) {
-    thread_local! {
-        static CSS_PROVIDER: RefCell<Option<gtk4::CssProvider>> = const { RefCell::new(None) };
+/// CSS text-shadow list: the outline, drawn as copies of the text offset in eight
+/// directions, then the configured extra shadow.
+fn text_shadows(appearance: &AppearanceConfig) -> Vec<String> {
+    let mut shadows = Vec::new();
+    let w = appearance.outline_width;
+    if w > 0.0 {
+        for (dx, dy) in [(-w, -w), (0.0, -w), (w, -w), (-w, 0.0), (w, 0.0), (-w, w), (0.0, w), (w, w)] {
+            shadows.push(format!("{dx}px {dy}px {}", appearance.outline_color));
+        }
+    }
+    let extra = appearance.text_shadow.trim();
+    if !extra.is_empty() {
+        shadows.push(extra.to_string());
     }
+    shadows
+}
 
-    let css = build_css(appearance);
+fn apply_text_align(label: &Label, align: TextAlign) {
+    let (xalign, justify) = match align {
+        TextAlign::Left => (0.0, gtk4::Justification::Left),
+        TextAlign::Center => (0.5, gtk4::Justification::Center),
+    };
+    label.set_xalign(xalign);
+    label.set_justify(justify);
+}
 
+/// Set CSS on a window's caption label and background to reflect appearance config.
+/// Each window has its own provider, scoped by its widget name.
+///
+/// Uses thread-local providers to avoid resource leaks: a window's old provider is
+/// removed before creating a new one on each call.
+pub fn apply_appearance(appearance: &AppearanceConfig, window_name: &str) {
+    let css = build_css(appearance, window_name);
     let display = gtk4::gdk::Display::default().expect("no GDK display");
 
-    CSS_PROVIDER.with(|provider_cell| {
-        let mut provider_opt = provider_cell.borrow_mut();
+    CSS_PROVIDERS.with(|providers| {
+        let mut providers = providers.borrow_mut();
 
         // Remove old provider if it exists
-        if let Some(ref old_provider) = *provider_opt {
-            gtk4::style_context_remove_provider_for_display(&display, old_provider);
+        if let Some(old_provider) = providers.remove(window_name) {
+            gtk4::style_context_remove_provider_for_display(&display, &old_provider);
         }
 
         // Create and add new provider
@@ -533,21 +920,117 @@ pub fn apply_appearance(appearance: &AppearanceConfig) {
         );
 
         // Store the new provider for next call
-        *provider_opt = Some(new_provider);
+        providers.insert(window_name.to_string(), new_provider);
+    });
+}
+
+/// Remove the CSS of a window that is gone.
+fn remove_appearance(window_name: &str) {
+    CSS_PROVIDERS.with(|providers| {
+        if let Some(provider) = providers.borrow_mut().remove(window_name) {
+            if let Some(display) = gtk4::gdk::Display::default() {
+                gtk4::style_context_remove_provider_for_display(&display, &provider);
+            }
+        }
     });
 }
 
-/// Estimate the number of characters that fit in the given pixel width at the given font size.
-/// Uses an approximate average character width of 0.6 × font_size (reasonable for proportional fonts).
-fn estimate_max_chars(width_px: i32, font_size_pt: f32, char_width_fraction: f32) -> i32 {
-    if width_px <= 0 || font_size_pt <= 0.0 {
-        return 80; // fallback
-    }
-    // Average char width ≈ 0.6 × font size in points (heuristic for proportional fonts).
-    // Subtract padding (8px + 12px = 20px per side from CSS).
-    let usable_width = (width_px - 24).max(100) as f32;
-    let avg_char_width = font_size_pt * 0.6;
-    (usable_width / avg_char_width * char_width_fraction).floor() as i32
+thread_local! {
+    /// CSS provider of each overlay window, by widget name.
+    static CSS_PROVIDERS: RefCell<HashMap<String, gtk4::CssProvider>> = RefCell::new(HashMap::new());
+}
+
+/// Restyle an overlay window and re-lay its captions for the new font and width.
+fn apply_window_appearance(window: &ApplicationWindow, buffer: &mut CaptionBuffer, appearance: &AppearanceConfig) {
+    apply_appearance(appearance, &window.widget_name());
+    let label = find_caption_label(window);
+    label.set_lines(appearance.max_lines as i32);
+    apply_text_align(&label, appearance.text_align);
+    window.set_width_request(appearance.width);
+    buffer.update_config(appearance.max_lines as usize, line_width_px(appearance), appearance.effective_expire_secs());
+    buffer.set_measure(pango_measure(&label, appearance));
+}
+
+/// Width in pixels available to a caption line: the overlay width (the column width in a
+/// side dock, see `Config::layout_appearance`) less the label's horizontal padding.
+fn line_width_px(appearance: &AppearanceConfig) -> usize {
+    let width = if appearance.width > 0 { appearance.width } else { AUTO_WIDTH_PX };
+    (width - 2 * appearance.padding_x.max(0)).max(100) as usize
+}
+
+/// Nearest Pango weight to a CSS font weight.
+fn pango_weight(weight: u16) -> gtk4::pango::Weight {
+    use gtk4::pango::Weight;
+    match weight {
+        ..=149 => Weight::Thin,
+        150..=249 => Weight::Ultralight,
+        250..=349 => Weight::Light,
+        350..=449 => Weight::Normal,
+        450..=549 => Weight::Medium,
+        550..=649 => Weight::Semibold,
+        650..=749 => Weight::Bold,
+        750..=849 => Weight::Ultrabold,
+        850.. => Weight::Heavy,
+    }
+}
+
+/// A Pango layout in the caption font: the configured family (or the label's own), weight
+/// and size.
+fn caption_layout(label: &Label, appearance: &AppearanceConfig) -> gtk4::pango::Layout {
+    let layout = label.create_pango_layout(None);
+    let mut font = label.pango_context().font_description().unwrap_or_default();
+    let family = appearance.font_family.trim();
+    if !family.is_empty() {
+        font.set_family(family);
+    }
+    font.set_weight(pango_weight(appearance.font_weight));
+    font.set_size((appearance.font_size * gtk4::pango::SCALE as f32).round() as i32);
+    layout.set_font_description(Some(&font));
+    layout
+}
+
+/// Height in pixels of one line of caption text, before line spacing.
+fn line_height_px(label: &Label, appearance: &AppearanceConfig) -> i32 {
+    let layout = caption_layout(label, appearance);
+    layout.set_text("Ag");
+    layout.pixel_size().1
+}
+
+/// Exclusive zone of a docked overlay with `reserve_space`: the space it takes from its
+/// edge with all `max_lines` lines shown, so the reservation does not change as captions
+/// come and go. `line_height` is the height of one line of text in pixels. 0 reserves
+/// nothing, letting the overlay cover other windows.
+fn exclusive_zone(cfg: &Config, line_height: i32) -> i32 {
+    if !cfg.reserve_space || cfg.overlay_mode != OverlayMode::Docked {
+        return 0;
+    }
+    let appearance = cfg.layout_appearance();
+    match cfg.screen_edge {
+        ScreenEdge::Left | ScreenEdge::Right if appearance.width > 0 => appearance.width,
+        ScreenEdge::Left | ScreenEdge::Right => AUTO_WIDTH_PX,
+        ScreenEdge::Top | ScreenEdge::Bottom if appearance.height > 0 => appearance.height,
+        ScreenEdge::Top | ScreenEdge::Bottom => {
+            let spacing = if appearance.line_spacing > 0.0 { appearance.line_spacing } else { 1.0 };
+            let text = (appearance.max_lines as f32 * line_height as f32 * spacing).ceil() as i32;
+            text + 2 * appearance.padding_y.max(0)
+        }
+    }
+}
+
+/// Reserve space for the overlay at its edge, or none, as `cfg` asks.
+fn apply_exclusive_zone(window: &ApplicationWindow, cfg: &Config) {
+    let label = find_caption_label(window);
+    window.set_exclusive_zone(exclusive_zone(cfg, line_height_px(&label, &cfg.layout_appearance())));
+}
+
+/// Measure text in pixels as the caption label renders it: in the configured font family
+/// (or the label's own), weight and size.
+fn pango_measure(label: &Label, appearance: &AppearanceConfig) -> Measure {
+    let layout = caption_layout(label, appearance);
+    Box::new(move |text| {
+        layout.set_text(text);
+        layout.pixel_size().0.max(0) as usize
+    })
 }
 
 fn find_caption_label(window: &ApplicationWindow) -> Label {
@@ -570,20 +1053,31 @@ fn find_caption_label(window: &ApplicationWindow) -> Label {
         .expect("caption label not found")
 }
 
+#[allow(clippy::too_many_arguments)]
 fn handle_overlay_command(
     window: &ApplicationWindow,
     cmd: OverlayCommand,
     config: &Arc<std::sync::Mutex<Config>>,
     is_dragging: &Rc<Cell<bool>>,
     caption_buffer: &Rc<RefCell<CaptionBuffer>>,
+    web: Option<&WebServer>,
+    history: &Rc<HistoryWindow>,
+    mirrors: &Rc<Mirrors>,
 ) {
     match cmd {
-        OverlayCommand::SetVisible(v) => window.set_visible(v),
+        OverlayCommand::SetVisible(v) => {
+            window.set_visible(v);
+            if let Some(web) = web {
+                web.publish_visible(v);
+            }
+        }
         OverlayCommand::SetMode(mode) => {
             // Reconfigure the existing layer-shell window for the new mode.
             // gtk4-layer-shell allows changing anchors/keyboard mode on a realized window.
             let mut cfg = config.lock().unwrap();
             cfg.overlay_mode = mode.clone();
+            // Docked to a side, captions are laid out in a column; elsewhere they span `width`.
+            apply_window_appearance(window, &mut caption_buffer.borrow_mut(), &cfg.layout_appearance());
             match mode {
                 OverlayMode::Docked => {
                     // Clear any floating anchors, set docked anchors.
@@ -592,6 +1086,7 @@ fn handle_overlay_command(
                         window.set_anchor(edge, false);
                     }
                     configure_docked(window, &cfg.screen_edge, &cfg.dock_position);
+                    apply_exclusive_zone(window, &cfg);
                     // Docked mode is always click-through.
                     input_region::set_empty_input_region(window);
                 }
@@ -602,9 +1097,9 @@ fn handle_overlay_command(
                     }
                     window.set_anchor(Edge::Top, true);
                     window.set_anchor(Edge::Left, true);
-                    // Restore position from config.
-                    window.set_margin(Edge::Left, cfg.position.x);
-                    window.set_margin(Edge::Top, cfg.position.y);
+                    window.set_exclusive_zone(0);
+                    // Restore the position saved for this monitor.
+                    restore_floating_position(window);
                     window.set_keyboard_mode(if cfg.locked {
                         KeyboardMode::None
                     } else {
@@ -614,7 +1109,7 @@ fn handle_overlay_command(
                         input_region::set_empty_input_region(window);
                     } else {
                         input_region::clear_input_region(window);
-                        add_drag_handler(window, is_dragging);
+                        add_drag_handler(window, is_dragging, history);
                     }
                 }
             }
@@ -626,24 +1121,53 @@ fn handle_overlay_command(
             } else {
                 input_region::clear_input_region(window);
                 window.set_keyboard_mode(KeyboardMode::OnDemand);
-                add_drag_handler(window, is_dragging);
+                add_drag_handler(window, is_dragging, history);
             }
         }
         OverlayCommand::UpdateAppearance(appearance) => {
-            apply_appearance(&appearance);
-            let label = find_caption_label(window);
-            let max_chars = estimate_max_chars(appearance.width, appearance.font_size, appearance.effective_char_width_fraction());
-            label.set_max_width_chars(max_chars);
-            label.set_lines(appearance.max_lines as i32);
-            window.set_width_request(appearance.width);
-            // Update buffer config for hot-reload
-            let mut buf = caption_buffer.borrow_mut();
-            buf.update_config(appearance.max_lines as usize, max_chars as usize, appearance.effective_expire_secs());
+            // Update window style and buffer config for hot-reload
+            let mut cfg = config.lock().unwrap();
+            cfg.appearance = appearance.clone();
+            apply_window_appearance(window, &mut caption_buffer.borrow_mut(), &cfg.layout_appearance());
+            mirrors.update_appearance(&appearance);
+            // The reserved space follows line count and font size.
+            apply_exclusive_zone(window, &cfg);
+            if let Some(web) = web {
+                web.publish_appearance(&appearance);
+            }
         }
         OverlayCommand::SetCaption(text) => {
             let label = find_caption_label(window);
             label.set_text(&text);
         }
+        OverlayCommand::ShowHistory => history.present(),
+        OverlayCommand::SetMonitor(monitor) => {
+            let mut cfg = config.lock().unwrap();
+            cfg.monitor = monitor;
+            if monitors::bind(window, cfg.monitor.as_deref()) && cfg.overlay_mode == OverlayMode::Floating {
+                restore_floating_position(window);
+            }
+        }
+        OverlayCommand::SetReserveSpace(reserve) => {
+            let mut cfg = config.lock().unwrap();
+            cfg.reserve_space = reserve;
+            apply_exclusive_zone(window, &cfg);
+            mirrors.set_reserve_space(reserve);
+        }
+        OverlayCommand::SetLayer(layer) => {
+            config.lock().unwrap().layer = layer;
+            window.set_layer(shell_layer(layer));
+            mirrors.set_layer(layer);
+        }
+        OverlayCommand::SetAutoHideFullscreen(enabled) => {
+            // Applied by the fullscreen timer on its next tick.
+            config.lock().unwrap().auto_hide_fullscreen = enabled;
+        }
+        OverlayCommand::SetMirrors(list) => {
+            let mut cfg = config.lock().unwrap();
+            cfg.mirrors = list;
+            mirrors.set(&cfg);
+        }
         OverlayCommand::Quit => {
             // Quit the GTK4 application cleanly so all cleanup (Drop impls) runs.
             if let Some(app) = window.application() {
@@ -670,7 +1194,9 @@ fn remove_drag_handlers(window: &ApplicationWindow) {
     }
 }
 
-fn add_drag_handler(window: &ApplicationWindow, is_dragging: &Rc<Cell<bool>>) {
+/// Make the unlocked floating overlay draggable. A click without movement opens the
+/// caption history.
+fn add_drag_handler(window: &ApplicationWindow, is_dragging: &Rc<Cell<bool>>, history: &Rc<HistoryWindow>) {
     // Remove any existing drag handlers first to prevent accumulation.
     remove_drag_handlers(window);
 
@@ -743,14 +1269,20 @@ fn add_drag_handler(window: &ApplicationWindow, is_dragging: &Rc<Cell<bool>>) {
     // Clear dragging flag and save position on drag end.
     let win_for_release = window.clone();
     let dragging_end = Rc::clone(is_dragging);
+    let history = Rc::clone(history);
     gesture.connect_drag_end(move |_, _offset_x, _offset_y| {
         dragging_end.set(false);
         let x = win_for_release.margin(Edge::Left);
         let y = win_for_release.margin(Edge::Top);
+        if x == start_x.load(Ordering::Relaxed) && y == start_y.load(Ordering::Relaxed) {
+            // Not moved: a click.
+            history.present();
+            return;
+        }
         eprintln!("info: overlay dragged to ({x}, {y})");
         let mut cfg = crate::config::Config::load();
-        cfg.position.x = x;
-        cfg.position.y = y;
+        let monitor = monitors::current_connector(&win_for_release);
+        cfg.set_position_on(monitor.as_deref(), crate::config::OverlayPosition { x, y });
         if let Err(e) = cfg.save() {
             eprintln!("warn: failed to save position: {e}");
         }
@@ -775,9 +1307,9 @@ mod tests {
             width: 800,
             height: 0,
             expire_secs: 8,
-            char_width_fraction: 0.95,
+            ..AppearanceConfig::default()
         };
-        let css = build_css(&appearance);
+        let css = build_css(&appearance, OVERLAY_NAME);
 
         // Verify CSS contains the background color
         assert!(css.contains("rgba(255,0,0,0.5)"), "CSS should contain background_color");
@@ -793,17 +1325,94 @@ mod tests {
     #[test]
     fn build_css_with_default_appearance() {
         let appearance = AppearanceConfig::default();
-        let css = build_css(&appearance);
+        let css = build_css(&appearance, OVERLAY_NAME);
 
         // Verify CSS contains the default colors and font size
         assert!(css.contains("rgba(0,0,0,0.7)"), "CSS should contain default background_color");
         assert!(css.contains("#ffffff"), "CSS should contain default text_color");
         assert!(css.contains("16"), "CSS should contain default font_size");
+        assert!(css.contains("border-radius: 12px;"));
+        assert!(css.contains("padding: 8px 12px;"));
+        // Unset options fall back to the theme.
+        assert!(!css.contains("font-family"));
+        assert!(!css.contains("line-height"));
+        assert!(!css.contains("text-shadow"));
+    }
+
+    #[test]
+    fn build_css_contains_font_and_layout_options() {
+        let appearance = AppearanceConfig {
+            font_family: "Atkinson \"Hyperlegible\"".to_string(),
+            font_weight: 1000,
+            line_spacing: 1.4,
+            border_radius: 0,
+            padding_x: 20,
+            padding_y: 4,
+            ..AppearanceConfig::default()
+        };
+        let css = build_css(&appearance, OVERLAY_NAME);
+        assert!(css.contains(r#"font-family: "Atkinson \"Hyperlegible\"";"#), "{css}");
+        assert!(css.contains("font-weight: 900;"), "weight is clamped to the CSS range");
+        assert!(css.contains("line-height: 1.4;"));
+        assert!(css.contains("border-radius: 0px;"));
+        assert!(css.contains("padding: 4px 20px;"));
+    }
+
+    #[test]
+    fn build_css_scopes_rules_to_one_window() {
+        let css = build_css(&AppearanceConfig::default(), "mirror-1");
+        assert!(css.contains("window#mirror-1 {"));
+        assert!(css.contains("window#mirror-1 #caption-label {"));
+        assert!(!css.contains("window#overlay"));
+    }
+
+    #[test]
+    fn build_css_draws_outline_and_extra_shadow() {
+        let appearance = AppearanceConfig {
+            outline_width: 1.5,
+            outline_color: "#101010".to_string(),
+            text_shadow: "2px 2px 4px black".to_string(),
+            ..AppearanceConfig::default()
+        };
+        let css = build_css(&appearance, OVERLAY_NAME);
+        let shadow = css.lines().find(|l| l.trim_start().starts_with("text-shadow:")).unwrap();
+        assert_eq!(shadow.matches("#101010").count(), 8, "{shadow}");
+        assert!(shadow.contains("-1.5px -1.5px #101010"));
+        assert!(shadow.trim_end().ends_with(", 2px 2px 4px black;"));
+    }
+
+    #[test]
+    fn line_width_leaves_room_for_padding() {
+        let appearance = AppearanceConfig { width: 800, padding_x: 30, ..AppearanceConfig::default() };
+        assert_eq!(line_width_px(&appearance), 740);
+        let auto = AppearanceConfig { width: 0, ..AppearanceConfig::default() };
+        assert_eq!(line_width_px(&auto), (AUTO_WIDTH_PX - 24) as usize);
+    }
+
+    #[test]
+    fn exclusive_zone_covers_all_lines_when_reserving() {
+        let mut cfg = Config::default();
+        cfg.reserve_space = true;
+        cfg.appearance = AppearanceConfig { max_lines: 3, padding_y: 8, ..AppearanceConfig::default() };
+        assert_eq!(exclusive_zone(&cfg, 20), 3 * 20 + 16);
+
+        cfg.appearance.line_spacing = 1.5;
+        assert_eq!(exclusive_zone(&cfg, 20), 90 + 16);
+        cfg.appearance.height = 150;
+        assert_eq!(exclusive_zone(&cfg, 20), 150);
+        cfg.screen_edge = ScreenEdge::Left;
+        assert_eq!(exclusive_zone(&cfg, 20), cfg.appearance.side_width);
+
+        cfg.overlay_mode = OverlayMode::Floating;
+        assert_eq!(exclusive_zone(&cfg, 20), 0);
+        cfg.overlay_mode = OverlayMode::Docked;
+        cfg.reserve_space = false;
+        assert_eq!(exclusive_zone(&cfg, 20), 0);
     }
 
     // CaptionBuffer line-fill tests
 
-    /// AC1.1: Text fills line 1 left-to-right, word by word, up to max_chars_per_line.
+    /// AC1.1: Text fills line 1 left-to-right, word by word, up to max_line_width.
     #[test]
     fn ac1_1_fill_single_line() {
         let mut buf = CaptionBuffer::new(3, 20, 8);
@@ -1097,10 +1706,14 @@ mod tests {
         buf.lines.push(CaptionLine {
             text: "old_content".to_string(),
             last_active: now - std::time::Duration::from_secs(2),
+            speaker: None,
+            labelled: false,
         });
         buf.lines.push(CaptionLine {
             text: "recent_content".to_string(),
             last_active: Instant::now(),
+            speaker: None,
+            labelled: false,
         });
 
         assert_eq!(buf.lines.len(), 2, "Should have 2 lines");
@@ -1114,63 +1727,195 @@ mod tests {
         assert!(!buf.expire(), "Active line should not expire");
     }
 
-    /// AC4.1: estimate_max_chars applies 0.85× conservative multiplier for visual padding.
-    #[test]
-    fn ac4_1_conservative_multiplier() {
-        let width_px = 800;
-        let font_size_pt = 24.0;
-
-        // Old formula: (usable_width / avg_char_width)
-        // usable_width = (800 - 24).max(100) = 776
-        // avg_char_width = 24.0 * 0.6 = 14.4
-        // old_result = 776 / 14.4 = 53.88... → floor = 53
-        // With fraction=0.95: 53 * 0.95 = 51.11 → floor = 51
-        let result = estimate_max_chars(width_px, font_size_pt, 0.95);
-        let expected_full = ((776.0_f32 / 14.4).floor()) as i32; // 53
-        let expected_95 = ((776.0_f32 / 14.4 * 0.95).floor()) as i32; // 51
-
-        assert_eq!(expected_full, 53, "Sanity check: full formula should give 53");
-        assert_eq!(expected_95, 51, "Sanity check: 0.95 formula should give 51");
-        assert_eq!(result, 51, "Result with 0.95 fraction");
-        assert!(
-            result < expected_full,
-            "Fraction should make result smaller than full formula"
-        );
-    }
-
     /// AC3.2: CaptionBuffer configuration can be updated via update_config for hot-reload.
-    /// Verifies that expire_secs and max_chars_per_line can be changed after creation.
+    /// Verifies that expire_secs and max_line_width can be changed after creation.
     #[test]
     fn ac3_2_update_config_hot_reload() {
         let mut buf = CaptionBuffer::new(3, 20, 8);
 
-        // Add initial text with the original max_chars_per_line (20).
+        // Add initial text with the original max_line_width (20).
         buf.push(" Hello".to_string());
         buf.push(" world".to_string());
 
         // Verify initial values
-        assert_eq!(buf.max_chars_per_line, 20, "Initial max_chars_per_line should be 20");
+        assert_eq!(buf.max_line_width, 20, "Initial max_line_width should be 20");
         assert_eq!(buf.expire_secs, 8, "Initial expire_secs should be 8");
 
-        // Update config to smaller max_chars_per_line, different max_lines, and expire_secs
+        // Update config to smaller max_line_width, different max_lines, and expire_secs
         buf.update_config(2, 10, 5);
 
         // Verify updated values
         assert_eq!(buf.max_lines, 2, "max_lines should be updated to 2");
-        assert_eq!(buf.max_chars_per_line, 10, "max_chars_per_line should be updated to 10");
+        assert_eq!(buf.max_line_width, 10, "max_line_width should be updated to 10");
         assert_eq!(buf.expire_secs, 5, "expire_secs should be updated to 5");
 
         // Verify that existing content is preserved
         let display = buf.display_text();
         assert_eq!(display, "Hello world", "Existing content should be preserved");
 
-        // Add more text with the new max_chars_per_line to verify it's applied
+        // Add more text with the new max_line_width to verify it's applied
         buf.push(" this".to_string());
 
-        // With max_chars_per_line=10, "Hello world this" won't fit on one line
+        // With max_line_width=10, "Hello world this" won't fit on one line
         // "Hello world" is 11 chars, so with max_chars=10, "world" would go to line 2
         let display = buf.display_text();
         let lines: Vec<&str> = display.split('\n').collect();
-        assert!(lines.len() >= 2, "New text should respect updated max_chars_per_line");
+        assert!(lines.len() >= 2, "New text should respect updated max_line_width");
+    }
+
+    fn tag(label: &str, color: Option<&str>) -> SourceTag {
+        SourceTag { label: label.to_string(), color: color.map(str::to_string) }
+    }
+
+    #[test]
+    fn speaker_change_starts_labelled_line() {
+        let mut buf = CaptionBuffer::new(3, 30, 8);
+        let me = tag("Me", None);
+        let firefox = tag("Firefox", None);
+        buf.push_from(Some(&me), " hello there".to_string());
+        buf.push_from(Some(&me), " friend".to_string());
+        buf.push_from(Some(&firefox), " welcome back".to_string());
+        assert_eq!(buf.display_text(), "Me: hello there friend\nFirefox: welcome back");
+    }
+
+    #[test]
+    fn speaker_change_never_continues_previous_word() {
+        let mut buf = CaptionBuffer::new(3, 30, 8);
+        buf.push_from(Some(&tag("Me", None)), " hel".to_string());
+        buf.push_from(Some(&tag("Firefox", None)), "lo".to_string());
+        assert_eq!(buf.display_text(), "Me: hel\nFirefox: lo");
+    }
+
+    #[test]
+    fn untagged_push_has_no_label() {
+        let mut buf = CaptionBuffer::new(3, 30, 8);
+        buf.push_from(None, " hello world".to_string());
+        assert_eq!(buf.display_text(), "hello world");
+        assert_eq!(buf.display_markup(), "hello world");
+    }
+
+    #[test]
+    fn display_markup_colours_label_and_escapes_text() {
+        let mut buf = CaptionBuffer::new(3, 40, 8);
+        buf.push_from(Some(&tag("Me", Some("#8ab4f8"))), " a <b> & c".to_string());
+        assert_eq!(
+            buf.display_markup(),
+            "<span foreground=\"#8ab4f8\"><b>Me:</b></span> a &lt;b&gt; &amp; c"
+        );
+    }
+
+    #[test]
+    fn wrapped_speaker_line_is_not_relabelled() {
+        let mut buf = CaptionBuffer::new(3, 12, 8);
+        buf.push_from(Some(&tag("Me", None)), " one two three four".to_string());
+        assert_eq!(buf.display_markup(), "<b>Me:</b> one two\nthree four");
+    }
+
+    #[test]
+    fn partial_hypothesis_is_replaced_by_final_text() {
+        let mut buf = CaptionBuffer::new(3, 40, 8);
+        buf.push_caption(Caption::test_final(0, " good", None));
+        buf.push_caption(Caption { is_final: false, ..Caption::test_final(0, " mor", None) });
+        assert_eq!(buf.display_text(), "good mor");
+        assert_eq!(buf.display_markup(), "good <i>mor</i>");
+        buf.push_caption(Caption::test_final(0, " morning", None));
+        assert_eq!(buf.display_text(), "good morning");
+    }
+
+    #[test]
+    fn partials_are_kept_per_source_after_each_speakers_lines() {
+        let mut buf = CaptionBuffer::new(3, 40, 8);
+        let me = Some("Me");
+        let firefox = Some("Firefox");
+        buf.push_caption(Caption::test_final(0, " hello", me));
+        buf.push_caption(Caption { is_final: false, ..Caption::test_final(0, " how", me) });
+        buf.push_caption(Caption { is_final: false, ..Caption::test_final(0, " we", firefox) });
+        assert_eq!(buf.display_text(), "Me: hello how\nFirefox: we");
+        // Another source's final text leaves the first speaker's hypothesis alone.
+        buf.push_caption(Caption::test_final(0, " welcome", firefox));
+        assert_eq!(buf.display_text(), "Me: hello how\nFirefox: welcome");
+        assert_eq!(buf.display_markup(), "<b>Me:</b> hello <i>how</i>\n<b>Firefox:</b> welcome");
+    }
+
+    #[test]
+    fn final_text_is_not_treated_as_overlap() {
+        // Being final says nothing about overlap; the engine declares that separately.
+        // From an engine that may repeat itself, the re-emitted tail is dropped...
+        let mut buf = CaptionBuffer::new(3, 60, 8);
+        buf.push_caption(Caption::test_final(0, " I know that", None));
+        buf.push_caption(Caption::test_final(0, " know that is true", None));
+        assert_eq!(buf.display_text(), "I know that is true");
+        // ...while from one that never does, a repeated word was really said twice.
+        let mut buf = CaptionBuffer::new(3, 60, 8);
+        buf.push_caption(Caption { may_overlap: false, ..Caption::test_final(0, " I know that", None) });
+        buf.push_caption(Caption { may_overlap: false, ..Caption::test_final(0, " know that is true", None) });
+        assert_eq!(buf.display_text(), "I know that know that is true");
+    }
+
+    #[test]
+    fn lines_fill_by_measured_width() {
+        // Narrow and wide glyphs, as a proportional font measures them.
+        let measure = |s: &str| s.chars().map(|c| if c == 'i' || c == ' ' { 1 } else { 3 }).sum();
+        let mut buf = CaptionBuffer::with_measure(3, 12, 8, Box::new(measure));
+        buf.push(" mmmm".to_string());
+        buf.push(" i".to_string());
+        assert_eq!(buf.display_text(), "mmmm\ni", "six characters, but 14 wide");
+        buf.push(" iiiiiiii".to_string());
+        assert_eq!(buf.display_text(), "mmmm\ni iiiiiiii", "ten characters, 10 wide");
+    }
+
+    // Multibyte text
+
+    #[test]
+    fn accented_words_are_measured_in_characters() {
+        // "café naïve" is 10 characters but 12 bytes.
+        let mut buf = CaptionBuffer::new(3, 10, 8);
+        buf.push(" café".to_string());
+        buf.push(" naïve".to_string());
+        assert_eq!(buf.display_text(), "café naïve");
+        // Continuations are measured the same way.
+        buf.push(" déjà".to_string());
+        buf.push("-vu".to_string());
+        assert_eq!(buf.display_text(), "café naïve\ndéjà-vu");
+    }
+
+    #[test]
+    fn wide_characters_take_two_columns_and_long_runs_wrap() {
+        // CJK has no spaces to wrap at; each character is two columns wide.
+        let mut buf = CaptionBuffer::new(3, 10, 8);
+        buf.push("你好世界你好世界".to_string());
+        assert_eq!(buf.display_text(), "你好世界你\n好世界");
+        buf.push("再见".to_string());
+        assert_eq!(buf.display_text(), "你好世界你\n好世界再见");
+        // Emoji are one grapheme, never split, and two columns wide.
+        let mut buf = CaptionBuffer::new(3, 6, 8);
+        buf.push(" 👍🏽 ok 👨‍👩‍👧".to_string());
+        assert_eq!(buf.display_text(), "👍🏽 ok\n👨‍👩‍👧");
+    }
+
+    #[test]
+    fn overlap_is_removed_from_multibyte_text() {
+        let mut buf = CaptionBuffer::new(3, 60, 8);
+        buf.push(" Grüße aus München".to_string());
+        buf.push(" aus München und Köln".to_string());
+        assert_eq!(buf.display_text(), "Grüße aus München und Köln");
+        // Lowercasing "İ" changes its length in bytes.
+        let mut buf = CaptionBuffer::new(3, 60, 8);
+        buf.push(" İstanbul".to_string());
+        buf.push(" İstanbul 🎉 güzel".to_string());
+        assert_eq!(buf.display_text(), "İstanbul 🎉 güzel");
+    }
+
+    #[test]
+    fn tail_tracking_keeps_long_multibyte_text_intact() {
+        let mut buf = CaptionBuffer::new(3, 30, 8);
+        for _ in 0..30 {
+            buf.push(" ÿö".to_string());
+        }
+        buf.push(" 日本語".to_string());
+        // Lines are joined without separators in the tail.
+        assert!(buf.last_tail.ends_with("ÿö日本語"), "{}", buf.last_tail);
+        assert_eq!(buf.last_tail.chars().count(), TAIL_GRAPHEMES);
+        assert_eq!(tail_of("ab"), "ab");
     }
 }
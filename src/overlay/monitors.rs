@@ -0,0 +1,154 @@
+//! Output selection: binds the overlay's layer surface to the configured monitor, and moves
+//! it when monitors are unplugged or replugged.
+
+use crate::config::Config;
+use gtk4::prelude::*;
+use gtk4::{gdk, glib, ApplicationWindow};
+use gtk4_layer_shell::LayerShell;
+use std::rc::Rc;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+
+/// A connected monitor, as listed in the tray.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonitorInfo {
+    /// Connector name, e.g. "DP-1". This is what the config stores.
+    pub connector: String,
+    /// Manufacturer and model, e.g. "Dell Inc. DELL U2720Q" (empty if unknown).
+    pub description: String,
+}
+
+impl MonitorInfo {
+    /// Menu label, e.g. "Dell Inc. DELL U2720Q (DP-1)".
+    pub fn label(&self) -> String {
+        if self.description.is_empty() || self.description == self.connector {
+            self.connector.clone()
+        } else {
+            format!("{} ({})", self.description, self.connector)
+        }
+    }
+}
+
+fn monitors(display: &gdk::Display) -> Vec<gdk::Monitor> {
+    let model = display.monitors();
+    (0..model.n_items())
+        .filter_map(|i| model.item(i).and_downcast::<gdk::Monitor>())
+        .collect()
+}
+
+/// Connected monitors that have a connector name.
+fn list(display: &gdk::Display) -> Vec<MonitorInfo> {
+    monitors(display)
+        .iter()
+        .filter_map(|monitor| {
+            let connector = monitor.connector()?.to_string();
+            let description = monitor.description().map(|d| d.to_string()).unwrap_or_else(|| {
+                [monitor.manufacturer(), monitor.model()]
+                    .into_iter()
+                    .flatten()
+                    .map(|s| s.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            });
+            Some(MonitorInfo { connector, description })
+        })
+        .collect()
+}
+
+/// The connected monitor with the given connector name.
+pub fn find(display: &gdk::Display, connector: &str) -> Option<gdk::Monitor> {
+    monitors(display)
+        .into_iter()
+        .find(|m| m.connector().as_deref() == Some(connector))
+}
+
+/// Bind the overlay to the monitor with the given connector. With no monitor configured, or
+/// the configured one disconnected, the compositor chooses. Returns whether the overlay's
+/// output changed.
+pub fn bind(window: &ApplicationWindow, connector: Option<&str>) -> bool {
+    let target = connector.and_then(|c| find(&WidgetExt::display(window), c));
+    if window.monitor() == target {
+        return false;
+    }
+    match (&target, connector) {
+        (Some(_), Some(c)) => eprintln!("info: overlay moved to monitor {c}"),
+        (None, Some(c)) => eprintln!("info: monitor {c} not connected; compositor places the overlay"),
+        _ => {}
+    }
+    window.set_monitor(target.as_ref());
+    true
+}
+
+/// Connector of the monitor the overlay is on: the one it is bound to, or else the one the
+/// compositor put it on (once mapped).
+pub fn current_connector(window: &ApplicationWindow) -> Option<String> {
+    let monitor = window.monitor().or_else(|| {
+        let surface = window.surface()?;
+        surface.display().monitor_at_surface(&surface)
+    })?;
+    monitor.connector().map(|c| c.to_string())
+}
+
+/// Keep the overlay on the configured monitor as monitors come and go, and send the
+/// connected monitors to the tray whenever they change. `on_change` runs after every
+/// change, told whether the overlay changed outputs.
+pub fn watch(
+    window: &ApplicationWindow,
+    config: &Arc<Mutex<Config>>,
+    monitor_tx: Sender<Vec<MonitorInfo>>,
+    on_change: impl Fn(bool) + 'static,
+) {
+    let display = WidgetExt::display(window);
+    let window_for_refresh = window.clone();
+    let config_for_refresh = Arc::clone(config);
+    let display_for_refresh = display.clone();
+    let refresh: Rc<dyn Fn()> = Rc::new(move || {
+        let _ = monitor_tx.send(list(&display_for_refresh));
+        let selected = config_for_refresh.lock().unwrap().monitor.clone();
+        on_change(bind(&window_for_refresh, selected.as_deref()));
+    });
+
+    // Wayland fills in a new monitor's connector name after announcing the monitor.
+    let watch_connector = |monitor: &gdk::Monitor, refresh: &Rc<dyn Fn()>| {
+        let refresh = Rc::clone(refresh);
+        monitor.connect_connector_notify(move |_| refresh());
+    };
+    for monitor in monitors(&display) {
+        watch_connector(&monitor, &refresh);
+    }
+    let refresh_for_changes = Rc::clone(&refresh);
+    display.monitors().connect_items_changed(move |model, position, _removed, added| {
+        for i in position..position + added {
+            if let Some(monitor) = model.item(i).and_downcast::<gdk::Monitor>() {
+                watch_connector(&monitor, &refresh_for_changes);
+            }
+        }
+        refresh_for_changes();
+    });
+
+    // Map the overlay again, on the configured monitor if it is back or wherever the
+    // compositor chooses.
+    let refresh_for_close = Rc::clone(&refresh);
+    reopen_when_closed(window, move |window, was_visible| {
+        refresh_for_close();
+        if was_visible {
+            window.set_visible(true);
+        }
+    });
+
+    refresh();
+}
+
+/// The compositor closes a layer surface whose output was removed. Keep the window instead:
+/// it is unmapped, and `reopen` is called (with whether it was visible) once that is done.
+pub fn reopen_when_closed(window: &ApplicationWindow, reopen: impl Fn(&ApplicationWindow, bool) + 'static) {
+    let reopen = Rc::new(reopen);
+    window.connect_close_request(move |window| {
+        let was_visible = window.is_visible();
+        window.set_visible(false);
+        let window = window.clone();
+        let reopen = Rc::clone(&reopen);
+        glib::idle_add_local_once(move || reopen(&window, was_visible));
+        glib::Propagation::Stop
+    });
+}
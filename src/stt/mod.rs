@@ -2,9 +2,11 @@
 
 pub mod nemotron;
 
+use crate::audio::resampler::OUTPUT_SAMPLE_RATE;
 use anyhow::Result;
 use ort::ep::ExecutionProvider as _;
 use ort::ep::CUDA;
+use serde::Serialize;
 use std::sync::mpsc;
 use std::thread;
 
@@ -19,32 +21,163 @@ pub trait SttEngine: Send + 'static {
 
     /// Process one 160ms chunk of 16kHz mono PCM.
     ///
-    /// Returns `Ok(Some(text))` when a complete utterance has been recognized,
-    /// `Ok(None)` when more audio is needed, or an error if inference failed
-    /// (caller should log and skip the chunk).
-    fn process_chunk(&mut self, pcm: &[f32]) -> Result<Option<String>>;
+    /// Returns `Ok(Some(result))` when text has been recognized, either final or as a partial
+    /// hypothesis that a later result will replace, `Ok(None)` when more audio is needed, or
+    /// an error if inference failed (caller should log and skip the chunk).
+    fn process_chunk(&mut self, chunk: &AudioChunk) -> Result<Option<Recognition>>;
+
+    /// Speech has paused: recognize any audio still buffered and start a fresh utterance.
+    /// Engines without internal buffering need not override this.
+    fn flush(&mut self) -> Result<Option<Recognition>> {
+        Ok(None)
+    }
+
+    /// Whether a result may begin by repeating the end of the previous one, as chunked
+    /// streaming decoders do at chunk boundaries. The overlay trims such repeats from its
+    /// lines; engines that never repeat themselves return false, so a word the speaker
+    /// really said twice is kept.
+    fn may_repeat_previous_text(&self) -> bool {
+        true
+    }
+}
+
+/// Message from the audio bridge to an inference thread.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EngineInput {
+    Audio(AudioChunk),
+    /// A long pause began (see `audio::vad`); flush the engine.
+    Flush,
+}
+
+/// A 160ms chunk of 16kHz mono PCM, stamped with its position on the session timeline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioChunk {
+    /// Offset of the first sample, in session samples (`SAMPLES_PER_SEC`) since the session
+    /// (or file) started.
+    pub start_sample: u64,
+    pub pcm: Vec<f32>,
+}
+
+/// Session timeline samples per second.
+pub const SAMPLES_PER_SEC: u64 = OUTPUT_SAMPLE_RATE as u64;
+
+/// Current position on a session timeline that started at `epoch`, in session samples.
+pub fn session_position(epoch: std::time::Instant) -> u64 {
+    (epoch.elapsed().as_secs_f64() * SAMPLES_PER_SEC as f64) as u64
+}
+
+/// Convert an offset in session samples (`SAMPLES_PER_SEC`) to milliseconds.
+pub fn samples_to_ms(samples: u64) -> u64 {
+    samples * 1000 / SAMPLES_PER_SEC
+}
+
+/// One result from an engine: recognized text and the span of audio it covers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recognition {
+    /// Recognized text. A leading space marks a word boundary; without one the text
+    /// continues the previous word.
+    pub text: String,
+    /// Offsets in session samples (`SAMPLES_PER_SEC`); `end_sample` is exclusive.
+    pub start_sample: u64,
+    pub end_sample: u64,
+    /// Final text is never revised. A partial hypothesis is superseded by the next result,
+    /// which covers the same audio.
+    pub is_final: bool,
+    /// Per-token confidence, when the engine reports it.
+    pub tokens: Option<Vec<TokenConfidence>>,
+}
+
+/// A decoded token and the engine's confidence in it (0.0-1.0).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TokenConfidence {
+    pub text: String,
+    pub confidence: f32,
+}
+
+/// Builds a fresh engine instance; used to start one engine per source in per-source mode.
+pub type EngineFactory = Box<dyn Fn() -> Result<Box<dyn SttEngine>> + Send>;
+
+/// Identity of the audio source a caption was heard on, as shown in the overlay.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceTag {
+    /// Speaker label, e.g. "Me" or "Firefox".
+    pub label: String,
+    /// Optional Pango colour for the label, e.g. "#8ab4f8".
+    pub color: Option<String>,
+}
+
+/// A recognized caption fragment, tagged with its source when sources are captioned separately.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Caption {
+    /// Source the fragment was heard on; None when all sources are mixed into one stream.
+    pub source: Option<SourceTag>,
+    pub text: String,
+    /// Span of audio the fragment was recognized from, in session samples (`SAMPLES_PER_SEC`).
+    pub start_sample: u64,
+    pub end_sample: u64,
+    /// False for a partial hypothesis, which the next caption from this source replaces.
+    pub is_final: bool,
+    /// The text may start by repeating the end of this source's previous caption
+    /// (see `SttEngine::may_repeat_previous_text`).
+    pub may_overlap: bool,
+    /// Per-token confidence, when the engine reports it.
+    pub tokens: Option<Vec<TokenConfidence>>,
+}
+
+#[cfg(test)]
+impl Caption {
+    /// Final caption of `text` heard on the source labelled `source`, covering half a second
+    /// from `start_sample`.
+    pub fn test_final(start_sample: u64, text: &str, source: Option<&str>) -> Caption {
+        Caption {
+            source: source.map(|label| SourceTag { label: label.to_string(), color: None }),
+            text: text.to_string(),
+            start_sample,
+            end_sample: start_sample + SAMPLES_PER_SEC / 2,
+            is_final: true,
+            may_overlap: true,
+            tokens: None,
+        }
+    }
 }
 
 /// Spawn the inference thread.
 ///
 /// Parameters:
 /// - `engine`: boxed SttEngine (Nemotron via parakeet-rs)
-/// - `audio_rx`: receives 160ms chunks from the audio processing thread
-/// - `caption_tx`: sends recognized text to the GTK4 main thread
+/// - `audio_rx`: receives 160ms chunks (and flushes) from the audio processing thread
+/// - `caption_tx`: sends recognized captions to the GTK4 main thread
+/// - `source`: tag attached to every caption (None for the mixed stream)
 ///
 /// Returns the thread JoinHandle for clean shutdown.
 pub fn spawn_inference_thread(
     mut engine: Box<dyn SttEngine>,
-    audio_rx: mpsc::Receiver<Vec<f32>>,
-    caption_tx: mpsc::SyncSender<String>,
+    audio_rx: mpsc::Receiver<EngineInput>,
+    caption_tx: mpsc::SyncSender<Caption>,
+    source: Option<SourceTag>,
 ) -> thread::JoinHandle<()> {
     thread::Builder::new()
         .name("stt-inference".to_string())
         .spawn(move || {
-            for chunk in audio_rx.iter() {
-                match engine.process_chunk(&chunk) {
-                    Ok(Some(text)) if !text.trim().is_empty() => {
-                        if caption_tx.send(text).is_err() {
+            let may_overlap = engine.may_repeat_previous_text();
+            for input in audio_rx.iter() {
+                let result = match input {
+                    EngineInput::Audio(chunk) => engine.process_chunk(&chunk),
+                    EngineInput::Flush => engine.flush(),
+                };
+                match result {
+                    // An empty partial still matters: it withdraws the previous hypothesis.
+                    Ok(Some(result)) if !result.is_final || !result.text.trim().is_empty() => {
+                        let caption = Caption {
+                            source: source.clone(),
+                            text: result.text,
+                            start_sample: result.start_sample,
+                            end_sample: result.end_sample,
+                            is_final: result.is_final,
+                            may_overlap,
+                            tokens: result.tokens,
+                        };
+                        if caption_tx.send(caption).is_err() {
                             break; // receiver dropped — shutdown
                         }
                     }
@@ -58,15 +191,15 @@ pub fn spawn_inference_thread(
         .expect("spawning inference thread")
 }
 
-/// Restart the inference thread with a new engine.
+/// Restart the mixed-stream inference thread with a new engine.
 /// Drops the old chunk_rx (causing the old thread to exit when its sender is replaced).
 /// Returns new chunk_tx for the audio bridge thread.
 pub fn restart_inference_thread(
     engine: Box<dyn SttEngine>,
-    caption_tx: mpsc::SyncSender<String>,
-) -> (mpsc::SyncSender<Vec<f32>>, thread::JoinHandle<()>) {
-    let (chunk_tx, chunk_rx) = mpsc::sync_channel::<Vec<f32>>(32);
-    let handle = spawn_inference_thread(engine, chunk_rx, caption_tx);
+    caption_tx: mpsc::SyncSender<Caption>,
+) -> (mpsc::SyncSender<EngineInput>, thread::JoinHandle<()>) {
+    let (chunk_tx, chunk_rx) = mpsc::sync_channel::<EngineInput>(32);
+    let handle = spawn_inference_thread(engine, chunk_rx, caption_tx, None);
     (chunk_tx, handle)
 }
 
@@ -144,64 +277,140 @@ mod tests {
     struct MockEngine {
         responses: Vec<Option<String>>,
         call_index: usize,
+        /// Report results as partial hypotheses instead of final text.
+        partial: bool,
+        tokens: Option<Vec<TokenConfidence>>,
+    }
+
+    impl MockEngine {
+        fn new(responses: Vec<Option<&str>>) -> Self {
+            MockEngine {
+                responses: responses.into_iter().map(|r| r.map(str::to_string)).collect(),
+                call_index: 0,
+                partial: false,
+                tokens: None,
+            }
+        }
     }
 
     impl SttEngine for MockEngine {
         fn sample_rate(&self) -> u32 { 16_000 }
-        fn process_chunk(&mut self, _pcm: &[f32]) -> Result<Option<String>> {
+        fn flush(&mut self) -> Result<Option<Recognition>> {
+            Ok(Some(Recognition {
+                text: " flushed".to_string(),
+                start_sample: 0,
+                end_sample: 0,
+                is_final: true,
+                tokens: None,
+            }))
+        }
+        fn process_chunk(&mut self, chunk: &AudioChunk) -> Result<Option<Recognition>> {
             let resp = self.responses.get(self.call_index).cloned().flatten();
             self.call_index += 1;
-            Ok(resp)
+            Ok(resp.map(|text| Recognition {
+                text,
+                start_sample: chunk.start_sample,
+                end_sample: chunk.start_sample + chunk.pcm.len() as u64,
+                is_final: !self.partial,
+                tokens: self.tokens.clone(),
+            }))
         }
     }
 
+    fn chunk(start_sample: u64) -> EngineInput {
+        EngineInput::Audio(AudioChunk { start_sample, pcm: vec![0.0f32; 2560] })
+    }
+
     #[test]
     fn inference_thread_forwards_recognized_text() {
-        let engine = Box::new(MockEngine {
-            responses: vec![Some("hello world".to_string())],
-            call_index: 0,
-        });
+        let engine = Box::new(MockEngine::new(vec![Some("hello world")]));
         let (chunk_tx, chunk_rx) = mpsc::sync_channel(4);
         let (caption_tx, caption_rx) = mpsc::sync_channel(4);
-        let _handle = spawn_inference_thread(engine, chunk_rx, caption_tx);
-        chunk_tx.send(vec![0.0f32; 2560]).unwrap();
+        let _handle = spawn_inference_thread(engine, chunk_rx, caption_tx, None);
+        chunk_tx.send(chunk(0)).unwrap();
         drop(chunk_tx);
-        let received: Vec<String> = caption_rx.iter().collect();
+        let received: Vec<String> = caption_rx.iter().map(|c| c.text).collect();
         assert_eq!(received, vec!["hello world"]);
     }
 
     #[test]
     fn inference_thread_suppresses_none_responses() {
-        let engine = Box::new(MockEngine {
-            responses: vec![None, Some("world".to_string())],
-            call_index: 0,
-        });
+        let engine = Box::new(MockEngine::new(vec![None, Some("world")]));
         let (chunk_tx, chunk_rx) = mpsc::sync_channel(4);
         let (caption_tx, caption_rx) = mpsc::sync_channel(4);
-        let _handle = spawn_inference_thread(engine, chunk_rx, caption_tx);
-        chunk_tx.send(vec![0.0f32; 2560]).unwrap(); // None
-        chunk_tx.send(vec![0.0f32; 2560]).unwrap(); // Some("world")
+        let _handle = spawn_inference_thread(engine, chunk_rx, caption_tx, None);
+        chunk_tx.send(chunk(0)).unwrap(); // None
+        chunk_tx.send(chunk(2560)).unwrap(); // Some("world")
         drop(chunk_tx);
-        let received: Vec<String> = caption_rx.iter().collect();
+        let received: Vec<String> = caption_rx.iter().map(|c| c.text).collect();
         assert_eq!(received, vec!["world"]);
     }
 
     #[test]
     fn inference_thread_suppresses_whitespace_only_text() {
-        let engine = Box::new(MockEngine {
-            responses: vec![Some("   ".to_string()), Some("hi".to_string())],
-            call_index: 0,
-        });
+        let engine = Box::new(MockEngine::new(vec![Some("   "), Some("hi")]));
         let (chunk_tx, chunk_rx) = mpsc::sync_channel(4);
         let (caption_tx, caption_rx) = mpsc::sync_channel(4);
-        let _handle = spawn_inference_thread(engine, chunk_rx, caption_tx);
-        chunk_tx.send(vec![0.0f32; 2560]).unwrap(); // whitespace only
-        chunk_tx.send(vec![0.0f32; 2560]).unwrap(); // "hi"
+        let _handle = spawn_inference_thread(engine, chunk_rx, caption_tx, None);
+        chunk_tx.send(chunk(0)).unwrap(); // whitespace only
+        chunk_tx.send(chunk(2560)).unwrap(); // "hi"
         drop(chunk_tx);
-        let received: Vec<String> = caption_rx.iter().collect();
+        let received: Vec<String> = caption_rx.iter().map(|c| c.text).collect();
         assert_eq!(received, vec!["hi"]);
     }
 
+    #[test]
+    fn inference_thread_tags_captions_with_source() {
+        let engine = Box::new(MockEngine::new(vec![Some("hi")]));
+        let tag = SourceTag { label: "Me".to_string(), color: None };
+        let (chunk_tx, chunk_rx) = mpsc::sync_channel(4);
+        let (caption_tx, caption_rx) = mpsc::sync_channel(4);
+        let _handle = spawn_inference_thread(engine, chunk_rx, caption_tx, Some(tag));
+        chunk_tx.send(chunk(5120)).unwrap();
+        drop(chunk_tx);
+        let received: Vec<Caption> = caption_rx.iter().collect();
+        assert_eq!(received, vec![Caption { end_sample: 7680, ..Caption::test_final(5120, "hi", Some("Me")) }]);
+    }
+
+    #[test]
+    fn inference_thread_keeps_token_confidence() {
+        let tokens = vec![TokenConfidence { text: "hi".to_string(), confidence: 0.9 }];
+        let engine = Box::new(MockEngine { tokens: Some(tokens.clone()), ..MockEngine::new(vec![Some("hi")]) });
+        let (chunk_tx, chunk_rx) = mpsc::sync_channel(4);
+        let (caption_tx, caption_rx) = mpsc::sync_channel(4);
+        let _handle = spawn_inference_thread(engine, chunk_rx, caption_tx, None);
+        chunk_tx.send(chunk(0)).unwrap();
+        drop(chunk_tx);
+        let received: Vec<_> = caption_rx.iter().map(|c| c.tokens).collect();
+        assert_eq!(received, vec![Some(tokens)]);
+    }
+
+    #[test]
+    fn inference_thread_forwards_partial_hypotheses() {
+        let engine = Box::new(MockEngine { partial: true, ..MockEngine::new(vec![Some("hel"), Some("")]) });
+        let (chunk_tx, chunk_rx) = mpsc::sync_channel(4);
+        let (caption_tx, caption_rx) = mpsc::sync_channel(4);
+        let _handle = spawn_inference_thread(engine, chunk_rx, caption_tx, None);
+        chunk_tx.send(chunk(0)).unwrap();
+        chunk_tx.send(chunk(2560)).unwrap(); // empty partial withdraws "hel"
+        drop(chunk_tx);
+        let received: Vec<(String, bool)> = caption_rx.iter().map(|c| (c.text, c.is_final)).collect();
+        assert_eq!(received, vec![("hel".to_string(), false), (String::new(), false)]);
+    }
+
+    #[test]
+    fn inference_thread_flushes_engine_on_pause() {
+        let engine = Box::new(MockEngine::new(vec![None]));
+        let (chunk_tx, chunk_rx) = mpsc::sync_channel(4);
+        let (caption_tx, caption_rx) = mpsc::sync_channel(4);
+        let _handle = spawn_inference_thread(engine, chunk_rx, caption_tx, None);
+        chunk_tx.send(chunk(0)).unwrap();
+        chunk_tx.send(EngineInput::Flush).unwrap();
+        drop(chunk_tx);
+        let received: Vec<String> = caption_rx.iter().map(|c| c.text).collect();
+        assert_eq!(received, vec![" flushed"]);
+    }
+
     /// AC5.3: CUDA probe subprocess returns a bool without crashing the parent.
     ///
     /// Note: This test spawns the release binary (not the test binary) as a subprocess.
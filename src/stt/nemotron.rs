@@ -7,7 +7,7 @@
 
 use anyhow::{Context, Result};
 use std::path::Path;
-use super::SttEngine;
+use super::{AudioChunk, Recognition, SttEngine};
 
 /// Nemotron expects 560ms chunks = 8960 samples at 16kHz.
 const NEMOTRON_CHUNK_SAMPLES: usize = 8960;
@@ -16,6 +16,8 @@ pub struct NemotronEngine {
     inner: parakeet_rs::Nemotron,
     /// Internal buffer to accumulate 160ms chunks until 560ms is reached.
     chunk_buf: Vec<f32>,
+    /// Session offset of `chunk_buf[0]`.
+    buf_start: u64,
 }
 
 impl NemotronEngine {
@@ -35,6 +37,7 @@ impl NemotronEngine {
         Ok(NemotronEngine {
             inner,
             chunk_buf: Vec::with_capacity(NEMOTRON_CHUNK_SAMPLES),
+            buf_start: 0,
         })
     }
 }
@@ -44,15 +47,22 @@ impl SttEngine for NemotronEngine {
         16_000
     }
 
-    fn process_chunk(&mut self, pcm: &[f32]) -> Result<Option<String>> {
-        self.chunk_buf.extend_from_slice(pcm);
+    fn process_chunk(&mut self, chunk: &AudioChunk) -> Result<Option<Recognition>> {
+        if self.chunk_buf.is_empty() {
+            self.buf_start = chunk.start_sample;
+        }
+        self.chunk_buf.extend_from_slice(&chunk.pcm);
 
         if self.chunk_buf.len() < NEMOTRON_CHUNK_SAMPLES {
             return Ok(None); // Still accumulating
         }
 
         // Drain exactly NEMOTRON_CHUNK_SAMPLES and process.
+        // The buffer is treated as contiguous; a dropped chunk only skews timing slightly.
+        let start_sample = self.buf_start;
+        let end_sample = start_sample + NEMOTRON_CHUNK_SAMPLES as u64;
         let chunk: Vec<f32> = self.chunk_buf.drain(..NEMOTRON_CHUNK_SAMPLES).collect();
+        self.buf_start = end_sample;
 
         let text = self.inner.transcribe_chunk(&chunk)
             .context("Nemotron transcribe_chunk")?;
@@ -60,7 +70,34 @@ impl SttEngine for NemotronEngine {
         if text.is_empty() {
             Ok(None)
         } else {
-            Ok(Some(text))
+            // Greedy RNNT decoding commits each token once, so every result is final. It may
+            // still re-emit words across a chunk boundary, which `may_repeat_previous_text`
+            // (left at its default) tells the overlay to trim.
+            // parakeet-rs does not expose token scores.
+            Ok(Some(Recognition { text, start_sample, end_sample, is_final: true, tokens: None }))
+        }
+    }
+
+    fn flush(&mut self) -> Result<Option<Recognition>> {
+        if self.chunk_buf.is_empty() {
+            self.inner.reset();
+            return Ok(None);
+        }
+        // Pad the partial chunk with silence so its audio is decoded, then drop the
+        // decoder state: the next speech is a new utterance.
+        let start_sample = self.buf_start;
+        let end_sample = start_sample + self.chunk_buf.len() as u64;
+        self.chunk_buf.resize(NEMOTRON_CHUNK_SAMPLES, 0.0);
+        let chunk = std::mem::take(&mut self.chunk_buf);
+        let text = self.inner.transcribe_chunk(&chunk)
+            .context("Nemotron transcribe_chunk (flush)");
+        self.inner.reset();
+        let text = text?;
+
+        if text.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(Recognition { text, start_sample, end_sample, is_final: true, tokens: None }))
         }
     }
 }
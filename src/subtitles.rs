@@ -0,0 +1,357 @@
+//! Subtitle export: groups timed caption fragments into readable cues and writes them as
+//! SRT or WebVTT, for offline transcription and live "record subtitles" sessions.
+
+use crate::config::SubtitleFormat;
+use crate::stt::{Caption, SAMPLES_PER_SEC};
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Timestamps are offsets in session samples (`SAMPLES_PER_SEC`).
+const SAMPLES_PER_MS: u64 = SAMPLES_PER_SEC / 1000;
+/// Longest subtitle line, in characters.
+const MAX_LINE_CHARS: usize = 42;
+/// A cue holds at most two full lines.
+const MAX_CUE_CHARS: usize = MAX_LINE_CHARS * 2;
+/// Longest time a single cue stays on screen (6s).
+const MAX_CUE_SAMPLES: u64 = 6 * SAMPLES_PER_SEC;
+/// Shortest time a cue stays on screen, if the next one does not start sooner (1s).
+const MIN_CUE_SAMPLES: u64 = SAMPLES_PER_SEC;
+/// A pause at least this long starts a new cue (1.5s).
+const MAX_GAP_SAMPLES: u64 = SAMPLES_PER_SEC * 3 / 2;
+
+impl SubtitleFormat {
+    /// Format implied by a file extension (`.srt` or `.vtt`).
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "srt" => Some(SubtitleFormat::Srt),
+            "vtt" => Some(SubtitleFormat::Vtt),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "srt",
+            SubtitleFormat::Vtt => "vtt",
+        }
+    }
+}
+
+/// One subtitle cue; times are offsets in session samples (`SAMPLES_PER_SEC`), `end_sample`
+/// exclusive.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+    pub start_sample: u64,
+    pub end_sample: u64,
+    pub text: String,
+}
+
+/// Groups caption fragments into cues. A cue ends at the end of a sentence, on a speaker
+/// change or a pause, or before it would grow past two lines or six seconds.
+#[derive(Default)]
+pub struct CueBuilder {
+    /// Cue being filled.
+    current: Option<Cue>,
+    /// Speaker of `current`.
+    speaker: Option<String>,
+    /// Finished cue held back until the next one starts, so its end can be extended to
+    /// the minimum duration without overlapping it.
+    closed: Option<Cue>,
+}
+
+impl CueBuilder {
+    /// Add a recognized fragment. Returns the cues completed by it.
+    pub fn push(&mut self, start_sample: u64, end_sample: u64, text: &str, speaker: Option<&str>) -> Vec<Cue> {
+        let mut done = Vec::new();
+        if let Some(cur) = &self.current {
+            let chars = cur.text.chars().count() + text.chars().count();
+            let breaks = self.speaker.as_deref() != speaker
+                || start_sample.saturating_sub(cur.end_sample) >= MAX_GAP_SAMPLES
+                || chars > MAX_CUE_CHARS
+                || end_sample.saturating_sub(cur.start_sample) > MAX_CUE_SAMPLES;
+            if breaks {
+                self.close();
+            }
+        }
+        if let Some(mut closed) = self.closed.take() {
+            closed.end_sample = closed.end_sample.max((closed.start_sample + MIN_CUE_SAMPLES).min(start_sample));
+            done.push(closed);
+        }
+
+        match &mut self.current {
+            Some(cur) => {
+                cur.text.push_str(text);
+                cur.end_sample = end_sample;
+            }
+            None => {
+                let prefix = speaker.map(|s| format!("{s}: ")).unwrap_or_default();
+                let text = format!("{prefix}{}", text.trim_start());
+                self.current = Some(Cue { start_sample, end_sample, text });
+            }
+        }
+        self.speaker = speaker.map(str::to_string);
+
+        if self.current.as_ref().is_some_and(|c| c.text.trim_end().ends_with(['.', '?', '!'])) {
+            self.close();
+        }
+        done
+    }
+
+    /// Flush the remaining cues at the end of the recording.
+    pub fn finish(&mut self) -> Vec<Cue> {
+        self.close();
+        self.closed
+            .take()
+            .map(|mut cue| {
+                cue.end_sample = cue.end_sample.max(cue.start_sample + MIN_CUE_SAMPLES);
+                cue
+            })
+            .into_iter()
+            .collect()
+    }
+
+    fn close(&mut self) {
+        if let Some(mut cue) = self.current.take() {
+            cue.text = cue.text.trim().to_string();
+            self.closed = Some(cue);
+        }
+    }
+}
+
+/// Writes cues as an SRT or WebVTT file.
+pub struct SubtitleWriter<W: Write> {
+    format: SubtitleFormat,
+    out: W,
+    builder: CueBuilder,
+    cues_written: usize,
+}
+
+impl<W: Write> SubtitleWriter<W> {
+    pub fn new(format: SubtitleFormat, mut out: W) -> io::Result<Self> {
+        if format == SubtitleFormat::Vtt {
+            out.write_all(b"WEBVTT\n\n")?;
+        }
+        Ok(SubtitleWriter { format, out, builder: CueBuilder::default(), cues_written: 0 })
+    }
+
+    /// Add a recognized fragment; finished cues are written out immediately.
+    pub fn push(&mut self, start_sample: u64, end_sample: u64, text: &str, speaker: Option<&str>) -> io::Result<()> {
+        for cue in self.builder.push(start_sample, end_sample, text, speaker) {
+            self.write_cue(&cue)?;
+        }
+        Ok(())
+    }
+
+    /// Write the last cue and return the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        for cue in self.builder.finish() {
+            self.write_cue(&cue)?;
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn write_cue(&mut self, cue: &Cue) -> io::Result<()> {
+        self.cues_written += 1;
+        if self.format == SubtitleFormat::Srt {
+            writeln!(self.out, "{}", self.cues_written)?;
+        }
+        writeln!(
+            self.out,
+            "{} --> {}",
+            format_timestamp(cue.start_sample, self.format),
+            format_timestamp(cue.end_sample, self.format)
+        )?;
+        writeln!(self.out, "{}\n", wrap_lines(&cue.text, MAX_LINE_CHARS).join("\n"))?;
+        self.out.flush()
+    }
+}
+
+/// `HH:MM:SS,mmm` (SRT) or `HH:MM:SS.mmm` (WebVTT).
+pub fn format_timestamp(sample: u64, format: SubtitleFormat) -> String {
+    let ms = sample / SAMPLES_PER_MS;
+    let separator = match format {
+        SubtitleFormat::Srt => ',',
+        SubtitleFormat::Vtt => '.',
+    };
+    format!(
+        "{:02}:{:02}:{:02}{separator}{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+/// Greedy word wrap; a word longer than a line gets a line of its own.
+fn wrap_lines(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Directory live subtitle recordings are saved to: ~/.local/share/subtidal/subtitles
+pub fn recordings_dir() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(|| PathBuf::from(".local/share"))
+        .join("subtidal")
+        .join("subtitles")
+}
+
+struct Recording {
+    writer: SubtitleWriter<BufWriter<File>>,
+    path: PathBuf,
+    /// Session position the recording started at; cue times are relative to it.
+    offset: u64,
+}
+
+/// Live "record subtitles" session, shared by the tray (start/stop) and the caption
+/// forwarding thread (push).
+#[derive(Clone)]
+pub struct Recorder {
+    /// Start of the session timeline that caption timestamps are measured from.
+    epoch: Instant,
+    recording: Arc<Mutex<Option<Recording>>>,
+}
+
+impl Recorder {
+    pub fn new(epoch: Instant) -> Self {
+        Recorder { epoch, recording: Arc::new(Mutex::new(None)) }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.lock().unwrap().is_some()
+    }
+
+    /// Start recording to a new file in `recordings_dir()`. Returns its path.
+    pub fn start(&self, format: SubtitleFormat) -> Result<PathBuf> {
+        let dir = recordings_dir();
+        std::fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+        let stamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let path = dir.join(format!("captions-{stamp}.{}", format.extension()));
+        let file = File::create(&path).with_context(|| format!("creating {}", path.display()))?;
+        let writer = SubtitleWriter::new(format, BufWriter::new(file))?;
+        let offset = crate::stt::session_position(self.epoch);
+        *self.recording.lock().unwrap() = Some(Recording { writer, path: path.clone(), offset });
+        Ok(path)
+    }
+
+    /// Stop recording and write the last cue. Returns the file path, if one was recording.
+    pub fn stop(&self) -> Result<Option<PathBuf>> {
+        let Some(recording) = self.recording.lock().unwrap().take() else {
+            return Ok(None);
+        };
+        recording
+            .writer
+            .finish()
+            .with_context(|| format!("writing {}", recording.path.display()))?;
+        Ok(Some(recording.path))
+    }
+
+    /// Add a caption to the recording, if one is running. Partial hypotheses are skipped;
+    /// only final text is written.
+    pub fn push(&self, caption: &Caption) {
+        if !caption.is_final {
+            return;
+        }
+        let mut guard = self.recording.lock().unwrap();
+        let Some(recording) = guard.as_mut() else {
+            return;
+        };
+        // Audio heard before the recording started is clamped to its first instant.
+        let start = caption.start_sample.saturating_sub(recording.offset);
+        let end = caption.end_sample.saturating_sub(recording.offset).max(start);
+        let speaker = caption.source.as_ref().map(|s| s.label.as_str());
+        if let Err(e) = recording.writer.push(start, end, &caption.text, speaker) {
+            eprintln!("warn: failed to write subtitles to {}: {e}", recording.path.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stt::SAMPLES_PER_SEC as SEC;
+
+    fn write_all(format: SubtitleFormat, fragments: &[(u64, u64, &str)]) -> String {
+        let mut w = SubtitleWriter::new(format, Vec::new()).unwrap();
+        for (start, end, text) in fragments {
+            w.push(*start, *end, text, None).unwrap();
+        }
+        String::from_utf8(w.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn timestamps_use_format_separator() {
+        let t = 3_723 * SEC + 456 * SAMPLES_PER_MS;
+        assert_eq!(format_timestamp(t, SubtitleFormat::Srt), "01:02:03,456");
+        assert_eq!(format_timestamp(t, SubtitleFormat::Vtt), "01:02:03.456");
+    }
+
+    #[test]
+    fn srt_cues_are_numbered_and_split_at_sentences() {
+        let srt = write_all(
+            SubtitleFormat::Srt,
+            &[(0, SEC / 2, "Hello"), (SEC / 2, SEC, " there."), (SEC, 2 * SEC, " How are you?")],
+        );
+        assert_eq!(
+            srt,
+            "1\n00:00:00,000 --> 00:00:01,000\nHello there.\n\n\
+             2\n00:00:01,000 --> 00:00:02,000\nHow are you?\n\n"
+        );
+    }
+
+    #[test]
+    fn vtt_has_header_and_dot_separator() {
+        let vtt = write_all(SubtitleFormat::Vtt, &[(0, 2 * SEC, "Hi.")]);
+        assert_eq!(vtt, "WEBVTT\n\n00:00:00.000 --> 00:00:02.000\nHi.\n\n");
+    }
+
+    #[test]
+    fn short_cue_is_extended_but_not_into_the_next() {
+        let mut b = CueBuilder::default();
+        assert!(b.push(0, SEC / 4, "Yes.", None).is_empty());
+        let done = b.push(SEC / 2, SEC, "No.", None);
+        assert_eq!(done[0].end_sample, SEC / 2, "extended up to the next cue");
+        let last = b.finish();
+        assert_eq!(last[0].end_sample, SEC / 2 + MIN_CUE_SAMPLES);
+    }
+
+    #[test]
+    fn pause_and_speaker_change_start_new_cues() {
+        let mut b = CueBuilder::default();
+        let mut cues = b.push(0, SEC, "one", Some("Me"));
+        cues.extend(b.push(3 * SEC, 4 * SEC, " two", Some("Me")));
+        cues.extend(b.push(4 * SEC, 5 * SEC, " three", Some("Firefox")));
+        cues.extend(b.finish());
+        let texts: Vec<&str> = cues.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, vec!["Me: one", "Me: two", "Firefox: three"]);
+    }
+
+    #[test]
+    fn long_text_wraps_to_two_lines() {
+        let text = "the quick brown fox jumps over the lazy dog and keeps running far away";
+        let lines = wrap_lines(text, MAX_LINE_CHARS);
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|l| l.chars().count() <= MAX_LINE_CHARS));
+    }
+}
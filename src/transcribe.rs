@@ -0,0 +1,103 @@
+//! Offline transcription: `subtidal transcribe <file>` decodes a file, runs it through the same
+//! resampler and engine as live capture, and prints the transcript (optionally also writing
+//! subtitles). No GTK, PipeWire or tray.
+
+use crate::audio::file::AudioFile;
+use crate::audio::resampler::AudioResampler;
+use crate::stt::{AudioChunk, Recognition, SttEngine};
+use anyhow::Result;
+
+/// Silence appended after the last sample so audio still buffered in the resampler and
+/// the engine's chunk accumulator gets recognized.
+const TAIL_SILENCE_SECS: usize = 1;
+
+/// Transcribe `file` with `engine`, calling `on_text` for each final fragment as it is
+/// recognized; partial hypotheses are skipped. Fragment timestamps are offsets in session
+/// samples (`SAMPLES_PER_SEC`) from the start of the file.
+pub fn transcribe(
+    file: &mut AudioFile,
+    engine: &mut dyn SttEngine,
+    mut on_text: impl FnMut(&Recognition) -> Result<()>,
+) -> Result<()> {
+    let mut resampler = AudioResampler::with_input(file.sample_rate, file.channels)?;
+    let mut next_sample = 0u64;
+    let mut feed = |samples: &[f32]| -> Result<()> {
+        for pcm in resampler.push_interleaved(samples)? {
+            let chunk = AudioChunk { start_sample: next_sample, pcm };
+            next_sample += chunk.pcm.len() as u64;
+            match engine.process_chunk(&chunk) {
+                Ok(Some(result)) if result.is_final && !result.text.trim().is_empty() => on_text(&result)?,
+                Ok(_) => {}
+                Err(e) => eprintln!("warn: inference error (skipping chunk): {e}"),
+            }
+        }
+        Ok(())
+    };
+
+    while let Some(block) = file.next_block()? {
+        feed(&block)?;
+    }
+    let tail = vec![0.0f32; file.sample_rate as usize * file.channels * TAIL_SILENCE_SECS];
+    feed(&tail)?;
+    match engine.flush() {
+        Ok(Some(result)) if result.is_final && !result.text.trim().is_empty() => on_text(&result),
+        Ok(_) => Ok(()),
+        Err(e) => {
+            eprintln!("warn: inference error (skipping chunk): {e}");
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::file::{RawFormat, RawSpec};
+    use crate::audio::resampler::{CHUNK_SAMPLES, OUTPUT_SAMPLE_RATE};
+    use std::io::Write;
+
+    /// Emits a word for every chunk it is fed and counts the samples.
+    struct CountingEngine {
+        samples: usize,
+    }
+
+    impl SttEngine for CountingEngine {
+        fn sample_rate(&self) -> u32 { 16_000 }
+        fn process_chunk(&mut self, chunk: &AudioChunk) -> Result<Option<Recognition>> {
+            assert_eq!(chunk.pcm.len(), CHUNK_SAMPLES);
+            assert_eq!(chunk.start_sample, self.samples as u64, "chunks are contiguous");
+            self.samples += chunk.pcm.len();
+            Ok(Some(Recognition {
+                text: " word".to_string(),
+                start_sample: chunk.start_sample,
+                end_sample: chunk.start_sample + CHUNK_SAMPLES as u64,
+                is_final: true,
+                tokens: None,
+            }))
+        }
+    }
+
+    #[test]
+    fn transcribes_whole_file_and_flushes_tail() {
+        // Two seconds of 44.1kHz stereo s16 raw PCM.
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        let bytes: Vec<u8> = (0..44_100 * 2 * 2).flat_map(|i: i32| ((i % 200) as i16).to_le_bytes()).collect();
+        file.write_all(&bytes).unwrap();
+        file.flush().unwrap();
+
+        let spec = RawSpec { sample_rate: 44_100, channels: 2, format: RawFormat::S16le };
+        let mut audio = AudioFile::open(file.path(), Some(spec)).unwrap();
+        let mut engine = CountingEngine { samples: 0 };
+        let mut fragments = Vec::new();
+        transcribe(&mut audio, &mut engine, |t| {
+            fragments.push(t.clone());
+            Ok(())
+        })
+        .unwrap();
+
+        // All two seconds reach the engine, plus part of the silent tail.
+        assert!(engine.samples >= OUTPUT_SAMPLE_RATE as usize * 2, "only {} samples", engine.samples);
+        assert_eq!(fragments.len(), engine.samples / CHUNK_SAMPLES);
+        assert_eq!(fragments[1].start_sample, CHUNK_SAMPLES as u64);
+    }
+}
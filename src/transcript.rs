@@ -0,0 +1,376 @@
+//! Transcript log: appends finalized captions, with wall-clock timestamps, to text or JSONL
+//! files in ~/.local/share/subtidal/transcripts.
+//!
+//! Each source gets its own file, named `<date>-<source>.<ext>`: captions tagged with a
+//! speaker label are filed under the label, mixed captions under the current capture mix.
+//! A new file is started every session (a numeric suffix keeps earlier files), whenever the
+//! capture mix changes, and when the current file grows past the configured size or age.
+
+use crate::audio::source_spec;
+use crate::config::{SourceConfig, TranscriptConfig, TranscriptFormat};
+use crate::stt::{samples_to_ms, Caption};
+use crate::subtitles::{Cue, CueBuilder};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use time::{OffsetDateTime, UtcOffset};
+
+/// Directory transcripts are written to: ~/.local/share/subtidal/transcripts
+pub fn transcripts_dir() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(|| PathBuf::from(".local/share"))
+        .join("subtidal")
+        .join("transcripts")
+}
+
+/// Name the capture mix is filed under, e.g. "system" or "system+firefox".
+pub fn mix_name(sources: &[SourceConfig]) -> String {
+    sources.iter().map(|s| source_spec(&s.source)).collect::<Vec<_>>().join("+")
+}
+
+/// One JSONL record.
+#[derive(Serialize)]
+struct Record<'a> {
+    /// RFC 3339 local time the cue started.
+    time: &'a str,
+    source: &'a str,
+    text: &'a str,
+    /// Session offsets, as in the D-Bus and WebSocket caption events.
+    start_ms: u64,
+    end_ms: u64,
+}
+
+/// The file one source is currently written to.
+struct LogFile {
+    file: File,
+    path: PathBuf,
+    opened: Instant,
+    bytes: u64,
+}
+
+#[derive(Default)]
+struct SourceLog {
+    cues: CueBuilder,
+    file: Option<LogFile>,
+}
+
+struct State {
+    dir: PathBuf,
+    format: TranscriptFormat,
+    /// Rotate once a file reaches this size; 0 = never.
+    max_bytes: u64,
+    /// Rotate once a file is this old.
+    max_age: Option<Duration>,
+    enabled: bool,
+    /// Name mixed (untagged) captions are filed under.
+    mix: String,
+    sources: HashMap<String, SourceLog>,
+}
+
+/// Transcript sink shared by the tray (toggle, source changes) and the caption forwarding
+/// thread (push). Cheap to clone.
+#[derive(Clone)]
+pub struct TranscriptLog {
+    /// Local wall-clock time at the start of the session timeline.
+    started: OffsetDateTime,
+    state: Arc<Mutex<State>>,
+}
+
+impl TranscriptLog {
+    /// `epoch` is the start of the session timeline; `utc_offset` the local time zone,
+    /// looked up at startup.
+    pub fn new(epoch: Instant, utc_offset: UtcOffset, settings: &TranscriptConfig) -> Self {
+        Self::in_dir(transcripts_dir(), epoch, utc_offset, settings)
+    }
+
+    fn in_dir(dir: PathBuf, epoch: Instant, utc_offset: UtcOffset, settings: &TranscriptConfig) -> Self {
+        let started = OffsetDateTime::now_utc().to_offset(utc_offset) - epoch.elapsed();
+        let state = State {
+            dir,
+            format: settings.format,
+            max_bytes: settings.rotate_size_mb * 1024 * 1024,
+            max_age: (settings.rotate_hours > 0).then(|| Duration::from_secs(settings.rotate_hours * 3600)),
+            enabled: settings.enabled,
+            mix: String::new(),
+            sources: HashMap::new(),
+        };
+        TranscriptLog { started, state: Arc::new(Mutex::new(state)) }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.state.lock().unwrap().enabled
+    }
+
+    /// Start or stop logging. Stopping writes out pending text and closes all files; the
+    /// next start opens new ones.
+    pub fn set_enabled(&self, enabled: bool) {
+        let mut state = self.state.lock().unwrap();
+        if state.enabled && !enabled {
+            self.close_all(&mut state);
+        }
+        state.enabled = enabled;
+    }
+
+    /// Record the selected capture sources. A different mix starts a new file for mixed
+    /// captions.
+    pub fn set_sources(&self, sources: &[SourceConfig]) {
+        let mix = mix_name(sources);
+        let mut state = self.state.lock().unwrap();
+        if state.mix == mix {
+            return;
+        }
+        let previous = std::mem::replace(&mut state.mix, mix);
+        if let Some(mut log) = state.sources.remove(&previous) {
+            let cues = log.cues.finish();
+            self.write_cues(&state, &previous, &mut log, cues);
+        }
+    }
+
+    /// Add a caption to the log, if logging is on. Partial hypotheses are skipped.
+    pub fn push(&self, caption: &Caption) {
+        if !caption.is_final {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        if !state.enabled {
+            return;
+        }
+        let key = match &caption.source {
+            Some(tag) => tag.label.clone(),
+            None => state.mix.clone(),
+        };
+        let mut log = state.sources.remove(&key).unwrap_or_default();
+        let cues = log.cues.push(caption.start_sample, caption.end_sample, &caption.text, None);
+        self.write_cues(&state, &key, &mut log, cues);
+        state.sources.insert(key, log);
+    }
+
+    /// Write out pending text and close all files, at exit.
+    pub fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        self.close_all(&mut state);
+    }
+
+    fn close_all(&self, state: &mut State) {
+        for (key, mut log) in std::mem::take(&mut state.sources) {
+            let cues = log.cues.finish();
+            self.write_cues(state, &key, &mut log, cues);
+        }
+    }
+
+    fn write_cues(&self, state: &State, key: &str, log: &mut SourceLog, cues: Vec<Cue>) {
+        for cue in cues {
+            let time = self.started + Duration::from_millis(samples_to_ms(cue.start_sample));
+            if let Err(e) = Self::write_cue(state, key, log, &cue, time) {
+                eprintln!("warn: failed to write transcript: {e:#}");
+                // Try a fresh file for the next cue.
+                log.file = None;
+            }
+        }
+    }
+
+    fn write_cue(state: &State, key: &str, log: &mut SourceLog, cue: &Cue, time: OffsetDateTime) -> Result<()> {
+        let expired = |f: &LogFile| {
+            (state.max_bytes > 0 && f.bytes >= state.max_bytes)
+                || state.max_age.is_some_and(|age| f.opened.elapsed() >= age)
+        };
+        if log.file.as_ref().is_none_or(expired) {
+            log.file = Some(open_file(&state.dir, key, state.format, time)?);
+        }
+        let out = log.file.as_mut().expect("opened above");
+        let line = match state.format {
+            TranscriptFormat::Text => {
+                format!("[{:02}:{:02}:{:02}] {}\n", time.hour(), time.minute(), time.second(), cue.text)
+            }
+            TranscriptFormat::Jsonl => {
+                let record = Record {
+                    time: &rfc3339(time),
+                    source: key,
+                    text: &cue.text,
+                    start_ms: samples_to_ms(cue.start_sample),
+                    end_ms: samples_to_ms(cue.end_sample),
+                };
+                let mut line = serde_json::to_string(&record).expect("transcript records always serialize");
+                line.push('\n');
+                line
+            }
+        };
+        // One write per line, so a crash never leaves half a record.
+        out.file
+            .write_all(line.as_bytes())
+            .with_context(|| format!("writing {}", out.path.display()))?;
+        out.bytes += line.len() as u64;
+        Ok(())
+    }
+}
+
+/// Create a new file for `key`, never reusing an existing one.
+fn open_file(dir: &Path, key: &str, format: TranscriptFormat, time: OffsetDateTime) -> Result<LogFile> {
+    std::fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    let ext = match format {
+        TranscriptFormat::Text => "txt",
+        TranscriptFormat::Jsonl => "jsonl",
+    };
+    let stem = format!("{:04}-{:02}-{:02}-{}", time.year(), u8::from(time.month()), time.day(), file_slug(key));
+    for n in 1.. {
+        let name = match n {
+            1 => format!("{stem}.{ext}"),
+            n => format!("{stem}-{n}.{ext}"),
+        };
+        let path = dir.join(name);
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => {
+                eprintln!("info: logging transcript to {}", path.display());
+                return Ok(LogFile { file, path, opened: Instant::now(), bytes: 0 });
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e).with_context(|| format!("creating {}", path.display())),
+        }
+    }
+    unreachable!("file numbers are unbounded")
+}
+
+/// Lowercase file-name-safe form of a source name: "Firefox (Meet)" → "firefox-meet".
+fn file_slug(name: &str) -> String {
+    let slug = name
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() { "audio".to_string() } else { slug }
+}
+
+fn rfc3339(time: OffsetDateTime) -> String {
+    let offset = time.offset();
+    let sign = if offset.is_negative() { '-' } else { '+' };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{sign}{:02}:{:02}",
+        time.year(),
+        u8::from(time.month()),
+        time.day(),
+        time.hour(),
+        time.minute(),
+        time.second(),
+        offset.whole_hours().unsigned_abs(),
+        offset.minutes_past_hour().unsigned_abs(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AudioSource;
+    use crate::stt::SAMPLES_PER_SEC as SEC;
+
+    fn log_in(dir: &Path, format: TranscriptFormat) -> TranscriptLog {
+        let settings = TranscriptConfig { enabled: true, format, ..Default::default() };
+        let log = TranscriptLog::in_dir(dir.to_path_buf(), Instant::now(), UtcOffset::UTC, &settings);
+        log.set_sources(&[SourceConfig::default()]);
+        log
+    }
+
+    /// File names in `dir` with the date prefix stripped, sorted.
+    fn files(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy()[11..].to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn read(dir: &Path, suffix: &str) -> String {
+        let entry = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .find(|p| p.to_string_lossy().ends_with(suffix))
+            .unwrap();
+        std::fs::read_to_string(entry).unwrap()
+    }
+
+    #[test]
+    fn final_text_is_filed_per_source_with_timestamps() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = log_in(dir.path(), TranscriptFormat::Text);
+        log.push(&Caption { is_final: false, ..Caption::test_final(0, " Hello", None) });
+        log.push(&Caption::test_final(0, " Hello there.", None));
+        log.push(&Caption::test_final(SEC, " Hi.", Some("Me")));
+        log.push(&Caption::test_final(2 * SEC, " Bye.", None));
+        log.close();
+
+        assert_eq!(files(dir.path()), vec!["me.txt", "system.txt"]);
+        let system = read(dir.path(), "system.txt");
+        let lines: Vec<&str> = system.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with('[') && lines[0].ends_with("] Hello there."), "{system}");
+        assert_eq!(&lines[0][3..4], ":");
+        assert!(lines[1].ends_with("] Bye."));
+        assert!(read(dir.path(), "me.txt").ends_with("] Hi.\n"));
+    }
+
+    #[test]
+    fn source_switch_and_restart_start_new_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = log_in(dir.path(), TranscriptFormat::Text);
+        log.push(&Caption::test_final(0, " One.", None));
+        let app = SourceConfig::new(AudioSource::Application {
+            node_id: 7,
+            node_name: "Firefox".to_string(),
+            app_name: None,
+            binary: None,
+        });
+        log.set_sources(&[SourceConfig::default(), app]);
+        log.push(&Caption::test_final(SEC, " Two.", None));
+        log.close();
+        // The next session keeps the earlier file.
+        let log = log_in(dir.path(), TranscriptFormat::Text);
+        log.push(&Caption::test_final(0, " Three.", None));
+        log.close();
+
+        assert_eq!(files(dir.path()), vec!["system-2.txt", "system-firefox.txt", "system.txt"]);
+    }
+
+    #[test]
+    fn files_rotate_by_size_and_logging_can_be_paused() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = log_in(dir.path(), TranscriptFormat::Text);
+        log.state.lock().unwrap().max_bytes = 20;
+        log.push(&Caption::test_final(0, " A sentence long enough.", None));
+        log.push(&Caption::test_final(2 * SEC, " Another one.", None));
+        log.set_enabled(false);
+        log.push(&Caption::test_final(4 * SEC, " Not logged.", None));
+        assert!(!log.is_enabled());
+
+        assert_eq!(files(dir.path()), vec!["system-2.txt", "system.txt"]);
+        assert!(read(dir.path(), "system-2.txt").ends_with("] Another one.\n"));
+    }
+
+    #[test]
+    fn jsonl_records_carry_local_time_and_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = TranscriptConfig { enabled: true, format: TranscriptFormat::Jsonl, ..Default::default() };
+        let offset = UtcOffset::from_hms(-5, -30, 0).unwrap();
+        let log = TranscriptLog::in_dir(dir.path().to_path_buf(), Instant::now(), offset, &settings);
+        log.push(&Caption::test_final(SEC, " Hi there.", Some("Guest")));
+        log.close();
+
+        let record: serde_json::Value = serde_json::from_str(read(dir.path(), "guest.jsonl").trim()).unwrap();
+        assert_eq!(record["source"], "Guest");
+        assert_eq!(record["text"], "Hi there.");
+        assert_eq!(record["start_ms"], 1000);
+        assert!(record["time"].as_str().unwrap().ends_with("-05:30"), "{record}");
+    }
+
+    #[test]
+    fn source_names_become_file_slugs() {
+        assert_eq!(file_slug("Firefox (Meet)"), "firefox-meet");
+        assert_eq!(file_slug("system+auto"), "system-auto");
+        assert_eq!(file_slug("  "), "audio");
+    }
+}
@@ -1,8 +1,8 @@
 //! System tray via ksni StatusNotifierItem.
 
-use crate::audio::{AudioCommand, AudioNode, NodeList};
-use crate::config::{AudioSource, Engine, OverlayMode};
-use crate::overlay::OverlayCommand;
+use crate::audio::{AudioCommand, AudioNode, NodeKind, NodeList};
+use crate::config::{AudioSource, Engine, OverlayLayer, OverlayMode, SourceConfig};
+use crate::overlay::{MonitorInfo, OverlayCommand};
 use ksni::{menu::*, Tray, TrayMethods};
 use std::sync::{
     atomic::{AtomicBool, Ordering},
@@ -13,9 +13,17 @@ use std::sync::{
 /// Full state of the tray — the menu is built fresh from these fields on every update.
 pub struct TrayState {
     pub captions_enabled: Arc<AtomicBool>,
-    pub active_source: AudioSource,
+    /// Sources selected for capture, with their gains. Sources whose app is not running
+    /// stay listed and are reattached by the audio thread when it appears.
+    pub active_sources: Vec<SourceConfig>,
+    /// Application an `Auto` source is currently following, by description.
+    pub following: Option<String>,
     pub overlay_mode: OverlayMode,
     pub locked: bool,
+    /// Connected monitors, as reported by the overlay.
+    pub monitors: Vec<MonitorInfo>,
+    /// Connector of the monitor the overlay is shown on (None = compositor's choice).
+    pub monitor: Option<String>,
     pub active_engine: Engine,
     /// Channel to send OverlayCommand to the GTK4 main thread.
     pub overlay_tx: Sender<OverlayCommand>,
@@ -25,6 +33,10 @@ pub struct TrayState {
     pub engine_tx: SyncSender<EngineCommand>,
     /// Shared node list from audio thread.
     pub node_list: NodeList,
+    /// Live subtitle recording, fed by the caption forwarding thread.
+    pub recorder: crate::subtitles::Recorder,
+    /// Transcript log, fed by the caption forwarding thread.
+    pub transcript: crate::transcript::TranscriptLog,
 }
 
 /// Commands for switching the STT engine at runtime.
@@ -35,11 +47,153 @@ pub enum EngineCommand {
 impl TrayState {
     /// Toggle captions on/off and notify the overlay. Single source of truth for
     /// the toggle — called from both left-click (activate) and the Captions checkmark.
-    fn toggle_captions(&mut self) {
+    pub fn toggle_captions(&mut self) {
         let prev = self.captions_enabled.load(Ordering::Relaxed);
         self.captions_enabled.store(!prev, Ordering::Relaxed);
         let _ = self.overlay_tx.send(OverlayCommand::SetVisible(!prev));
     }
+
+    /// Tick or untick a source in the capture mix, then apply and persist the new mix.
+    /// The last remaining source cannot be unticked.
+    pub fn toggle_source(&mut self, source: AudioSource) {
+        // A selected source matches when it is the same entry or captures the same live node,
+        // so a source saved under an old node ID is unticked by clicking its restarted app.
+        let nodes = crate::audio::list_nodes(&self.node_list);
+        let live_id = |s: &AudioSource| crate::audio::find_node(s, &nodes).map(|n| n.node_id);
+        let target = live_id(&source);
+        let pos = self
+            .active_sources
+            .iter()
+            .position(|s| s.source == source || (target.is_some() && live_id(&s.source) == target));
+        match pos {
+            Some(_) if self.active_sources.len() == 1 => return,
+            Some(i) => {
+                self.active_sources.remove(i);
+            }
+            None => self.active_sources.push(SourceConfig::new(source)),
+        }
+        self.apply_sources();
+    }
+
+    /// Capture only `source`, replacing the current selection. A source already selected
+    /// keeps its gain and label.
+    pub fn select_source(&mut self, source: AudioSource) {
+        let entry = match self.active_sources.iter().find(|s| s.source.same_source(&source)) {
+            Some(existing) => SourceConfig { source, ..existing.clone() },
+            None => SourceConfig::new(source),
+        };
+        self.active_sources = vec![entry];
+        self.apply_sources();
+    }
+
+    /// Reflect a source change made elsewhere (config reload, audio thread fallback).
+    pub fn set_active_sources(&mut self, sources: Vec<SourceConfig>) {
+        self.transcript.set_sources(&sources);
+        self.active_sources = sources;
+    }
+
+    /// Send the selected sources to the audio thread and persist them.
+    fn apply_sources(&mut self) {
+        self.transcript.set_sources(&self.active_sources);
+        let _ = self.audio_tx.send(AudioCommand::SetSources(self.active_sources.clone()));
+        // Persist audio source change to config.
+        // Note: load-modify-save pattern has a theoretical race if multiple tray actions fire simultaneously. Acceptable for single-user desktop app.
+        let mut cfg = crate::config::Config::load();
+        cfg.audio_sources = self.active_sources.clone();
+        if let Err(e) = cfg.save() {
+            eprintln!("warn: failed to save config: {e}");
+        }
+    }
+
+    /// Switch between docked and floating overlay, and persist the choice.
+    pub fn set_overlay_mode(&mut self, mode: OverlayMode) {
+        self.overlay_mode = mode.clone();
+        let _ = self.overlay_tx.send(OverlayCommand::SetMode(mode));
+        let mut cfg = crate::config::Config::load();
+        cfg.overlay_mode = self.overlay_mode.clone();
+        if let Err(e) = cfg.save() {
+            eprintln!("warn: failed to save config: {e}");
+        }
+    }
+
+    /// Lock or unlock the floating overlay. Has no effect in docked mode, which is always locked.
+    pub fn set_locked(&mut self, locked: bool) {
+        if self.overlay_mode != OverlayMode::Floating {
+            return;
+        }
+        self.locked = locked;
+        let _ = self.overlay_tx.send(OverlayCommand::SetLocked(locked));
+        let mut cfg = crate::config::Config::load();
+        cfg.locked = locked;
+        if let Err(e) = cfg.save() {
+            eprintln!("warn: failed to save config: {e}");
+        }
+    }
+
+    /// Show the overlay on another monitor, and persist the choice.
+    pub fn set_monitor(&mut self, monitor: Option<String>) {
+        if self.monitor == monitor {
+            return;
+        }
+        self.monitor = monitor.clone();
+        let _ = self.overlay_tx.send(OverlayCommand::SetMonitor(monitor));
+        let mut cfg = crate::config::Config::load();
+        cfg.monitor = self.monitor.clone();
+        if let Err(e) = cfg.save() {
+            eprintln!("warn: failed to save config: {e}");
+        }
+    }
+
+    /// Switch the STT engine and persist the choice. Selecting the active engine again is a
+    /// no-op, so its model is not reloaded.
+    pub fn set_engine(&mut self, engine: Engine) {
+        if self.active_engine == engine {
+            return;
+        }
+        self.active_engine = engine.clone();
+        let _ = self.engine_tx.send(EngineCommand::Switch(engine));
+        // Note: load-modify-save pattern has a theoretical race if multiple tray actions fire simultaneously. Acceptable for single-user desktop app.
+        let mut cfg = crate::config::Config::load();
+        cfg.engine = self.active_engine.clone();
+        if let Err(e) = cfg.save() {
+            eprintln!("warn: failed to save config: {e}");
+        }
+    }
+
+    /// Start or stop recording captions to a subtitle file.
+    fn toggle_recording(&mut self) {
+        if self.recorder.is_recording() {
+            match self.recorder.stop() {
+                Ok(Some(path)) => {
+                    eprintln!("info: subtitles saved to {}", path.display());
+                    let _ = notify_rust::Notification::new()
+                        .summary("Live Captions: Subtitles Saved")
+                        .body(&path.display().to_string())
+                        .timeout(notify_rust::Timeout::Milliseconds(5000))
+                        .show();
+                }
+                Ok(None) => {}
+                Err(e) => eprintln!("warn: failed to finish subtitle recording: {e:#}"),
+            }
+        } else {
+            let format = crate::config::Config::load().subtitle_format;
+            match self.recorder.start(format) {
+                Ok(path) => eprintln!("info: recording subtitles to {}", path.display()),
+                Err(e) => eprintln!("warn: failed to start subtitle recording: {e:#}"),
+            }
+        }
+    }
+
+    /// Start or stop logging the transcript, and persist the choice.
+    fn toggle_transcript(&mut self) {
+        let enabled = !self.transcript.is_enabled();
+        self.transcript.set_enabled(enabled);
+        let mut cfg = crate::config::Config::load();
+        cfg.transcript.enabled = enabled;
+        if let Err(e) = cfg.save() {
+            eprintln!("warn: failed to save config: {e}");
+        }
+    }
 }
 
 /// Ensure tray icons exist on disk at an XDG-standard location.
@@ -318,12 +472,44 @@ impl Tray for TrayState {
             }
             .into(),
 
+            // --- Record captions to a subtitle file ---
+            CheckmarkItem {
+                label: "Record Subtitles".to_string(),
+                checked: self.recorder.is_recording(),
+                activate: Box::new(|tray: &mut TrayState| {
+                    tray.toggle_recording();
+                }),
+                ..Default::default()
+            }
+            .into(),
+
+            // --- Append final captions to the transcript log ---
+            CheckmarkItem {
+                label: "Log Transcript".to_string(),
+                checked: self.transcript.is_enabled(),
+                activate: Box::new(|tray: &mut TrayState| {
+                    tray.toggle_transcript();
+                }),
+                ..Default::default()
+            }
+            .into(),
+
+            // --- Scrollback of the session's captions ---
+            StandardItem {
+                label: "Caption History...".to_string(),
+                activate: Box::new(|tray: &mut TrayState| {
+                    let _ = tray.overlay_tx.send(OverlayCommand::ShowHistory);
+                }),
+                ..Default::default()
+            }
+            .into(),
+
             MenuItem::Separator,
 
             // --- Audio Source submenu ---
             SubMenu {
                 label: "Audio Source".to_string(),
-                submenu: build_audio_source_submenu(&self.active_source, &nodes),
+                submenu: build_audio_source_submenu(&self.active_sources, &nodes, self.following.as_deref()),
                 ..Default::default()
             }
             .into(),
@@ -369,65 +555,90 @@ impl Tray for TrayState {
 }
 
 fn build_audio_source_submenu(
-    active: &AudioSource,
+    active: &[SourceConfig],
     nodes: &[AudioNode],
+    following: Option<&str>,
 ) -> Vec<MenuItem<TrayState>> {
+    // Each source is a checkmark: ticked sources are captured together and mixed.
+    // A node is ticked when a selected source resolves to it, whatever ID it was saved under.
+    let is_active = |node: &AudioNode| {
+        active
+            .iter()
+            .any(|s| crate::audio::find_node(&s.source, nodes).is_some_and(|n| n.node_id == node.node_id))
+    };
+
     // System output is always the first option (AC4.3).
-    let system_selected = matches!(active, AudioSource::SystemOutput);
-
-    let items: Vec<MenuItem<TrayState>> = vec![RadioGroup {
-        selected: if system_selected { 0 } else {
-            nodes.iter().position(|n| {
-                if let AudioSource::Application { node_id, .. } = active {
-                    n.node_id == *node_id
-                } else {
-                    false
-                }
-            })
-            .map(|i| i + 1)
-            .unwrap_or(0)
-        },
-        select: Box::new(|tray: &mut TrayState, idx: usize| {
-            let nodes = tray.node_list.lock().unwrap().clone();
-            let new_source = if idx == 0 {
-                AudioSource::SystemOutput
-            } else if let Some(node) = nodes.get(idx - 1) {
-                AudioSource::Application {
-                    node_id: node.node_id,
-                    node_name: node.name.clone(),
-                }
-            } else {
-                AudioSource::SystemOutput
-            };
-            tray.active_source = new_source.clone();
-            let _ = tray.audio_tx.send(AudioCommand::SwitchSource(new_source.clone()));
-            // Persist audio source change to config.
-            // Note: load-modify-save pattern has a theoretical race if multiple tray actions fire simultaneously. Acceptable for single-user desktop app.
-            let mut cfg = crate::config::Config::load();
-            cfg.audio_source = tray.active_source.clone();
-            if let Err(e) = cfg.save() {
-                eprintln!("warn: failed to save config: {e}");
-            }
+    let mut items: Vec<MenuItem<TrayState>> = vec![CheckmarkItem {
+        label: "System Output".to_string(),
+        checked: active.iter().any(|s| s.source == AudioSource::SystemOutput),
+        activate: Box::new(|tray: &mut TrayState| {
+            tray.toggle_source(AudioSource::SystemOutput);
         }),
-        options: {
-            let mut opts = vec![RadioItem {
-                label: "System Output".to_string(),
-                enabled: true,
+        ..Default::default()
+    }
+    .into()];
+
+    // Auto follows whichever application is playing; show which one while it is ticked.
+    let auto = active.iter().any(|s| s.source == AudioSource::Auto);
+    items.push(
+        CheckmarkItem {
+            label: match following {
+                Some(app) if auto => format!("Auto (following {app})"),
+                _ => "Auto (follow playing app)".to_string(),
+            },
+            checked: auto,
+            activate: Box::new(|tray: &mut TrayState| {
+                tray.toggle_source(AudioSource::Auto);
+            }),
+            ..Default::default()
+        }
+        .into(),
+    );
+
+    for node in nodes {
+        // Disambiguate duplicate names with PID (PipeWire node ID).
+        // Capture devices are prefixed so they stand out from app streams.
+        let label = match node.kind {
+            NodeKind::Input => format!("Input: {} (id:{})", node.description, node.node_id),
+            NodeKind::Monitor | NodeKind::Application => {
+                format!("{} (id:{})", node.description, node.node_id)
+            }
+        };
+        let source = node.to_source();
+        items.push(
+            CheckmarkItem {
+                label,
+                checked: is_active(node),
+                activate: Box::new(move |tray: &mut TrayState| {
+                    tray.toggle_source(source.clone());
+                }),
                 ..Default::default()
-            }];
-            for node in nodes {
-                // Disambiguate duplicate names with PID (PipeWire node ID).
-                let label = format!("{} (id:{})", node.description, node.node_id);
-                opts.push(RadioItem {
-                    label,
-                    enabled: true,
-                    ..Default::default()
-                });
             }
-            opts
-        },
+            .into(),
+        );
+    }
+
+    // Selected sources that are not running: still ticked, so they can be deselected.
+    for entry in active {
+        let Some(name) = entry.source.node_name() else {
+            continue;
+        };
+        if crate::audio::find_node(&entry.source, nodes).is_some() {
+            continue;
+        }
+        let source = entry.source.clone();
+        items.push(
+            CheckmarkItem {
+                label: format!("{name} (waiting)"),
+                checked: true,
+                activate: Box::new(move |tray: &mut TrayState| {
+                    tray.toggle_source(source.clone());
+                }),
+                ..Default::default()
+            }
+            .into(),
+        );
     }
-    .into()];
 
     items
 }
@@ -440,6 +651,14 @@ const SIZE_PRESETS: &[(& str, i32)] = &[
     ("Extra Large (1000px)", 1000),
 ];
 
+/// Layer-shell layers for the overlay layer submenu.
+const LAYER_OPTIONS: &[(&str, OverlayLayer)] = &[
+    ("Background", OverlayLayer::Background),
+    ("Bottom", OverlayLayer::Bottom),
+    ("Top", OverlayLayer::Top),
+    ("Overlay (Above Fullscreen)", OverlayLayer::Overlay),
+];
+
 fn build_overlay_submenu(tray: &TrayState) -> Vec<MenuItem<TrayState>> {
     let is_docked = tray.overlay_mode == OverlayMode::Docked;
 
@@ -451,19 +670,23 @@ fn build_overlay_submenu(tray: &TrayState) -> Vec<MenuItem<TrayState>> {
         .position(|(_, w)| *w == current_width)
         .unwrap_or(1); // default to Medium if custom
 
+    // A theme is selected when applying it again would change nothing; a hand-edited style
+    // matches none, and no item is ticked.
+    let themes = cfg.themes();
+    let theme_idx = themes
+        .iter()
+        .position(|(_, _, theme)| theme.apply(&cfg.appearance) == cfg.appearance)
+        .unwrap_or(usize::MAX);
+    let theme_labels: Vec<String> = themes.iter().map(|(_, label, _)| label.clone()).collect();
+    let layer_idx = LAYER_OPTIONS.iter().position(|(_, l)| *l == cfg.layer).unwrap_or(2);
+
     vec![
         // Docked / Floating radio.
         RadioGroup {
             selected: if is_docked { 0 } else { 1 },
             select: Box::new(|tray: &mut TrayState, idx: usize| {
                 let mode = if idx == 0 { OverlayMode::Docked } else { OverlayMode::Floating };
-                tray.overlay_mode = mode.clone();
-                let _ = tray.overlay_tx.send(OverlayCommand::SetMode(mode.clone()));
-                let mut cfg = crate::config::Config::load();
-                cfg.overlay_mode = tray.overlay_mode.clone();
-                if let Err(e) = cfg.save() {
-                    eprintln!("warn: failed to save config: {e}");
-                }
+                tray.set_overlay_mode(mode);
             }),
             options: vec![
                 RadioItem { label: "Docked".to_string(), enabled: true, ..Default::default() },
@@ -503,6 +726,87 @@ fn build_overlay_submenu(tray: &TrayState) -> Vec<MenuItem<TrayState>> {
         }
         .into(),
 
+        // Style presets: built-in themes and [themes.<name>] from the config.
+        SubMenu {
+            label: "Style".to_string(),
+            submenu: vec![RadioGroup {
+                selected: theme_idx,
+                select: Box::new(move |tray: &mut TrayState, idx: usize| {
+                    let Some((_, _, theme)) = themes.get(idx) else {
+                        return;
+                    };
+                    let mut cfg = crate::config::Config::load();
+                    cfg.appearance = theme.apply(&cfg.appearance);
+                    let appearance = cfg.appearance.clone();
+                    if let Err(e) = cfg.save() {
+                        eprintln!("warn: failed to save config: {e}");
+                    }
+                    let _ = tray.overlay_tx.send(OverlayCommand::UpdateAppearance(appearance));
+                }),
+                options: theme_labels
+                    .into_iter()
+                    .map(|label| RadioItem { label, enabled: true, ..Default::default() })
+                    .collect(),
+            }
+            .into()],
+            ..Default::default()
+        }
+        .into(),
+
+        // Output the overlay is shown on.
+        SubMenu {
+            label: "Monitor".to_string(),
+            submenu: build_monitor_submenu(&tray.monitors, tray.monitor.as_deref()),
+            ..Default::default()
+        }
+        .into(),
+
+        // Layer-shell layer; the top layer is hidden by fullscreen windows on some compositors.
+        SubMenu {
+            label: "Layer".to_string(),
+            submenu: vec![RadioGroup {
+                selected: layer_idx,
+                select: Box::new(|tray: &mut TrayState, idx: usize| {
+                    let Some((_, layer)) = LAYER_OPTIONS.get(idx) else {
+                        return;
+                    };
+                    let mut cfg = crate::config::Config::load();
+                    cfg.layer = *layer;
+                    if let Err(e) = cfg.save() {
+                        eprintln!("warn: failed to save config: {e}");
+                    }
+                    let _ = tray.overlay_tx.send(OverlayCommand::SetLayer(*layer));
+                }),
+                options: LAYER_OPTIONS
+                    .iter()
+                    .map(|(label, _)| RadioItem {
+                        label: label.to_string(),
+                        enabled: true,
+                        ..Default::default()
+                    })
+                    .collect(),
+            }
+            .into()],
+            ..Default::default()
+        }
+        .into(),
+
+        CheckmarkItem {
+            label: "Hide Over Fullscreen Windows".to_string(),
+            checked: cfg.auto_hide_fullscreen,
+            activate: Box::new(|tray: &mut TrayState| {
+                let mut cfg = crate::config::Config::load();
+                cfg.auto_hide_fullscreen = !cfg.auto_hide_fullscreen;
+                let enabled = cfg.auto_hide_fullscreen;
+                if let Err(e) = cfg.save() {
+                    eprintln!("warn: failed to save config: {e}");
+                }
+                let _ = tray.overlay_tx.send(OverlayCommand::SetAutoHideFullscreen(enabled));
+            }),
+            ..Default::default()
+        }
+        .into(),
+
         MenuItem::Separator,
 
         // Lock overlay position (disabled in docked mode) — AC4.5.
@@ -511,15 +815,7 @@ fn build_overlay_submenu(tray: &TrayState) -> Vec<MenuItem<TrayState>> {
             checked: tray.locked,
             enabled: !is_docked, // greyed out in docked mode (AC4.5)
             activate: Box::new(|tray: &mut TrayState| {
-                if tray.overlay_mode == OverlayMode::Floating {
-                    tray.locked = !tray.locked;
-                    let _ = tray.overlay_tx.send(OverlayCommand::SetLocked(tray.locked));
-                    let mut cfg = crate::config::Config::load();
-                    cfg.locked = tray.locked;
-                    if let Err(e) = cfg.save() {
-                        eprintln!("warn: failed to save config: {e}");
-                    }
-                }
+                tray.set_locked(!tray.locked);
             }),
             ..Default::default()
         }
@@ -527,20 +823,39 @@ fn build_overlay_submenu(tray: &TrayState) -> Vec<MenuItem<TrayState>> {
     ]
 }
 
+/// "Automatic" followed by the connected monitors. A configured monitor that is unplugged
+/// stays listed, so the choice remains visible until it returns.
+fn build_monitor_submenu(monitors: &[MonitorInfo], selected: Option<&str>) -> Vec<MenuItem<TrayState>> {
+    let mut choices: Vec<(Option<String>, String)> = vec![(None, "Automatic".to_string())];
+    choices.extend(monitors.iter().map(|m| (Some(m.connector.clone()), m.label())));
+    if let Some(connector) = selected {
+        if !monitors.iter().any(|m| m.connector == connector) {
+            choices.push((Some(connector.to_string()), format!("{connector} (disconnected)")));
+        }
+    }
+    let selected_idx = choices.iter().position(|(c, _)| c.as_deref() == selected).unwrap_or(0);
+    let options = choices
+        .iter()
+        .map(|(_, label)| RadioItem { label: label.clone(), enabled: true, ..Default::default() })
+        .collect();
+    vec![RadioGroup {
+        selected: selected_idx,
+        select: Box::new(move |tray: &mut TrayState, idx: usize| {
+            if let Some((connector, _)) = choices.get(idx) {
+                tray.set_monitor(connector.clone());
+            }
+        }),
+        options,
+    }
+    .into()]
+}
+
 #[allow(dead_code)]
 fn build_engine_submenu(_active: &Engine) -> Vec<MenuItem<TrayState>> {
     vec![RadioGroup {
         selected: 0, // Only Nemotron is available
         select: Box::new(|tray: &mut TrayState, _idx: usize| {
-            let engine = Engine::Nemotron;
-            tray.active_engine = engine.clone();
-            let _ = tray.engine_tx.send(EngineCommand::Switch(engine.clone()));
-            // Note: load-modify-save pattern has a theoretical race if multiple tray actions fire simultaneously. Acceptable for single-user desktop app.
-            let mut cfg = crate::config::Config::load();
-            cfg.engine = tray.active_engine.clone();
-            if let Err(e) = cfg.save() {
-                eprintln!("warn: failed to save config: {e}");
-            }
+            tray.set_engine(Engine::Nemotron);
         }),
         options: vec![
             RadioItem {
@@ -579,14 +894,23 @@ mod tests {
 
         let tray = TrayState {
             captions_enabled: Arc::new(AtomicBool::new(true)),
-            active_source: AudioSource::SystemOutput,
+            active_sources: vec![SourceConfig::default()],
+            following: None,
             overlay_mode: OverlayMode::Docked,
             locked: false,
+            monitors: Vec::new(),
+            monitor: None,
             active_engine: Engine::Nemotron,
             overlay_tx,
             audio_tx,
             engine_tx,
             node_list: Arc::new(std::sync::Mutex::new(vec![])),
+            recorder: crate::subtitles::Recorder::new(std::time::Instant::now()),
+            transcript: crate::transcript::TranscriptLog::new(
+                std::time::Instant::now(),
+                time::UtcOffset::UTC,
+                &Default::default(),
+            ),
         };
 
         // The build_overlay_submenu function is responsible for ensuring
@@ -611,14 +935,23 @@ mod tests {
 
         let tray = TrayState {
             captions_enabled: Arc::new(AtomicBool::new(true)),
-            active_source: AudioSource::SystemOutput,
+            active_sources: vec![SourceConfig::default()],
+            following: None,
             overlay_mode: OverlayMode::Floating,
             locked: false,
+            monitors: Vec::new(),
+            monitor: None,
             active_engine: Engine::Nemotron,
             overlay_tx,
             audio_tx,
             engine_tx,
             node_list: Arc::new(std::sync::Mutex::new(vec![])),
+            recorder: crate::subtitles::Recorder::new(std::time::Instant::now()),
+            transcript: crate::transcript::TranscriptLog::new(
+                std::time::Instant::now(),
+                time::UtcOffset::UTC,
+                &Default::default(),
+            ),
         };
 
         // The build_overlay_submenu function is responsible for enabling
@@ -642,14 +975,23 @@ mod tests {
 
         let tray = TrayState {
             captions_enabled: Arc::new(AtomicBool::new(true)),
-            active_source: AudioSource::SystemOutput,
+            active_sources: vec![SourceConfig::default()],
+            following: None,
             overlay_mode: OverlayMode::Docked,
             locked: true,
+            monitors: Vec::new(),
+            monitor: None,
             active_engine: Engine::Nemotron,
             overlay_tx,
             audio_tx,
             engine_tx,
             node_list: Arc::new(std::sync::Mutex::new(vec![])),
+            recorder: crate::subtitles::Recorder::new(std::time::Instant::now()),
+            transcript: crate::transcript::TranscriptLog::new(
+                std::time::Instant::now(),
+                time::UtcOffset::UTC,
+                &Default::default(),
+            ),
         };
 
         let menu_items = tray.menu();
@@ -673,4 +1015,61 @@ mod tests {
             "Menu should have expected items (Captions, separators, submenus, Settings, Quit)"
         );
     }
+
+    #[test]
+    fn source_submenu_lists_waiting_sources_as_ticked() {
+        let firefox = AudioSource::Application {
+            node_id: 7,
+            node_name: "Firefox".to_string(),
+            app_name: Some("Firefox".to_string()),
+            binary: Some("firefox".to_string()),
+        };
+        let active = vec![SourceConfig::new(firefox)];
+        let items = build_audio_source_submenu(&active, &[], None);
+        let labels: Vec<(String, bool)> = items
+            .iter()
+            .filter_map(|item| match item {
+                MenuItem::Checkmark(c) => Some((c.label.clone(), c.checked)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                ("System Output".to_string(), false),
+                ("Auto (follow playing app)".to_string(), false),
+                ("Firefox (waiting)".to_string(), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn source_submenu_shows_followed_app() {
+        let active = vec![SourceConfig::new(AudioSource::Auto)];
+        let items = build_audio_source_submenu(&active, &[], Some("Firefox"));
+        let auto = items.iter().find_map(|item| match item {
+            MenuItem::Checkmark(c) if c.label.starts_with("Auto") => Some((c.label.clone(), c.checked)),
+            _ => None,
+        });
+        assert_eq!(auto, Some(("Auto (following Firefox)".to_string(), true)));
+    }
+
+    #[test]
+    fn monitor_menu_keeps_disconnected_choice() {
+        let monitors = vec![MonitorInfo { connector: "DP-1".to_string(), description: "Dell U2720Q".to_string() }];
+        let menu = build_monitor_submenu(&monitors, Some("HDMI-A-1"));
+        let MenuItem::RadioGroup(group) = &menu[0] else {
+            panic!("monitor submenu should be a radio group");
+        };
+        let labels: Vec<&str> = group.options.iter().map(|o| o.label.as_str()).collect();
+        assert_eq!(labels, vec!["Automatic", "Dell U2720Q (DP-1)", "HDMI-A-1 (disconnected)"]);
+        assert_eq!(group.selected, 2);
+
+        let menu = build_monitor_submenu(&monitors, None);
+        let MenuItem::RadioGroup(group) = &menu[0] else {
+            panic!("monitor submenu should be a radio group");
+        };
+        assert_eq!(group.options.len(), 2);
+        assert_eq!(group.selected, 0);
+    }
 }
@@ -0,0 +1,399 @@
+//! Local caption server for OBS browser sources.
+//!
+//! Listens on 127.0.0.1 only. `GET /` serves a built-in page that renders captions with the
+//! configured appearance; a WebSocket upgrade on any path streams JSON events: every caption
+//! fragment as it is recognized, and the overlay's lines whenever they change. A client that
+//! connects late first receives the current appearance, visibility and lines.
+//!
+//! Browsers let any page open a WebSocket to localhost, so upgrades carrying an `Origin`
+//! other than this server's own page are refused. Clients that send no `Origin` (OBS,
+//! command-line tools) are allowed.
+
+use crate::config::AppearanceConfig;
+use crate::stt::{samples_to_ms, Caption, TokenConfidence};
+use anyhow::{Context, Result};
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::broadcast;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::{header, StatusCode};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+static INDEX_HTML: &str = include_str!("../assets/web/index.html");
+
+/// Events a slow client may fall behind by before it skips ahead.
+const EVENT_BACKLOG: usize = 256;
+/// How long a client may take to send its request headers.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A line as the overlay shows it.
+#[derive(Serialize)]
+pub struct Line {
+    pub text: String,
+    /// Partial hypothesis of the line's speaker, shown after their last line until final.
+    pub partial: Option<String>,
+}
+
+/// One JSON message on the WebSocket stream.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Event<'a> {
+    /// A recognized fragment. Partial hypotheses (`is_final` false) are replaced by the
+    /// next fragment from the same source.
+    Caption {
+        text: &'a str,
+        /// Speaker label, null when sources are mixed.
+        source: Option<&'a str>,
+        is_final: bool,
+        start_ms: u64,
+        end_ms: u64,
+        /// Per-token confidence, null when the engine does not report it.
+        tokens: Option<&'a [TokenConfidence]>,
+    },
+    /// The lines the overlay is showing.
+    Lines { lines: &'a [Line] },
+    Appearance {
+        background_color: &'a str,
+        text_color: &'a str,
+        font_size: f32,
+        max_lines: u32,
+    },
+    Visible { visible: bool },
+}
+
+impl Event<'_> {
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("caption events always serialize")
+    }
+}
+
+/// Latest state events, replayed to clients as they connect.
+struct Snapshot {
+    appearance: String,
+    visible: String,
+    lines: String,
+}
+
+struct Hub {
+    events: broadcast::Sender<String>,
+    snapshot: Mutex<Snapshot>,
+}
+
+impl Hub {
+    fn new(appearance: &AppearanceConfig) -> Self {
+        Hub {
+            events: broadcast::channel(EVENT_BACKLOG).0,
+            snapshot: Mutex::new(Snapshot {
+                appearance: appearance_event(appearance).to_json(),
+                visible: Event::Visible { visible: true }.to_json(),
+                lines: Event::Lines { lines: &[] }.to_json(),
+            }),
+        }
+    }
+
+    fn broadcast(&self, json: String) {
+        // Fails only when no client is connected.
+        let _ = self.events.send(json);
+    }
+
+    /// Broadcast a state event and keep it for clients that connect later. Unchanged
+    /// state is not re-sent.
+    fn update(&self, json: String, slot: impl FnOnce(&mut Snapshot) -> &mut String) {
+        let mut snapshot = self.snapshot.lock().unwrap();
+        let current = slot(&mut snapshot);
+        if *current != json {
+            *current = json.clone();
+            self.broadcast(json);
+        }
+    }
+
+    fn initial_events(&self) -> Vec<String> {
+        let snapshot = self.snapshot.lock().unwrap();
+        vec![snapshot.appearance.clone(), snapshot.visible.clone(), snapshot.lines.clone()]
+    }
+}
+
+fn caption_event(caption: &Caption) -> Event<'_> {
+    Event::Caption {
+        text: &caption.text,
+        source: caption.source.as_ref().map(|s| s.label.as_str()),
+        is_final: caption.is_final,
+        start_ms: samples_to_ms(caption.start_sample),
+        end_ms: samples_to_ms(caption.end_sample),
+        tokens: caption.tokens.as_deref(),
+    }
+}
+
+fn appearance_event(appearance: &AppearanceConfig) -> Event<'_> {
+    Event::Appearance {
+        background_color: &appearance.background_color,
+        text_color: &appearance.text_color,
+        font_size: appearance.font_size,
+        max_lines: appearance.max_lines,
+    }
+}
+
+/// Handle for publishing to connected clients. Cheap to clone.
+#[derive(Clone)]
+pub struct WebServer {
+    hub: Arc<Hub>,
+}
+
+impl WebServer {
+    /// Send a caption fragment as it arrives from the recognizer.
+    pub fn publish_caption(&self, caption: &Caption) {
+        self.hub.broadcast(caption_event(caption).to_json());
+    }
+
+    /// Send the overlay's current lines.
+    pub fn publish_lines(&self, lines: &[Line]) {
+        self.hub.update(Event::Lines { lines }.to_json(), |s| &mut s.lines);
+    }
+
+    pub fn publish_appearance(&self, appearance: &AppearanceConfig) {
+        self.hub.update(appearance_event(appearance).to_json(), |s| &mut s.appearance);
+    }
+
+    pub fn publish_visible(&self, visible: bool) {
+        self.hub.update(Event::Visible { visible }.to_json(), |s| &mut s.visible);
+    }
+}
+
+/// Start serving on 127.0.0.1:`port`.
+pub fn start(runtime: &tokio::runtime::Runtime, port: u16, appearance: &AppearanceConfig) -> Result<WebServer> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    let listener = std::net::TcpListener::bind(addr).with_context(|| format!("binding {addr}"))?;
+    listener.set_nonblocking(true)?;
+    let port = listener.local_addr()?.port();
+    let listener = {
+        let _guard = runtime.enter();
+        tokio::net::TcpListener::from_std(listener)?
+    };
+
+    let hub = Arc::new(Hub::new(appearance));
+    let server = WebServer { hub: Arc::clone(&hub) };
+    runtime.spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(handle_connection(stream, port, Arc::clone(&hub)));
+                }
+                Err(e) => eprintln!("warn: caption server: accept failed: {e}"),
+            }
+        }
+    });
+    eprintln!("info: caption page at http://{addr}/");
+    Ok(server)
+}
+
+/// What a client asked for, judged from its request head.
+#[derive(Debug, PartialEq)]
+enum Route {
+    WebSocket,
+    Page,
+    NotFound,
+}
+
+fn route(head: &str) -> Route {
+    let mut lines = head.lines();
+    let path = lines.next().and_then(|l| l.split_whitespace().nth(1)).unwrap_or("");
+    let upgrade = lines.any(|l| {
+        l.split_once(':').is_some_and(|(name, value)| {
+            name.trim().eq_ignore_ascii_case("upgrade") && value.trim().eq_ignore_ascii_case("websocket")
+        })
+    });
+    match path {
+        _ if upgrade => Route::WebSocket,
+        "/" | "/index.html" => Route::Page,
+        _ => Route::NotFound,
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, port: u16, hub: Arc<Hub>) {
+    // Peek, so the WebSocket handshake can still read the request itself.
+    let mut head = [0u8; 4096];
+    let n = match tokio::time::timeout(REQUEST_TIMEOUT, stream.peek(&mut head)).await {
+        Ok(Ok(n)) if n > 0 => n,
+        _ => return,
+    };
+    let route = route(&String::from_utf8_lossy(&head[..n]));
+    if route == Route::WebSocket {
+        stream_events(stream, port, hub).await;
+        return;
+    }
+
+    // Consume the request so closing the connection does not reset it under the reply.
+    let _ = stream.read(&mut head).await;
+    let (status, content_type, body) = match route {
+        Route::Page => ("200 OK", "text/html; charset=utf-8", INDEX_HTML),
+        _ => ("404 Not Found", "text/plain; charset=utf-8", "not found\n"),
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    if stream.write_all(response.as_bytes()).await.is_ok() {
+        let _ = stream.shutdown().await;
+    }
+}
+
+/// Whether a WebSocket upgrade with this `Origin` header may read captions: only the
+/// built-in page served from this port, or a client that sends no `Origin` at all.
+fn origin_allowed(origin: Option<&str>, port: u16) -> bool {
+    match origin {
+        None => true,
+        Some(origin) => {
+            origin == format!("http://127.0.0.1:{port}") || origin == format!("http://localhost:{port}")
+        }
+    }
+}
+
+/// Complete the WebSocket handshake, refusing upgrades from foreign origins with 403.
+// The callback's error type is tungstenite's HTTP response; it is not ours to shrink.
+#[allow(clippy::result_large_err)]
+async fn accept<S>(stream: S, port: u16) -> Result<WebSocketStream<S>, tokio_tungstenite::tungstenite::Error>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    tokio_tungstenite::accept_hdr_async(stream, move |request: &Request, response: Response| {
+        // A header that is not valid text counts as foreign.
+        let origin = request.headers().get(header::ORIGIN).map(|v| v.to_str().unwrap_or(""));
+        if origin_allowed(origin, port) {
+            return Ok(response);
+        }
+        let mut rejection = ErrorResponse::new(Some("origin not allowed\n".to_string()));
+        *rejection.status_mut() = StatusCode::FORBIDDEN;
+        Err(rejection)
+    })
+    .await
+}
+
+/// Run one WebSocket client until it disconnects.
+async fn stream_events(stream: TcpStream, port: u16, hub: Arc<Hub>) {
+    let ws = match accept(stream, port).await {
+        Ok(ws) => ws,
+        Err(e) => {
+            eprintln!("warn: caption server: WebSocket handshake failed: {e}");
+            return;
+        }
+    };
+    let (mut outgoing, mut incoming) = ws.split();
+    // Subscribe before taking the snapshot, so no update falls in between.
+    let mut events = hub.events.subscribe();
+    for json in hub.initial_events() {
+        if outgoing.send(Message::text(json)).await.is_err() {
+            return;
+        }
+    }
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(json) => {
+                    if outgoing.send(Message::text(json)).await.is_err() {
+                        return;
+                    }
+                }
+                // A client too slow to keep up skips what it missed.
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            // Clients have nothing to say; just notice when they leave. Pings are answered
+            // by the WebSocket layer.
+            message = incoming.next() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stt::SAMPLES_PER_SEC;
+
+    #[test]
+    fn requests_are_routed_by_path_and_upgrade_header() {
+        let ws = "GET /stream HTTP/1.1\r\nHost: localhost\r\nUpgrade: WebSocket\r\nConnection: Upgrade\r\n\r\n";
+        assert_eq!(route(ws), Route::WebSocket);
+        assert_eq!(route("GET / HTTP/1.1\r\nHost: localhost\r\n\r\n"), Route::Page);
+        assert_eq!(route("GET /index.html HTTP/1.1\r\n\r\n"), Route::Page);
+        assert_eq!(route("GET /favicon.ico HTTP/1.1\r\n\r\n"), Route::NotFound);
+        assert_eq!(route(""), Route::NotFound);
+    }
+
+    #[test]
+    fn only_missing_or_own_origins_are_allowed() {
+        assert!(origin_allowed(None, 8765));
+        assert!(origin_allowed(Some("http://127.0.0.1:8765"), 8765));
+        assert!(origin_allowed(Some("http://localhost:8765"), 8765));
+        assert!(!origin_allowed(Some("http://localhost:8766"), 8765));
+        assert!(!origin_allowed(Some("https://example.com"), 8765));
+        assert!(!origin_allowed(Some("null"), 8765));
+        assert!(!origin_allowed(Some(""), 8765));
+    }
+
+    #[tokio::test]
+    async fn upgrade_from_foreign_origin_is_rejected() {
+        use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+        async fn handshake(origin: Option<&str>) -> bool {
+            let (client, server) = tokio::io::duplex(4096);
+            let mut request = "ws://127.0.0.1:8765/".into_client_request().unwrap();
+            if let Some(origin) = origin {
+                request.headers_mut().insert(header::ORIGIN, origin.parse().unwrap());
+            }
+            let (server, client) =
+                tokio::join!(accept(server, 8765), tokio_tungstenite::client_async(request, client));
+            assert_eq!(server.is_ok(), client.is_ok());
+            server.is_ok()
+        }
+
+        assert!(!handshake(Some("https://example.com")).await);
+        assert!(handshake(Some("http://127.0.0.1:8765")).await);
+        assert!(handshake(None).await);
+    }
+
+    #[test]
+    fn caption_event_json() {
+        let caption = Caption { is_final: false, ..Caption::test_final(SAMPLES_PER_SEC, " hello", Some("Me")) };
+        assert_eq!(
+            caption_event(&caption).to_json(),
+            r#"{"type":"caption","text":" hello","source":"Me","is_final":false,"start_ms":1000,"end_ms":1500,"tokens":null}"#
+        );
+        let tokens = vec![TokenConfidence { text: " hello".to_string(), confidence: 0.5 }];
+        let caption = Caption { tokens: Some(tokens), ..caption };
+        assert!(caption_event(&caption).to_json().ends_with(r#""tokens":[{"text":" hello","confidence":0.5}]}"#));
+    }
+
+    #[test]
+    fn late_clients_get_latest_state_and_unchanged_state_is_not_resent() {
+        let server = WebServer { hub: Arc::new(Hub::new(&AppearanceConfig::default())) };
+        let mut events = server.hub.events.subscribe();
+
+        let lines = vec![Line { text: "first line".to_string(), partial: Some("sec".to_string()) }];
+        server.publish_lines(&lines);
+        server.publish_lines(&lines);
+        server.publish_visible(false);
+
+        let sent: Vec<String> = std::iter::from_fn(|| events.try_recv().ok()).collect();
+        assert_eq!(
+            sent,
+            vec![
+                r#"{"type":"lines","lines":[{"text":"first line","partial":"sec"}]}"#.to_string(),
+                r#"{"type":"visible","visible":false}"#.to_string(),
+            ]
+        );
+        // A client connecting now starts from the appearance, visibility and lines.
+        let initial = server.hub.initial_events();
+        assert!(initial[0].starts_with(r#"{"type":"appearance","background_color":"rgba(0,0,0,0.7)""#));
+        assert_eq!(initial[1], sent[1]);
+        assert_eq!(initial[2], sent[0]);
+    }
+}